@@ -0,0 +1,308 @@
+#!/usr/bin/env python3
+# 生成 CjkSubset.ttf：只有“中文字体第二章”几个汉字的最小 TrueType 字体，
+# 内嵌在默认字体链的最后，找不到系统中文字体时窗口标题和演示文字也能显示，测试和黄金图像也用它。
+# 字形是在 16x16 的网格上用矩形笔画拼出来的方块字，斜笔画用台阶近似，不追求美观，只要能认出来。
+# 字形是本仓库原创的，这个脚本和生成的字体都以 CC0 发布，可以随意使用。
+# 用法：python3 assets/fonts/make_cjk_subset.py，在同一目录下重新生成 CjkSubset.ttf
+
+import os
+import struct
+
+UNITS_PER_EM = 1024
+CELL = 64
+ASCENDER = 880
+DESCENDER = -144
+FAMILY = "MyWgpu CJK Subset"
+
+
+def stairs(x, y, steps, dx, width=2):
+    # 从 (x, y) 开始每往下一格横向移动 dx 格的斜笔画
+    return [(x + i * dx, y + i, x + i * dx + width, y + i + 1) for i in range(steps)]
+
+
+# 每个矩形是 (左列, 上行, 右列, 下行)，右边和下边不包含在内
+GLYPHS = {
+    "中": [
+        (2, 4, 14, 6),
+        (2, 10, 14, 12),
+        (2, 4, 4, 12),
+        (12, 4, 14, 12),
+        (7, 1, 9, 15),
+    ],
+    "文": [(7, 1, 9, 3), (1, 4, 15, 6)] + stairs(4, 6, 8, 1) + stairs(10, 6, 8, -1),
+    "字": [
+        (7, 0, 9, 2),
+        (2, 2, 14, 4),
+        (2, 2, 4, 5),
+        (12, 2, 14, 5),
+        (4, 6, 12, 8),
+        (9, 8, 11, 9),
+        (1, 10, 15, 12),
+        (7, 8, 9, 15),
+        (5, 13, 7, 15),
+    ],
+    "体": [(4, 1, 6, 2), (3, 2, 5, 4), (2, 4, 4, 6), (3, 5, 5, 15), (6, 4, 16, 6), (10, 1, 12, 15), (8, 12, 14, 13)]
+    + stairs(8, 6, 6, -0.5)
+    + stairs(12, 6, 6, 0.5),
+    "第": [
+        (3, 0, 5, 1),
+        (2, 1, 8, 2),
+        (5, 2, 6, 4),
+        (10, 0, 12, 1),
+        (9, 1, 15, 2),
+        (12, 2, 13, 4),
+        (3, 4, 13, 5),
+        (12, 4, 13, 8),
+        (3, 7, 13, 8),
+        (3, 7, 4, 10),
+        (3, 10, 14, 11),
+        (13, 10, 14, 14),
+        (11, 14, 14, 15),
+        (7, 4, 9, 16),
+    ]
+    + stairs(5, 11, 4, -1),
+    "二": [(3, 3, 13, 5), (1, 11, 15, 13)],
+    "章": [
+        (7, 0, 9, 2),
+        (3, 2, 13, 3),
+        (5, 3, 7, 5),
+        (9, 3, 11, 5),
+        (1, 5, 15, 6),
+        (3, 7, 13, 8),
+        (3, 7, 4, 12),
+        (12, 7, 13, 12),
+        (3, 9, 13, 10),
+        (3, 11, 13, 12),
+        (1, 13, 15, 14),
+        (7, 12, 9, 16),
+    ],
+}
+
+
+def contour(rect):
+    # TrueType 的外轮廓是顺时针的，y 轴向上
+    left, top, right, bottom = rect
+    x0, x1 = round(left * CELL), round(right * CELL)
+    y0, y1 = ASCENDER - round(bottom * CELL), ASCENDER - round(top * CELL)
+    return [(x0, y0), (x0, y1), (x1, y1), (x1, y0)]
+
+
+def glyph_data(rects):
+    if not rects:
+        return b"", (0, 0, 0, 0)
+    contours = [contour(r) for r in rects]
+    points = [p for c in contours for p in c]
+    xs = [p[0] for p in points]
+    ys = [p[1] for p in points]
+    bbox = (min(xs), min(ys), max(xs), max(ys))
+    data = struct.pack(">hhhhh", len(contours), *bbox)
+    end = -1
+    for c in contours:
+        end += len(c)
+        data += struct.pack(">H", end)
+    data += struct.pack(">H", 0)
+    # 所有点都在曲线上，坐标都用两个字节的差值
+    data += bytes([0x01] * len(points))
+    last = 0
+    for x, _ in points:
+        data += struct.pack(">h", x - last)
+        last = x
+    last = 0
+    for _, y in points:
+        data += struct.pack(">h", y - last)
+        last = y
+    if len(data) % 4:
+        data += b"\0" * (4 - len(data) % 4)
+    return data, bbox
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(f">{len(data) // 4}I", data)) & 0xFFFFFFFF
+
+
+def cmap_table(chars):
+    # 格式 4，每个字符一个区段，最后是 0xFFFF 的结束区段
+    ends = [ord(c) for c in chars] + [0xFFFF]
+    starts = ends[:]
+    deltas = [(gid - code) & 0xFFFF for gid, code in enumerate(ends[:-1], start=1)] + [1]
+    seg_x2 = len(ends) * 2
+    search = 2 ** (len(ends).bit_length() - 1) * 2
+    sub = struct.pack(
+        ">HHHHHHH",
+        4,
+        0,
+        0,
+        seg_x2,
+        search,
+        (search // 2).bit_length() - 1,
+        seg_x2 - search,
+    )
+    sub += struct.pack(f">{len(ends)}H", *ends) + b"\0\0"
+    sub += struct.pack(f">{len(starts)}H", *starts)
+    sub += struct.pack(f">{len(deltas)}H", *deltas)
+    sub += struct.pack(f">{len(ends)}H", *([0] * len(ends)))
+    sub = sub[:2] + struct.pack(">H", len(sub)) + sub[4:]
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + sub
+
+
+def name_table():
+    names = {1: FAMILY, 2: "Regular", 4: FAMILY, 6: FAMILY.replace(" ", "")}
+    records = b""
+    strings = b""
+    for name_id, text in names.items():
+        encoded = text.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    return struct.pack(">HHH", 0, len(names), 6 + len(records)) + records + strings
+
+
+def build():
+    chars = sorted(GLYPHS)
+    glyphs = [glyph_data([])] + [glyph_data(GLYPHS[c]) for c in chars]
+    glyf = b""
+    offsets = []
+    for data, _ in glyphs:
+        offsets.append(len(glyf))
+        glyf += data
+    offsets.append(len(glyf))
+    loca = struct.pack(f">{len(offsets)}I", *offsets)
+    boxes = [bbox for data, bbox in glyphs if data]
+    x_min = min(b[0] for b in boxes)
+    y_min = min(b[1] for b in boxes)
+    x_max = max(b[2] for b in boxes)
+    y_max = max(b[3] for b in boxes)
+    max_points = max(len(GLYPHS[c]) * 4 for c in chars)
+    max_contours = max(len(GLYPHS[c]) for c in chars)
+    num_glyphs = len(glyphs)
+
+    head = struct.pack(
+        ">IIIIHHqqhhhhHHhhh",
+        0x00010000,
+        0x00010000,
+        0,
+        0x5F0F3CF5,
+        0x000B,
+        UNITS_PER_EM,
+        0,
+        0,
+        x_min,
+        y_min,
+        x_max,
+        y_max,
+        0,
+        8,
+        2,
+        1,
+        0,
+    )
+    hhea = struct.pack(
+        ">IhhhHhhhhhhhhhhhH",
+        0x00010000,
+        ASCENDER,
+        DESCENDER,
+        0,
+        UNITS_PER_EM,
+        0,
+        0,
+        x_max,
+        1,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        num_glyphs,
+    )
+    hmtx = b"".join(
+        struct.pack(">Hh", UNITS_PER_EM, bbox[0] if data else 0) for data, bbox in glyphs
+    )
+    maxp = struct.pack(
+        ">IHHHHHHHHHHHHHH", 0x00010000, num_glyphs, max_points, max_contours, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0
+    )
+    codes = [ord(c) for c in chars]
+    os2 = struct.pack(
+        ">HhHHH11h10s4I4s3H3h2H2I2h3H",
+        4,
+        UNITS_PER_EM,
+        400,
+        5,
+        0,
+        # 上下标和删除线的尺寸、位置，字体族分类
+        650,
+        700,
+        0,
+        140,
+        650,
+        700,
+        0,
+        480,
+        50,
+        300,
+        0,
+        bytes(10),
+        # Unicode 区段第 59 位：中日韩统一表意文字
+        0,
+        1 << (59 - 32),
+        0,
+        0,
+        b"MYWG",
+        0x40,
+        min(codes),
+        max(codes),
+        ASCENDER,
+        DESCENDER,
+        0,
+        ASCENDER,
+        -DESCENDER,
+        # 代码页第 18 位：简体中文
+        1 << 18,
+        0,
+        500,
+        700,
+        0,
+        0x20,
+        1,
+    )
+    post = struct.pack(">IIhhIIIII", 0x00030000, 0, -100, 50, 0, 0, 0, 0, 0)
+    tables = {
+        "OS/2": os2,
+        "cmap": cmap_table(chars),
+        "glyf": glyf,
+        "head": head,
+        "hhea": hhea,
+        "hmtx": hmtx,
+        "loca": loca,
+        "maxp": maxp,
+        "name": name_table(),
+        "post": post,
+    }
+
+    tags = sorted(tables)
+    count = len(tags)
+    search = 2 ** (count.bit_length() - 1) * 16
+    font = struct.pack(">IHHHH", 0x00010000, count, search, (search // 16).bit_length() - 1, count * 16 - search)
+    offset = len(font) + 16 * count
+    directory = b""
+    body = b""
+    head_offset = 0
+    for tag in tags:
+        data = tables[tag]
+        if tag == "head":
+            head_offset = offset
+        directory += struct.pack(">4sIII", tag.encode(), checksum(data), offset, len(data))
+        padded = data + b"\0" * (-len(data) % 4)
+        body += padded
+        offset += len(padded)
+    font += directory + body
+    adjustment = (0xB1B0AFBA - checksum(font)) & 0xFFFFFFFF
+    font = font[: head_offset + 8] + struct.pack(">I", adjustment) + font[head_offset + 12 :]
+    return font
+
+
+if __name__ == "__main__":
+    path = os.path.join(os.path.dirname(os.path.abspath(__file__)), "CjkSubset.ttf")
+    with open(path, "wb") as f:
+        f.write(build())
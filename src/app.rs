@@ -0,0 +1,1163 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use glam::{Vec2, Vec3};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, ElementState, KeyEvent, MouseButton, MouseScrollDelta, TouchPhase},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{CursorGrabMode, Window},
+};
+
+use crate::camera::{Camera, CameraBinding, CameraController, OrbitController};
+use crate::compute::{Compute, ReadbackId};
+use crate::debug_draw::{DebugDraw, DebugPen};
+use crate::demo;
+use crate::depth::{DepthConfig, DepthTexture};
+use crate::exposure::{AutoExposure, AutoExposureSettings};
+use crate::hdr::{HDR_FORMAT, HdrTarget, TonemapPass, TonemapSettings};
+use crate::headless::{self, HeadlessError, HeadlessOptions, OffscreenTarget, ReadbackError};
+use crate::ibl::{Environment, Skybox};
+use crate::light::{Light, LightId, Lights};
+use crate::material::Materials;
+use crate::msaa::{self, MsaaTarget};
+use crate::particles::{EmitterId, ParticleSystem};
+use crate::pipeline::{Renderer, TargetState};
+use crate::post::{PostProcess, PostSettings};
+use crate::render_graph::{RenderGraph, TransientPool};
+use crate::scene::{Scene, SceneError};
+use crate::shader_reload::{ErrorOverlay, ShaderError, ShaderWatcher};
+use crate::shadow::ShadowSettings;
+use crate::sky::{self, ProceduralSky, SkySettings};
+use crate::sprite::{Sprite, SpriteBatch, SpriteTextureId};
+use crate::text::{TextError, TextRenderer, TextSection};
+use crate::texture::{Texture, TextureError};
+
+pub struct WgpuApp {
+    // adapter: GPU适配器，运行时查询格式支持的采样数等能力
+    pub(crate) adapter: wgpu::Adapter,
+    // 窗口相关，无窗口模式下为 None
+    pub(crate) window: Option<Arc<Window>>,
+    // surface: 展示平面，无窗口模式下为 None
+    pub(crate) surface: Option<wgpu::Surface<'static>>,
+    // offscreen: 离屏渲染目标，只在无窗口模式下存在
+    pub(crate) offscreen: Option<OffscreenTarget>,
+    // device: GPU设备
+    pub(crate) device: wgpu::Device,
+    // queue：GPU队列
+    pub(crate) queue: wgpu::Queue,
+    // config：展示平面的配置
+    pub(crate) config: wgpu::SurfaceConfiguration,
+    // size：物理尺寸
+    pub(crate) size: winit::dpi::PhysicalSize<u32>,
+    // size_changed: 尺寸是否改变
+    pub(crate) size_changed: bool,
+    // 第二章挑战内容
+    // clear_color: 清除颜色
+    pub(crate) clear_color: wgpu::Color,
+    // renderer: 渲染管线、网格和绘制列表
+    pub(crate) renderer: Renderer,
+    // shader_watcher: 着色器热重载的文件监听，只在有窗口时开启
+    pub(crate) shader_watcher: Option<ShaderWatcher>,
+    // watched_generation: 上次注册监听时管线的版本，管线没有变化时不再重复注册
+    pub(crate) watched_generation: Option<u64>,
+    // shader_error: 最近一次着色器编译错误，存在时显示错误覆盖层
+    pub(crate) shader_error: Option<ShaderError>,
+    pub(crate) error_overlay: ErrorOverlay,
+    // base_title: 显示着色器错误之前的窗口标题
+    pub(crate) base_title: Option<String>,
+    // depth: 深度缓冲区，尺寸跟随展示平面，None 表示不使用深度测试
+    pub(crate) depth: Option<DepthTexture>,
+    // sample_count: MSAA 采样数，1 表示不开启
+    pub(crate) sample_count: u32,
+    // msaa: 多重采样的颜色纹理，只在 sample_count 大于 1 时存在
+    pub(crate) msaa: Option<MsaaTarget>,
+    // hdr: 场景渲染到的 Rgba16Float 中间纹理，尺寸跟随展示平面
+    pub(crate) hdr: HdrTarget,
+    // post: 色调映射之前的后处理效果链和色调映射之后的 FXAA，用到的纹理由渲染图临时分配
+    pub(crate) post: PostProcess,
+    // tonemap: 把 hdr（或后处理的输出）映射到展示平面的色调映射通道
+    pub(crate) tonemap: TonemapPass,
+    // auto_exposure: 统计 hdr 的亮度直方图，只在色调映射开启自动曝光时运行
+    pub(crate) auto_exposure: AutoExposure,
+    // camera: 相机，宽高比跟随展示平面
+    pub(crate) camera: Camera,
+    // camera_controller: 当前的相机控制器，按 C 键在轨道和第一人称之间切换
+    pub(crate) camera_controller: CameraController,
+    // camera_binding: 相机的 uniform 缓冲区，每帧在 update() 中写入
+    pub(crate) camera_binding: CameraBinding,
+    // last_update: 上一次 update() 的时间，用来计算帧间隔
+    pub(crate) last_update: Instant,
+    // lights: 场景里的光源，可以在 update() 中增删和移动，渲染前上传
+    pub(crate) lights: Lights,
+    // materials: glTF 场景的 PBR 材质，多个网格共用，参数可以在运行时修改
+    pub(crate) materials: Materials,
+    // environment: 基于图像的光照，加载 .hdr 全景图后同时作为天空盒
+    pub(crate) environment: Environment,
+    pub(crate) skybox: Skybox,
+    // sky: 程序化大气天空，开启时代替天空盒，并驱动 sky_sun 这个平行光
+    pub(crate) sky: ProceduralSky,
+    pub(crate) sky_sun: Option<LightId>,
+    // compute: 计算管线和每帧的调度列表，在场景渲染之前执行
+    pub(crate) compute: Compute,
+    // particles: GPU 粒子，模拟在计算调度之后、场景渲染之前，绘制在天空之后
+    pub(crate) particles: ParticleSystem,
+    // graph_pool: 渲染图的临时纹理，跨帧复用
+    pub(crate) graph_pool: TransientPool,
+    // render_graph_dot: 开启记录后保存最近一帧渲染图的 DOT 文本
+    pub(crate) render_graph_dot: Option<String>,
+    // debug_draw: 立即模式的调试线段，画在场景的渲染通道里
+    pub(crate) debug_draw: DebugDraw,
+    // sprites: 2D 精灵批处理，每帧排队的精灵在色调映射之后、文字之前画到展示平面上
+    pub(crate) sprites: SpriteBatch,
+    // text: 文字渲染，每帧排队的文字在色调映射之后画到展示平面上
+    pub(crate) text: TextRenderer,
+}
+impl WgpuApp {
+    /*
+       new()
+       创建一个新的 WgpuApp 实例
+       必须参数：
+       - window: 窗口实例。
+       instance: GPU实例，
+       surface: 展示平面，用于创建渲染目标。
+       adapter: GPU适配器，用于选择和配置 GPU 设备。
+       device: GPU设备，用于执行渲染操作。
+       queue: GPU队列，用于提交命令到 GPU。
+
+    */
+    pub async fn new(window: Arc<Window>) -> Self {
+        // instance: GPU实例
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            // 后端: 可以是OpenGL, Vulkan, Metal, DX12, or Browsers WebGPU
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        // surface: 展示平面
+        let surface = instance.create_surface(window.clone()).unwrap();
+        // adapter: GPU适配器
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                // power_preference: 电源偏好
+                // 可以是HighPerformance, LowPower, or Default
+                power_preference: wgpu::PowerPreference::default(),
+                // 兼容的展示平面
+                compatible_surface: Some(&surface),
+                // 是否强制使用回退适配器
+                force_fallback_adapter: false,
+            })
+            .await
+            .unwrap();
+
+        // device: GPU设备、queue: GPU队列
+        let (device, queue) = Self::request_device(&adapter).await.unwrap();
+        // caps: 展示平面的能力，比如支持的格式、alpha 模式等
+        let caps = surface.get_capabilities(&adapter);
+        // 处理窗口尺寸，max(1) 宽高最少1像素
+        let mut size = window.inner_size();
+        size.width = size.width.max(1);
+        size.height = size.height.max(1);
+        let config = wgpu::SurfaceConfiguration {
+            // 展示平面的使用方式
+            // RENDER_ATTACHMENT: 表示这个表面将用作渲染目标，可以进行绘制操作
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // format：指定了 SurfaceTexture 在 GPU 内存上如何被存储
+            format: caps.formats[0],
+            // 宽高不能为0，否则会崩溃
+            width: size.width,
+            height: size.height,
+            // present_mode: 展示模式
+            // FIFO: 表示展示模式为先进先出，即按照绘制顺序展示图像
+            // FIFO：指定了显示设备的刷新率做为渲染的帧速率，这本质上就是垂直同步
+            present_mode: wgpu::PresentMode::Fifo,
+            // 透明度模式，使用第一个支持的模式
+            alpha_mode: caps.alpha_modes[0],
+            // 视图格式：空向量，因为我们没有使用多视图渲染
+            view_formats: vec![],
+            // 期望的最大帧延迟：2帧，
+            // 表示 GPU 可以延迟展示 2 帧图像，以提高渲染性能
+            desired_maximum_frame_latency: 2,
+        };
+        // 配置展示平面
+        surface.configure(&device, &config);
+
+        let mut app = Self::from_parts(adapter, Some(window), Some(surface), device, queue, config);
+        // 有窗口时开启着色器热重载
+        match ShaderWatcher::new() {
+            Ok(watcher) => app.shader_watcher = Some(watcher),
+            Err(e) => log::warn!("无法监听着色器文件，热重载不可用: {e}"),
+        }
+        // 中文窗口标题和文字优先用系统的中文字体显示
+        app.text.add_system_cjk_font();
+        app
+    }
+
+    /*
+       new_headless()
+       创建一个没有窗口的 WgpuApp 实例，渲染到离屏纹理上
+       没有 surface，所以 adapter 不需要兼容任何展示平面，
+       force_fallback_adapter 为 true 时使用软件适配器，适合 CI 和服务器
+    */
+    pub async fn new_headless(options: HeadlessOptions) -> Result<Self, HeadlessError> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: options.force_fallback_adapter,
+            })
+            .await?;
+        log::info!("无窗口模式使用适配器: {:?}", adapter.get_info());
+        let (device, queue) = Self::request_device(&adapter).await?;
+
+        let size = PhysicalSize::new(options.width.max(1), options.height.max(1));
+        // 没有展示平面，这份配置只用来记录渲染目标的格式和尺寸
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: headless::OFFSCREEN_FORMAT,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        let mut app = Self::from_parts(adapter, None, None, device, queue, config);
+        if options.system_fonts {
+            app.text.add_system_cjk_font();
+        }
+        Ok(app)
+    }
+
+    // 两种模式共用：根据已经创建好的设备和配置创建各个子系统
+    // 没有 surface 时创建离屏渲染目标
+    fn from_parts(
+        adapter: wgpu::Adapter,
+        window: Option<Arc<Window>>,
+        surface: Option<wgpu::Surface<'static>>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let offscreen = surface
+            .is_none()
+            .then(|| OffscreenTarget::new(&device, &config));
+        let depth = DepthTexture::new(
+            &device,
+            DepthConfig::default(),
+            config.width,
+            config.height,
+            1,
+        );
+        let targets = Self::compute_target_state(Some(&depth), 1);
+        let hdr = HdrTarget::new(&device, config.width, config.height);
+        let auto_exposure = AutoExposure::new(&device, &hdr, AutoExposureSettings::default());
+        let post = PostProcess::new(&device, &hdr, config.format, PostSettings::default());
+        let tonemap = TonemapPass::new(
+            &device,
+            config.format,
+            auto_exposure.luminance_buffer(),
+            TonemapSettings::default(),
+        );
+        let camera = Camera::new(config.width as f32 / config.height as f32);
+        let camera_binding = CameraBinding::new(&device);
+        camera_binding.write(&queue, &camera);
+        let environment = Environment::new(&device, &queue);
+        let skybox = Skybox::new(
+            &device,
+            &targets,
+            &camera_binding.layout.layout,
+            &environment,
+        );
+        let sky = ProceduralSky::new(&device, &targets, &camera_binding.layout.layout);
+        let particles = ParticleSystem::new(&device, &targets, &camera_binding.layout.layout);
+        let debug_draw = DebugDraw::new(&device, &targets, &camera_binding.layout.layout);
+        let sprites = SpriteBatch::new(&device, config.format);
+        let text = TextRenderer::new(&device, config.format);
+        Self {
+            adapter,
+            window,
+            surface,
+            offscreen,
+            size: PhysicalSize::new(config.width, config.height),
+            size_changed: false,
+            clear_color: Self::default_clear_color(),
+            renderer: Renderer::new(targets),
+            shader_watcher: None,
+            watched_generation: None,
+            shader_error: None,
+            error_overlay: ErrorOverlay::new(&device, config.format),
+            base_title: None,
+            depth: Some(depth),
+            sample_count: 1,
+            msaa: None,
+            hdr,
+            post,
+            tonemap,
+            auto_exposure,
+            camera_controller: CameraController::Orbit(OrbitController::from_camera(&camera)),
+            camera,
+            camera_binding,
+            last_update: Instant::now(),
+            lights: Lights::new(&device),
+            materials: Materials::new(&device),
+            environment,
+            skybox,
+            sky,
+            sky_sun: None,
+            compute: Compute::new(),
+            particles,
+            graph_pool: TransientPool::new(),
+            render_graph_dot: None,
+            debug_draw,
+            sprites,
+            text,
+            device,
+            queue,
+            config,
+        }
+    }
+
+    // 有窗口和无窗口两种模式共用的设备创建流程
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+    ) -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+        // 为什么 device 和 queue 要一起声明，因为request_device方法返回的是一个元组，包含了 device 和 queue
+        adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                // 所需的功能
+                // 适配器支持时开启特定格式功能，才能使用 2x、8x 等 4x 以外的 MSAA 采样数
+                required_features: adapter.features()
+                    & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                // 所需的限制
+                required_limits: wgpu::Limits::defaults(),
+                // 实验性功能: wgpu 27 新增参数
+                experimental_features: wgpu::ExperimentalFeatures::disabled(),
+                // 设备标签
+                label: None,
+                // 内存提示：作用是提示 GPU 内存分配器如何分配内存
+                memory_hints: wgpu::MemoryHints::Performance,
+                // 跟踪: 开启跟踪会在 GPU 上记录所有操作，用于调试
+                trace: wgpu::Trace::Off,
+            })
+            .await
+    }
+
+    fn default_clear_color() -> wgpu::Color {
+        wgpu::Color {
+            r: 0.1,
+            g: 0.2,
+            b: 0.3,
+            a: 1.0,
+        }
+    }
+
+    // 根据深度配置和采样数计算管线需要匹配的渲染目标状态，颜色格式总是 HDR 格式
+    fn compute_target_state(depth: Option<&DepthTexture>, sample_count: u32) -> TargetState {
+        TargetState {
+            color_format: HDR_FORMAT,
+            depth_format: depth.map(|d| d.config.format),
+            depth_compare: depth
+                .map(|d| d.config.compare)
+                .unwrap_or(wgpu::CompareFunction::Always),
+            sample_count,
+        }
+    }
+
+    // 渲染目标变化后，重建所有管线让它们和新的附件保持一致
+    fn apply_target_state(&mut self) {
+        let targets = Self::compute_target_state(self.depth.as_ref(), self.sample_count);
+        if targets == self.renderer.targets {
+            return;
+        }
+        self.renderer.set_targets(&self.device, targets);
+        self.skybox.set_targets(&self.device, &targets);
+        self.sky.set_targets(&self.device, &targets);
+        self.particles.set_targets(&self.device, &targets);
+        self.debug_draw.set_targets(&self.device, &targets);
+    }
+
+    // 修改深度缓冲区配置，None 表示关闭深度测试，使用这个深度缓冲区的管线会被重建
+    pub fn set_depth_config(&mut self, config: Option<DepthConfig>) {
+        // 反向 Z 的深度配置要配合反向的投影矩阵，近处的物体才会留在前面
+        self.camera.reverse_z = config.is_some_and(|c| c.is_reversed());
+        self.camera_binding.write(&self.queue, &self.camera);
+        self.depth = config.map(|c| {
+            DepthTexture::new(
+                &self.device,
+                c,
+                self.config.width,
+                self.config.height,
+                self.sample_count,
+            )
+        });
+        // 新的深度格式可能不支持当前的采样数
+        if !self.supported_sample_counts().contains(&self.sample_count) {
+            log::warn!("深度格式不支持 {}x MSAA，回退到 1x", self.sample_count);
+            self.sample_count = 1;
+            self.recreate_render_targets();
+        }
+        self.apply_target_state();
+    }
+
+    // 修改色调映射算子和曝光，下一帧生效
+    pub fn set_tonemap_settings(&mut self, settings: TonemapSettings) {
+        self.tonemap.set_settings(&self.queue, settings);
+    }
+
+    pub fn post_settings(&self) -> &PostSettings {
+        self.post.settings()
+    }
+
+    // 修改后处理效果的顺序、开关和参数，下一帧生效
+    pub fn set_post_settings(&mut self, settings: PostSettings) {
+        self.post.set_settings(&self.queue, settings);
+    }
+
+    // 修改阴影的级联、偏移和 PCF 设置，下一帧生效
+    pub fn set_shadow_settings(&mut self, settings: ShadowSettings) {
+        self.lights.set_shadow_settings(&self.device, settings);
+    }
+
+    // 修改自动曝光的亮度范围和适应速度，下一次 update() 生效
+    pub fn set_auto_exposure_settings(&mut self, settings: AutoExposureSettings) {
+        self.auto_exposure.settings = settings;
+    }
+
+    // HDR 颜色格式和当前深度格式都支持的 MSAA 采样数
+    pub fn supported_sample_counts(&self) -> Vec<u32> {
+        msaa::supported_sample_counts(
+            &self.adapter,
+            self.device.features(),
+            HDR_FORMAT,
+            self.depth.as_ref().map(|d| d.config.format),
+        )
+    }
+
+    // 修改 MSAA 采样数，不支持时返回 false 并保持原样
+    // 修改后多重采样纹理和所有管线都会重新创建
+    pub fn set_sample_count(&mut self, sample_count: u32) -> bool {
+        if !self.supported_sample_counts().contains(&sample_count) {
+            log::warn!("当前格式不支持 {sample_count}x MSAA");
+            return false;
+        }
+        if sample_count != self.sample_count {
+            self.sample_count = sample_count;
+            self.recreate_render_targets();
+            self.apply_target_state();
+            log::info!("MSAA 采样数: {sample_count}x");
+        }
+        true
+    }
+
+    // 按尺寸和采样数重新创建离屏、HDR、多重采样和深度纹理
+    fn recreate_render_targets(&mut self) {
+        // 无窗口模式下重新创建离屏纹理
+        if self.offscreen.is_some() {
+            self.offscreen = Some(OffscreenTarget::new(&self.device, &self.config));
+        }
+        let (width, height) = (self.config.width, self.config.height);
+        if self.hdr.texture.size()
+            != (wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            })
+        {
+            self.hdr = HdrTarget::new(&self.device, width, height);
+            self.post.resize(&self.queue, &self.hdr);
+            self.auto_exposure.set_input(&self.device, &self.hdr);
+        }
+        self.msaa = (self.sample_count > 1)
+            .then(|| MsaaTarget::new(&self.device, HDR_FORMAT, width, height, self.sample_count));
+        // 深度纹理的尺寸和采样数必须和颜色附件一致，所以一起重新创建
+        if let Some(depth) = &self.depth {
+            self.depth = Some(DepthTexture::new(
+                &self.device,
+                depth.config,
+                self.config.width,
+                self.config.height,
+                self.sample_count,
+            ));
+        }
+    }
+
+    // 窗口，无窗口模式下为 None
+    pub fn window(&self) -> Option<&Arc<Window>> {
+        self.window.as_ref()
+    }
+
+    pub fn set_window_resized(&mut self, new_size: PhysicalSize<u32>) {
+        if new_size == self.size {
+            return;
+        }
+        self.size = new_size;
+        self.size_changed = true;
+    }
+    // 调整展示平面大小
+    pub fn resize_surface_if_needed(&mut self) {
+        if self.size_changed {
+            self.config.width = self.size.width;
+            self.config.height = self.size.height;
+            // configure参数：device: GPU设备, config: 展示平面配置
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+            self.recreate_render_targets();
+            self.camera.aspect = self.config.width as f32 / self.config.height as f32;
+            self.size_changed = false;
+        }
+    }
+
+    // 加载教程的演示场景
+    pub fn setup_demo_scene(&mut self) {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/cube.obj");
+        if let Err(e) = demo::add_obj_model(
+            &mut self.renderer,
+            &self.device,
+            &self.queue,
+            &self.camera_binding,
+            &self.lights,
+            path,
+        ) {
+            log::error!("{e}");
+        }
+        let lights = demo::add_demo_lights(&mut self.lights);
+        demo::cast_demo_shadows(&mut self.lights, &lights);
+    }
+
+    // 导入 glTF/GLB 场景代替演示场景，场景里有相机时使用第一个相机
+    pub fn load_scene(&mut self, path: &Path) -> Result<(), SceneError> {
+        let scene = Scene::load_gltf(&self.device, &self.queue, &self.materials.layout, path)?;
+        log::info!(
+            "已导入场景 {}: {} 个节点, {} 个网格, {} 个材质, {} 个相机",
+            path.display(),
+            scene.nodes.len(),
+            scene.meshes.len(),
+            scene.materials.len(),
+            scene.cameras.len()
+        );
+        if let Some(camera) = scene.camera(0, self.camera.aspect) {
+            // 反向 Z 跟随深度配置，不跟随场景
+            self.camera = Camera {
+                reverse_z: self.camera.reverse_z,
+                ..camera
+            };
+            self.camera_controller =
+                CameraController::Orbit(OrbitController::from_camera(&self.camera));
+        }
+        self.auto_exposure.reset(&self.queue);
+        demo::add_gltf_scene(
+            &mut self.renderer,
+            &self.device,
+            &self.camera_binding,
+            &self.lights,
+            &self.environment,
+            &mut self.materials,
+            scene,
+        );
+        demo::add_scene_sun(&mut self.lights);
+        if self.sky.is_enabled() {
+            self.update_sky_sun();
+        }
+        Ok(())
+    }
+
+    // 加载 .hdr 全景图作为环境光照和天空盒，失败时保留之前的环境
+    pub fn load_environment(&mut self, path: &Path) -> Result<(), TextureError> {
+        self.environment.load_hdr(&self.device, &self.queue, path)?;
+        log::info!("已加载环境贴图 {}", path.display());
+        Ok(())
+    }
+
+    // 开启程序化天空并设置时间等参数，None 关闭后恢复天空盒或清除颜色
+    // 开启时场景的第一个平行光跟着太阳变化，没有平行光时添加一个投射阴影的
+    pub fn set_procedural_sky(&mut self, settings: Option<SkySettings>) {
+        let enabled = settings.is_some();
+        self.sky.set_settings(&self.queue, settings);
+        if enabled {
+            self.update_sky_sun();
+        }
+    }
+
+    pub fn sky_settings(&self) -> Option<SkySettings> {
+        self.sky.is_enabled().then(|| self.sky.settings())
+    }
+
+    // 用程序化天空的时间更新它驱动的平行光
+    fn update_sky_sun(&mut self) {
+        let sun = self
+            .sky_sun
+            .filter(|id| self.lights.get(*id).is_some())
+            .or_else(|| sky::first_directional(&self.lights))
+            .or_else(|| {
+                self.lights
+                    .add(Light::directional(Vec3::NEG_Y, Vec3::ONE, 1.0).with_shadows())
+            });
+        self.sky_sun = sun;
+        if let Some(light) = sun.and_then(|id| self.lights.get_mut(id)) {
+            self.sky.settings().drive_light(light);
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.reload_changed_shaders();
+        let now = Instant::now();
+        let dt = (now - self.last_update).as_secs_f32();
+        self.last_update = now;
+        self.update_camera(dt);
+        self.auto_exposure.update(&self.queue, dt);
+        self.particles.update(&self.queue, dt);
+        self.debug_draw.update(dt);
+    }
+
+    // 光源可以在每帧的 update() 之后随时增删和修改，修改会在下一次 render() 时上传
+    pub fn lights_mut(&mut self) -> &mut Lights {
+        &mut self.lights
+    }
+
+    // 创建计算管线、缓冲区和绑定组时需要设备
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    // 添加的调度每一帧都会在 render() 的命令编码器里执行
+    pub fn compute_mut(&mut self) -> &mut Compute {
+        &mut self.compute
+    }
+
+    // 添加和修改粒子发射器
+    pub fn particles_mut(&mut self) -> &mut ParticleSystem {
+        &mut self.particles
+    }
+
+    // 在场景里加一个容量为 capacity 的粒子喷泉
+    pub fn add_particle_fountain(&mut self, capacity: u32) -> EmitterId {
+        demo::add_particle_fountain(&mut self.particles, &self.device, capacity)
+    }
+
+    // 请求在下一次 render() 之后读回缓冲区，用 take_readback 取结果
+    pub fn read_buffer(&mut self, buffer: &wgpu::Buffer) -> ReadbackId {
+        self.compute.read_buffer(&self.device, buffer)
+    }
+
+    // 不阻塞地取回读结果，GPU 还没有完成时返回 None，可以下一帧再试
+    pub fn take_readback(&mut self, id: ReadbackId) -> Option<Vec<u8>> {
+        self.compute.try_take(&self.device, id)
+    }
+
+    // 开启后每次 render() 都把渲染图导出为 Graphviz DOT，用 render_graph_dot() 取得
+    pub fn record_render_graph(&mut self, enabled: bool) {
+        self.render_graph_dot = enabled.then(String::new);
+    }
+
+    // 最近一次 render() 的渲染图，没有开启记录时返回 None
+    pub fn render_graph_dot(&self) -> Option<&str> {
+        self.render_graph_dot.as_deref()
+    }
+
+    // 调试绘制的画笔，默认只显示一帧、开启深度测试，可以在 update() 之后随时调用：
+    // app.debug_draw().with_lifetime(DebugLifetime::Seconds(2.0)).arrow(from, to, color);
+    pub fn debug_draw(&mut self) -> DebugPen<'_> {
+        self.debug_draw.pen()
+    }
+
+    // 排队一个精灵，在下一次 render() 时画出来；每帧都要重新排队
+    pub fn draw_sprite(&mut self, sprite: Sprite) {
+        self.sprites.queue(sprite);
+    }
+
+    // 添加一张精灵纹理，颜色贴图应该用 ColorSpace::matching(config.format) 创建
+    pub fn add_sprite_texture(&mut self, texture: &Texture) -> SpriteTextureId {
+        self.sprites.add_texture(&self.device, texture)
+    }
+
+    pub fn load_sprite_texture(&mut self, path: &Path) -> Result<SpriteTextureId, TextureError> {
+        self.sprites.load_texture(&self.device, &self.queue, path)
+    }
+
+    // 精灵的正交相机在 sprites_mut().camera 里，可以平移和缩放
+    pub fn sprites_mut(&mut self) -> &mut SpriteBatch {
+        &mut self.sprites
+    }
+
+    // 排队一段文字，在下一次 render() 时画出来；每帧都要重新排队
+    pub fn draw_text(&mut self, section: TextSection) {
+        self.text.queue(section);
+    }
+
+    // 加载一个后备字体，默认字体里没有的字符（比如中文）会用它显示
+    pub fn load_font(&mut self, path: &Path) -> Result<usize, TextError> {
+        self.text.load_font(path)
+    }
+
+    pub fn text_mut(&mut self) -> &mut TextRenderer {
+        &mut self.text
+    }
+
+    // 修改材质参数后，所有使用这个材质的网格在下一次 render() 时一起变化
+    pub fn materials_mut(&mut self) -> &mut Materials {
+        &mut self.materials
+    }
+
+    // 让控制器更新相机，再把相机矩阵写入 uniform 缓冲区
+    pub fn update_camera(&mut self, dt: f32) {
+        self.camera_controller.update(&mut self.camera, dt);
+        self.camera_binding.write(&self.queue, &self.camera);
+    }
+
+    // 在轨道和第一人称控制器之间切换，第一人称时锁定并隐藏光标
+    pub fn toggle_camera_controller(&mut self) {
+        self.camera_controller = self.camera_controller.toggled(&self.camera);
+        let Some(window) = &self.window else {
+            return;
+        };
+        if self.camera_controller.wants_cursor_grab() {
+            // 有的平台不支持 Locked（比如 X11），退而求其次用 Confined
+            let grabbed = window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+            if let Err(e) = grabbed {
+                log::warn!("无法锁定光标: {e}");
+            }
+            window.set_cursor_visible(false);
+        } else {
+            if let Err(e) = window.set_cursor_grab(CursorGrabMode::None) {
+                log::warn!("无法释放光标: {e}");
+            }
+            window.set_cursor_visible(true);
+        }
+    }
+
+    // 检查监听的着色器文件，有变化就重新编译并替换管线
+    fn reload_changed_shaders(&mut self) {
+        let Some(watcher) = &mut self.shader_watcher else {
+            return;
+        };
+        // 只在添加或修改了管线之后注册新的着色器文件，已经监听的文件 watch() 会直接跳过
+        let generation = self.renderer.pipelines_generation();
+        if self.watched_generation != Some(generation) {
+            self.watched_generation = Some(generation);
+            for path in self.renderer.shader_paths() {
+                // 文件不存在（比如发布后没有源码目录）时跳过
+                if path.exists()
+                    && let Err(e) = watcher.watch(&path)
+                {
+                    log::warn!("无法监听着色器 {}: {e}", path.display());
+                }
+            }
+        }
+        for path in watcher.changed_files() {
+            self.reload_shader(&path);
+        }
+    }
+
+    // 重新加载一个着色器文件，失败时保留之前的管线并显示错误
+    pub fn reload_shader(&mut self, path: &Path) {
+        match self.renderer.reload_shader(&self.device, path) {
+            Ok(count) => {
+                log::info!("已重新加载着色器 {}，替换了 {count} 条管线", path.display());
+                let fixed = self
+                    .shader_error
+                    .as_ref()
+                    .is_some_and(|e| e.path.canonicalize().ok() == path.canonicalize().ok());
+                if fixed {
+                    self.set_shader_error(None);
+                }
+            }
+            Err(e) => {
+                log::error!("着色器编译失败，继续使用之前的管线: {e}");
+                self.set_shader_error(Some(e));
+            }
+        }
+    }
+
+    // 设置或清除着色器错误，同时更新窗口标题
+    fn set_shader_error(&mut self, error: Option<ShaderError>) {
+        if let Some(window) = &self.window {
+            let base = self.base_title.get_or_insert_with(|| window.title());
+            match &error {
+                Some(e) => window.set_title(&format!("{base} - 着色器错误: {e}")),
+                None => window.set_title(base),
+            }
+        }
+        self.shader_error = error;
+    }
+
+    // 渲染函数
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // 有窗口时从展示平面获取纹理，无窗口时渲染到离屏纹理
+        let output = match &self.surface {
+            Some(surface) => Some(surface.get_current_texture()?),
+            None => None,
+        };
+        let view = match (&output, &self.offscreen) {
+            (Some(output), _) => output.texture.create_view(&Default::default()),
+            (None, Some(offscreen)) => offscreen.texture.create_view(&Default::default()),
+            (None, None) => unreachable!("WgpuApp 既没有展示平面也没有离屏目标"),
+        };
+        // 上传 update() 里修改过的实例、光源和材质
+        self.renderer.flush_instances(&self.device, &self.queue);
+        self.lights.flush(&self.queue, &self.camera);
+        self.materials.flush(&self.queue);
+        // 着色器错误的第一行画在红色提示条上，字号跟随提示条的高度；
+        // 提示条很窄，只显示文件名，完整的路径在日志和窗口标题里
+        if let Some(error) = &self.shader_error {
+            let file = error.path.file_name().unwrap_or_default().to_string_lossy();
+            let message = format!("{file}:{}:{}: {}", error.line, error.column, error.message);
+            let line = message.lines().next().unwrap_or_default();
+            let size = (self.size.height as f32 * 0.075 * 0.6).clamp(8.0, 24.0);
+            self.text
+                .queue(TextSection::new(line, Vec2::new(8.0, 4.0)).with_size(size));
+        }
+        self.debug_draw.prepare(&self.device, &self.queue);
+        self.sprites
+            .prepare(&self.device, &self.queue, self.size.width, self.size.height);
+        self.text
+            .prepare(&self.device, &self.queue, self.size.width, self.size.height);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                // label 作用：用于调试，方便在 GPU 上查看命令编码器
+                label: Some("Render Encoder"),
+            });
+        let mut pool = std::mem::take(&mut self.graph_pool);
+        let graph = self.build_render_graph(&view);
+        // 渲染图有环时记录错误并跳过这一帧，不让整个程序崩溃；导出的 DOT 保留上一次成功的结果
+        let dot = match self.render_graph_dot.is_some().then(|| graph.to_dot()) {
+            Some(Ok(dot)) => Some(dot),
+            Some(Err(e)) => {
+                log::error!("无法导出渲染图: {e}");
+                None
+            }
+            None => None,
+        };
+        let result = graph.execute(&self.device, &mut encoder, &mut pool);
+        self.graph_pool = pool;
+        if let Err(e) = result {
+            log::error!("渲染图无法执行，跳过这一帧: {e}");
+            return Ok(());
+        }
+        if dot.is_some() {
+            self.render_graph_dot = dot;
+        }
+        // 回读要修改回读的状态，不放进渲染图，在所有通道之后复制
+        self.compute.encode_readbacks(&mut encoder);
+        self.queue.submit(Some(encoder.finish()));
+        self.compute.after_submit();
+        if let Some(output) = output {
+            output.present();
+        }
+        Ok(())
+    }
+
+    // 声明这一帧的所有通道和它们读写的资源，执行顺序由渲染图根据依赖决定：
+    // 计算调度和粒子模拟在场景之前，阴影贴图在场景之前，场景之后是自动曝光、后处理、色调映射和 FXAA。
+    // 色调映射不读取亮度时自动曝光被剔除，没有开启后处理效果时后处理被剔除。
+    // 精灵、着色器错误的提示条和文字最后画在展示平面上，不受曝光和后处理的影响。
+    fn build_render_graph<'a>(&'a self, output: &'a wgpu::TextureView) -> RenderGraph<'a> {
+        let mut graph = RenderGraph::new();
+        let compute_buffers = graph.import("Compute Buffers");
+        let particles = graph.import("Particles");
+        let shadow_maps = graph.import("Shadow Maps");
+        let hdr = graph.import("HDR");
+        let luminance = graph.import("Luminance");
+        let surface = graph.import("Surface");
+
+        // 用户添加的计算调度可能写入渲染图看不到的缓冲区，总是执行
+        let mut pass = graph.add_pass("Compute");
+        pass.side_effect();
+        let compute_buffers = pass.write(compute_buffers);
+        pass.execute(|ctx| self.compute.encode(ctx.encoder));
+
+        let mut pass = graph.add_pass("Particle Simulation");
+        pass.read(compute_buffers);
+        let particles = pass.write(particles);
+        pass.execute(|ctx| self.particles.encode(ctx.encoder));
+
+        let mut pass = graph.add_pass("Shadows");
+        let shadow_maps = pass.write(shadow_maps);
+        pass.execute(|ctx| self.lights.shadows.render(ctx.encoder, &self.renderer));
+
+        let mut pass = graph.add_pass("Scene");
+        pass.read(compute_buffers).read(particles).read(shadow_maps);
+        let hdr = pass.write(hdr);
+        pass.execute(|ctx| self.draw_scene(ctx.encoder));
+
+        // 自动曝光的结果直接留在 GPU 上给色调映射使用，不需要等待回读
+        let mut pass = graph.add_pass("Auto Exposure");
+        pass.read(hdr);
+        let luminance = pass.write(luminance);
+        pass.execute(|ctx| self.auto_exposure.run(ctx.encoder));
+
+        // 自动曝光统计的是后处理之前的场景，泛光不会让画面变暗；
+        // 后处理的输出都是临时纹理，没有开启效果时色调映射直接读取 HDR 纹理
+        let post_output = self
+            .post
+            .add_passes(&mut graph, &self.device, hdr, &self.hdr.view);
+
+        // 色调映射，把 HDR 纹理（或后处理的输出）写入展示平面的纹理；
+        // 开启 FXAA 时先写入 LDR 纹理，FXAA 再把抗锯齿的结果写入展示平面
+        let ldr = self
+            .post
+            .fxaa_enabled()
+            .then(|| graph.create_texture("FXAA Input", self.post.ldr_texture()));
+        let mut pass = graph.add_pass("Tonemap");
+        pass.read(post_output.unwrap_or(hdr));
+        if self.tonemap.settings.auto_exposure {
+            pass.read(luminance);
+        }
+        let tonemapped = pass.write(ldr.unwrap_or(surface));
+        pass.execute(move |ctx| {
+            let input = post_output.map_or(&self.hdr.view, |r| ctx.texture(r));
+            let target = ldr.map_or(output, |r| ctx.texture(r));
+            self.tonemap.run(&self.device, ctx.encoder, input, target)
+        });
+
+        let surface = match ldr {
+            Some(_) => {
+                let mut pass = graph.add_pass("FXAA");
+                pass.read(tonemapped);
+                let surface = pass.write(surface);
+                pass.execute(move |ctx| {
+                    let input = ctx.texture(tonemapped);
+                    self.post.run_fxaa(&self.device, ctx.encoder, input, output)
+                });
+                surface
+            }
+            None => tonemapped,
+        };
+
+        let mut pass = graph.add_pass("Sprites");
+        pass.read(surface);
+        let surface = pass.write(surface);
+        pass.execute(|ctx| self.sprites.draw(ctx.encoder, output));
+
+        // 着色器错误的提示条在文字下面，文字画在提示条上
+        let surface = if self.shader_error.is_some() {
+            let mut pass = graph.add_pass("Shader Error");
+            pass.read(surface);
+            let surface = pass.write(surface);
+            pass.execute(|ctx| self.error_overlay.draw(ctx.encoder, output));
+            surface
+        } else {
+            surface
+        };
+
+        let mut pass = graph.add_pass("Text");
+        pass.read(surface);
+        let surface = pass.write(surface);
+        pass.execute(|ctx| self.text.draw(ctx.encoder, output));
+        graph.mark_output(surface);
+        graph
+    }
+
+    // 场景的主渲染通道：物体、天空、粒子和调试线段
+    fn draw_scene(&self, encoder: &mut wgpu::CommandEncoder) {
+        // 场景渲染到 HDR 纹理，开启 MSAA 时先渲染到多重采样纹理，再解析到 HDR 纹理
+        let (color_view, resolve_target) = match &self.msaa {
+            Some(msaa) => (&msaa.view, Some(&self.hdr.view)),
+            None => (&self.hdr.view, None),
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: color_view,
+                resolve_target,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_color),
+                    // 开启 MSAA 时只需要保留解析后的结果
+                    store: if self.msaa.is_some() {
+                        wgpu::StoreOp::Discard
+                    } else {
+                        wgpu::StoreOp::Store
+                    },
+                },
+            })],
+            depth_stencil_attachment: self.depth.as_ref().map(DepthTexture::attachment),
+            ..Default::default()
+        });
+        // 天空在场景之后画在远平面上，只填充没有被物体覆盖的像素；
+        // 没有深度缓冲区时做不到，只能先画天空作为背景
+        if self.depth.is_none() {
+            self.draw_sky(&mut render_pass);
+        }
+        self.renderer.draw(&mut render_pass);
+        if self.depth.is_some() {
+            self.draw_sky(&mut render_pass);
+        }
+        // 半透明的粒子不写深度，要在天空之后画，否则会被天空覆盖
+        self.particles
+            .draw(&mut render_pass, &self.camera_binding.bind_group);
+        // 调试线段也要在天空之后画，关闭深度测试的线段最后画，盖在所有东西上面
+        self.debug_draw
+            .draw(&mut render_pass, &self.camera_binding.bind_group);
+    }
+
+    // 程序化天空优先，其次是环境贴图的天空盒，都没有时背景是清除颜色
+    fn draw_sky(&self, pass: &mut wgpu::RenderPass<'_>) {
+        if self.sky.is_enabled() {
+            self.sky.draw(pass, &self.camera_binding.bind_group);
+        } else if self.environment.is_loaded() {
+            self.skybox
+                .draw(pass, &self.camera_binding.bind_group, &self.environment);
+        }
+    }
+
+    // 把离屏目标的内容读回 CPU，返回紧密排列的 RGBA8 像素，只在无窗口模式下可用
+    pub fn read_pixels(&self) -> Result<Vec<u8>, ReadbackError> {
+        let offscreen = self
+            .offscreen
+            .as_ref()
+            .ok_or(ReadbackError::NoOffscreenTarget)?;
+        headless::read_texture_rgba8(&self.device, &self.queue, &offscreen.texture)
+    }
+
+    // 把离屏目标的内容保存为 PNG 文件，有窗口的应用返回错误
+    pub fn save_png(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        let pixels = self.read_pixels()?;
+        headless::save_png(path, self.config.width, self.config.height, &pixels)
+    }
+
+    // 各种事件处理函数
+    // 键盘事件, event: &KeyEvent 是键盘事件的引用
+    pub fn keyboard_input(&mut self, event: &KeyEvent) -> bool {
+        // 移动键的按下和松开都要交给相机控制器
+        if self.camera_controller.keyboard_input(event) {
+            return true;
+        }
+        if event.state != ElementState::Pressed || event.repeat {
+            return false;
+        }
+        match event.physical_key {
+            // C 键：切换相机控制器
+            PhysicalKey::Code(KeyCode::KeyC) => {
+                self.toggle_camera_controller();
+                true
+            }
+            // Esc 键：第一人称时回到轨道控制器，释放光标
+            PhysicalKey::Code(KeyCode::Escape) if self.camera_controller.wants_cursor_grab() => {
+                self.toggle_camera_controller();
+                true
+            }
+            // M 键：在支持的 MSAA 采样数之间循环切换
+            PhysicalKey::Code(KeyCode::KeyM) => {
+                let counts = self.supported_sample_counts();
+                let next = counts
+                    .iter()
+                    .position(|&c| c == self.sample_count)
+                    .map(|i| counts[(i + 1) % counts.len()])
+                    .unwrap_or(1);
+                self.set_sample_count(next)
+            }
+            // T 键：切换色调映射算子
+            PhysicalKey::Code(KeyCode::KeyT) => {
+                let mut settings = self.tonemap.settings;
+                settings.tonemapper = settings.tonemapper.next();
+                log::info!("色调映射: {:?}", settings.tonemapper);
+                self.set_tonemap_settings(settings);
+                true
+            }
+            // [ 和 ] 键：曝光减少或增加半档
+            PhysicalKey::Code(code @ (KeyCode::BracketLeft | KeyCode::BracketRight)) => {
+                let mut settings = self.tonemap.settings;
+                settings.exposure += if code == KeyCode::BracketLeft {
+                    -0.5
+                } else {
+                    0.5
+                };
+                log::info!("曝光: {:+.1} EV", settings.exposure);
+                self.set_tonemap_settings(settings);
+                true
+            }
+            // B 键：开关阴影级联的调试视图
+            PhysicalKey::Code(KeyCode::KeyB) => {
+                let mut settings = self.lights.shadow_settings();
+                settings.debug_cascades = !settings.debug_cascades;
+                self.set_shadow_settings(settings);
+                true
+            }
+            // E 键：开关自动曝光，开启后 [ ] 调整的是曝光补偿
+            PhysicalKey::Code(KeyCode::KeyE) => {
+                let mut settings = self.tonemap.settings;
+                settings.auto_exposure = !settings.auto_exposure;
+                log::info!("自动曝光: {}", settings.auto_exposure);
+                self.set_tonemap_settings(settings);
+                true
+            }
+            // G 键：开关泛光
+            PhysicalKey::Code(KeyCode::KeyG) => {
+                let mut settings = self.post.settings().clone();
+                settings.bloom.enabled = !settings.bloom.enabled;
+                log::info!("泛光: {}", settings.bloom.enabled);
+                self.set_post_settings(settings);
+                true
+            }
+            // F 键：开关 FXAA
+            PhysicalKey::Code(KeyCode::KeyF) => {
+                let mut settings = self.post.settings().clone();
+                settings.fxaa.enabled = !settings.fxaa.enabled;
+                log::info!("FXAA: {}", settings.fxaa.enabled);
+                self.set_post_settings(settings);
+                true
+            }
+            PhysicalKey::Code(KeyCode::KeyK) => {
+                let settings = (!self.sky.is_enabled()).then(|| self.sky.settings());
+                log::info!("程序化天空: {}", settings.is_some());
+                self.set_procedural_sky(settings);
+                true
+            }
+            // 程序化天空开启时，逗号和句号把时间前后调一个小时
+            PhysicalKey::Code(code @ (KeyCode::Comma | KeyCode::Period))
+                if self.sky.is_enabled() =>
+            {
+                let mut settings = self.sky.settings();
+                let step = if code == KeyCode::Comma { -1.0 } else { 1.0 };
+                settings.time_of_day = (settings.time_of_day + step).rem_euclid(24.0);
+                log::info!("时间: {:.0} 点", settings.time_of_day);
+                self.set_procedural_sky(Some(settings));
+                true
+            }
+            _ => false,
+        }
+    }
+    // 鼠标点击事件, state: ElementState 是鼠标按钮的状态, button: MouseButton 是鼠标按钮
+    pub fn mouse_click(&mut self, _state: ElementState, _button: MouseButton) -> bool {
+        match _button {
+            MouseButton::Left if _state == ElementState::Pressed => {
+                self.clear_color = wgpu::Color {
+                    r: 0.2,
+                    g: 0.3,
+                    b: 0.4,
+                    a: 1.0,
+                };
+            }
+            MouseButton::Right if _state == ElementState::Pressed => {
+                self.clear_color = Self::default_clear_color();
+            }
+            _ => {}
+        }
+        self.camera_controller.mouse_click(_state, _button)
+    }
+    // 鼠标滚轮事件, delta: MouseScrollDelta 是鼠标滚轮的滚动量, phase: TouchPhase 是触摸阶段
+    pub fn mouse_wheel(&mut self, delta: MouseScrollDelta, _phase: TouchPhase) -> bool {
+        self.camera_controller.mouse_wheel(delta)
+    }
+    // 鼠标移动事件, position: 鼠标的物理位置
+    pub fn cursor_move(&mut self, position: PhysicalPosition<f64>) -> bool {
+        self.camera_controller.cursor_move(position)
+    }
+    // 设备输入事件，event:设备事件
+    pub fn device_input(&mut self, event: &DeviceEvent) -> bool {
+        self.camera_controller.device_input(event)
+    }
+}
@@ -0,0 +1,465 @@
+// 相机子系统
+// 相机的观察矩阵和投影矩阵放在一个 uniform 缓冲区里，每帧在 update() 中更新。
+// 提供两种可以运行时切换的控制器：
+// - 轨道控制器：拖动鼠标绕目标旋转，滚轮缩放
+// - 第一人称控制器：WASD 移动，使用原始的 DeviceEvent::MouseMotion 转动视角，需要锁定光标
+
+use glam::{Mat4, Vec3};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{DeviceEvent, ElementState, KeyEvent, MouseButton, MouseScrollDelta},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::texture::{BindingKind, BindingLayout};
+
+// 触控板的 PixelDelta 换算成多少像素算一行滚动
+const PIXELS_PER_LINE: f32 = 20.0;
+// 俯仰角限制在 ±89°，避免和 up 向量平行
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+// 根据偏航角和俯仰角计算朝向，yaw 从 +X 轴开始计算，yaw = -90° 时看向 -Z
+pub fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3::new(
+        yaw.cos() * pitch.cos(),
+        pitch.sin(),
+        yaw.sin() * pitch.cos(),
+    )
+}
+
+// 把滚轮的行滚动和像素滚动统一成行数，向上滚为正
+pub fn scroll_lines(delta: MouseScrollDelta) -> f32 {
+    match delta {
+        MouseScrollDelta::LineDelta(_, y) => y,
+        MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / PIXELS_PER_LINE,
+    }
+}
+
+// 透视相机
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    pub eye: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    // 宽高比，跟随窗口尺寸
+    pub aspect: f32,
+    // 垂直视野，弧度
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+    // 反向 Z：近平面映射到深度 1、远平面映射到 0，远处的深度精度更高；
+    // 由 WgpuApp::set_depth_config 根据 DepthConfig::is_reversed 设置，要和深度的清除值、比较函数一起改
+    pub reverse_z: bool,
+}
+
+impl Camera {
+    pub fn new(aspect: f32) -> Self {
+        Self {
+            eye: Vec3::new(0.0, 1.5, 3.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            aspect,
+            fovy: 45f32.to_radians(),
+            znear: 0.1,
+            zfar: 100.0,
+            reverse_z: false,
+        }
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    // wgpu 的裁剪空间深度范围是 [0, 1]，glam 的 perspective_rh 正好对应；
+    // 交换近平面和远平面就得到反向 Z 的投影，裁剪范围不变
+    pub fn projection(&self) -> Mat4 {
+        if self.reverse_z {
+            Mat4::perspective_rh(self.fovy, self.aspect, self.zfar, self.znear)
+        } else {
+            Mat4::perspective_rh(self.fovy, self.aspect, self.znear, self.zfar)
+        }
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection() * self.view()
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        (self.target - self.eye).normalize_or(Vec3::NEG_Z)
+    }
+}
+
+// 上传到 GPU 的相机数据，和着色器里的 CameraUniform 对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view_proj: [[f32; 4]; 4],
+    pub view: [[f32; 4]; 4],
+    pub proj: [[f32; 4]; 4],
+    // 相机的世界坐标，w 分量没有使用，只是为了 16 字节对齐
+    pub position: [f32; 4],
+}
+
+impl CameraUniform {
+    pub fn from_camera(camera: &Camera) -> Self {
+        Self {
+            view_proj: camera.view_projection().to_cols_array_2d(),
+            view: camera.view().to_cols_array_2d(),
+            proj: camera.projection().to_cols_array_2d(),
+            position: camera.eye.extend(1.0).to_array(),
+        }
+    }
+}
+
+// 相机的 uniform 缓冲区和绑定组
+pub struct CameraBinding {
+    pub buffer: wgpu::Buffer,
+    pub layout: BindingLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl CameraBinding {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Camera Buffer"),
+            size: std::mem::size_of::<CameraUniform>() as wgpu::BufferAddress,
+            // COPY_DST: 每帧用 write_buffer 更新
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = BindingLayout::new(
+            device,
+            "Camera Bind Group Layout",
+            &[(wgpu::ShaderStages::VERTEX_FRAGMENT, BindingKind::Uniform)],
+        );
+        let bind_group = layout.create_bind_group(device, &[buffer.as_entire_binding()]);
+        Self {
+            buffer,
+            layout,
+            bind_group,
+        }
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, camera: &Camera) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::from_camera(camera)),
+        );
+    }
+}
+
+// 轨道控制器：相机绕 target 旋转，始终看向 target
+#[derive(Debug, Clone)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    // 按住哪个鼠标按键拖动时旋转
+    pub rotate_button: MouseButton,
+    // 每移动一个像素旋转的弧度
+    pub rotate_speed: f32,
+    // 每滚动一行缩放的比例
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    dragging: bool,
+    last_cursor: Option<PhysicalPosition<f64>>,
+}
+
+impl OrbitController {
+    // 从相机当前的位置和朝向创建，切换控制器时视角不会跳变
+    pub fn from_camera(camera: &Camera) -> Self {
+        let offset = camera.eye - camera.target;
+        let distance = offset.length().max(0.01);
+        // 相机看向 -offset 方向
+        let forward = -offset / distance;
+        Self {
+            target: camera.target,
+            distance,
+            yaw: forward.z.atan2(forward.x),
+            pitch: forward.y.clamp(-1.0, 1.0).asin(),
+            rotate_button: MouseButton::Left,
+            rotate_speed: 0.01,
+            zoom_speed: 0.1,
+            min_distance: 0.1,
+            max_distance: 50.0,
+            dragging: false,
+            last_cursor: None,
+        }
+    }
+
+    pub fn mouse_click(&mut self, state: ElementState, button: MouseButton) -> bool {
+        if button != self.rotate_button {
+            return false;
+        }
+        self.dragging = state == ElementState::Pressed;
+        true
+    }
+
+    pub fn cursor_move(&mut self, position: PhysicalPosition<f64>) -> bool {
+        let last = self.last_cursor.replace(position);
+        let Some(last) = last else {
+            return false;
+        };
+        if !self.dragging {
+            return false;
+        }
+        let dx = (position.x - last.x) as f32;
+        let dy = (position.y - last.y) as f32;
+        // 画面跟着鼠标转：向右拖动时相机向左绕，向下拖动时相机向上绕
+        self.yaw += dx * self.rotate_speed;
+        self.pitch = (self.pitch - dy * self.rotate_speed).clamp(-MAX_PITCH, MAX_PITCH);
+        true
+    }
+
+    pub fn mouse_wheel(&mut self, delta: MouseScrollDelta) -> bool {
+        let lines = scroll_lines(delta);
+        // 向上滚动拉近
+        self.distance = (self.distance * (1.0 - self.zoom_speed).powf(lines))
+            .clamp(self.min_distance, self.max_distance);
+        true
+    }
+
+    pub fn update(&self, camera: &mut Camera) {
+        camera.target = self.target;
+        camera.eye = self.target - direction(self.yaw, self.pitch) * self.distance;
+    }
+}
+
+// 第一人称控制器：WASD 前后左右，空格上升，左 Shift 下降，鼠标原始移动量转动视角
+#[derive(Debug, Clone)]
+pub struct FirstPersonController {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    // 每秒移动的距离
+    pub speed: f32,
+    // 鼠标每移动一个单位转动的弧度
+    pub sensitivity: f32,
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+}
+
+impl FirstPersonController {
+    pub fn from_camera(camera: &Camera) -> Self {
+        let forward = camera.forward();
+        Self {
+            position: camera.eye,
+            yaw: forward.z.atan2(forward.x),
+            pitch: forward.y.clamp(-1.0, 1.0).asin(),
+            speed: 2.0,
+            sensitivity: 0.003,
+            forward: false,
+            backward: false,
+            left: false,
+            right: false,
+            up: false,
+            down: false,
+        }
+    }
+
+    pub fn keyboard_input(&mut self, event: &KeyEvent) -> bool {
+        let pressed = event.state == ElementState::Pressed;
+        let PhysicalKey::Code(code) = event.physical_key else {
+            return false;
+        };
+        let flag = match code {
+            KeyCode::KeyW | KeyCode::ArrowUp => &mut self.forward,
+            KeyCode::KeyS | KeyCode::ArrowDown => &mut self.backward,
+            KeyCode::KeyA | KeyCode::ArrowLeft => &mut self.left,
+            KeyCode::KeyD | KeyCode::ArrowRight => &mut self.right,
+            KeyCode::Space => &mut self.up,
+            KeyCode::ShiftLeft => &mut self.down,
+            _ => return false,
+        };
+        *flag = pressed;
+        true
+    }
+
+    pub fn device_input(&mut self, event: &DeviceEvent) -> bool {
+        let DeviceEvent::MouseMotion { delta: (dx, dy) } = *event else {
+            return false;
+        };
+        self.yaw += dx as f32 * self.sensitivity;
+        self.pitch = (self.pitch - dy as f32 * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        true
+    }
+
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let forward = direction(self.yaw, self.pitch);
+        // 水平面上的前方和右方，上下移动只沿世界坐标的 Y 轴
+        let flat_forward = Vec3::new(forward.x, 0.0, forward.z).normalize_or_zero();
+        let right = flat_forward.cross(Vec3::Y);
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+        let movement = flat_forward * axis(self.forward, self.backward)
+            + right * axis(self.right, self.left)
+            + Vec3::Y * axis(self.up, self.down);
+        self.position += movement.normalize_or_zero() * self.speed * dt;
+        camera.eye = self.position;
+        camera.target = self.position + forward;
+    }
+}
+
+// 当前使用的控制器
+#[derive(Debug, Clone)]
+pub enum CameraController {
+    Orbit(OrbitController),
+    FirstPerson(FirstPersonController),
+}
+
+impl CameraController {
+    // 切换到另一种控制器，新控制器从相机当前的姿态开始
+    pub fn toggled(&self, camera: &Camera) -> Self {
+        match self {
+            CameraController::Orbit(_) => {
+                CameraController::FirstPerson(FirstPersonController::from_camera(camera))
+            }
+            CameraController::FirstPerson(_) => {
+                CameraController::Orbit(OrbitController::from_camera(camera))
+            }
+        }
+    }
+
+    // 第一人称控制器需要锁定并隐藏光标
+    pub fn wants_cursor_grab(&self) -> bool {
+        matches!(self, CameraController::FirstPerson(_))
+    }
+
+    pub fn keyboard_input(&mut self, event: &KeyEvent) -> bool {
+        match self {
+            CameraController::Orbit(_) => false,
+            CameraController::FirstPerson(c) => c.keyboard_input(event),
+        }
+    }
+
+    pub fn mouse_click(&mut self, state: ElementState, button: MouseButton) -> bool {
+        match self {
+            CameraController::Orbit(c) => c.mouse_click(state, button),
+            CameraController::FirstPerson(_) => false,
+        }
+    }
+
+    pub fn mouse_wheel(&mut self, delta: MouseScrollDelta) -> bool {
+        match self {
+            CameraController::Orbit(c) => c.mouse_wheel(delta),
+            CameraController::FirstPerson(_) => false,
+        }
+    }
+
+    pub fn cursor_move(&mut self, position: PhysicalPosition<f64>) -> bool {
+        match self {
+            CameraController::Orbit(c) => c.cursor_move(position),
+            CameraController::FirstPerson(_) => false,
+        }
+    }
+
+    pub fn device_input(&mut self, event: &DeviceEvent) -> bool {
+        match self {
+            CameraController::Orbit(_) => false,
+            CameraController::FirstPerson(c) => c.device_input(event),
+        }
+    }
+
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        match self {
+            CameraController::Orbit(c) => c.update(camera),
+            CameraController::FirstPerson(c) => c.update(camera, dt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::dpi::PhysicalPosition;
+
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn reverse_z_maps_near_to_one_and_far_to_zero() {
+        let mut camera = Camera::new(1.0);
+        let depth = |camera: &Camera, distance: f32| {
+            let clip = camera.projection() * Vec3::new(0.0, 0.0, -distance).extend(1.0);
+            clip.z / clip.w
+        };
+        assert!(depth(&camera, camera.znear).abs() < 1e-5);
+        assert!((depth(&camera, camera.zfar) - 1.0).abs() < 1e-5);
+        camera.reverse_z = true;
+        assert!((depth(&camera, camera.znear) - 1.0).abs() < 1e-5);
+        assert!(depth(&camera, camera.zfar).abs() < 1e-5);
+        // 近处的物体深度更大，配合 Greater 比较函数留在前面
+        assert!(depth(&camera, 1.0) > depth(&camera, 2.0));
+    }
+
+    #[test]
+    fn orbit_round_trips_camera_pose() {
+        let mut camera = Camera::new(1.0);
+        let before = camera.eye;
+        OrbitController::from_camera(&camera).update(&mut camera);
+        assert_close(camera.eye, before);
+    }
+
+    #[test]
+    fn orbit_zoom_handles_line_and_pixel_deltas() {
+        let camera = Camera::new(1.0);
+        let mut lines = OrbitController::from_camera(&camera);
+        let mut pixels = lines.clone();
+        lines.mouse_wheel(MouseScrollDelta::LineDelta(0.0, 2.0));
+        pixels.mouse_wheel(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+            0.0,
+            2.0 * PIXELS_PER_LINE as f64,
+        )));
+        assert!(lines.distance < OrbitController::from_camera(&camera).distance);
+        assert!((lines.distance - pixels.distance).abs() < 1e-5);
+    }
+
+    #[test]
+    fn orbit_rotates_only_while_dragging() {
+        let camera = Camera::new(1.0);
+        let mut orbit = OrbitController::from_camera(&camera);
+        let yaw = orbit.yaw;
+        orbit.cursor_move(PhysicalPosition::new(0.0, 0.0));
+        orbit.cursor_move(PhysicalPosition::new(50.0, 0.0));
+        assert_eq!(orbit.yaw, yaw);
+
+        orbit.mouse_click(ElementState::Pressed, MouseButton::Left);
+        orbit.cursor_move(PhysicalPosition::new(100.0, 0.0));
+        assert!((orbit.yaw - yaw - 50.0 * orbit.rotate_speed).abs() < 1e-5);
+    }
+
+    #[test]
+    fn first_person_mouse_motion_turns_view() {
+        let mut camera = Camera::new(1.0);
+        let mut fps = FirstPersonController::from_camera(&camera);
+        let forward = camera.forward();
+        fps.device_input(&DeviceEvent::MouseMotion {
+            delta: (100.0, 0.0),
+        });
+        fps.update(&mut camera, 0.0);
+        assert!(camera.forward().dot(forward) < 0.99);
+        assert_close(camera.eye, Vec3::new(0.0, 1.5, 3.0));
+    }
+
+    #[test]
+    fn toggling_keeps_camera_pose() {
+        let mut camera = Camera::new(1.0);
+        let (eye, forward) = (camera.eye, camera.forward());
+        let mut controller =
+            CameraController::Orbit(OrbitController::from_camera(&camera)).toggled(&camera);
+        controller.update(&mut camera, 0.0);
+        assert_close(camera.eye, eye);
+        assert_close(camera.forward(), forward);
+        controller = controller.toggled(&camera);
+        controller.update(&mut camera, 0.0);
+        assert_close(camera.eye, eye);
+    }
+}
@@ -0,0 +1,488 @@
+// 计算子系统
+// 和渲染管线平行的计算 API：从 WGSL 创建 wgpu::ComputePipeline，绑定存储缓冲区和存储纹理，
+// 按问题规模（要处理多少个元素）除以着色器里声明的工作组大小得到调度次数。
+// 调度列表在 render() 的同一个命令编码器里、场景渲染之前执行，同一帧里的多次调度按添加顺序串联，
+// 前一次调度写入的存储缓冲区在后一次调度里就能读到。
+// 回读是异步的：复制命令放在这一帧的最后、和这一帧一起提交，提交后才开始映射，之后每帧检查一次是否完成，不会阻塞渲染。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::DeviceExt;
+
+use crate::pipeline::Shader;
+use crate::shader_reload::{self, ShaderError};
+
+// 创建计算管线所需的全部信息
+#[derive(Debug, Clone)]
+pub struct ComputeDescriptor {
+    pub label: String,
+    pub shader: Shader,
+    // 入口函数名，同一个着色器可以有多个计算入口
+    pub entry: String,
+    pub bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+}
+
+impl ComputeDescriptor {
+    pub fn new(label: &str, shader: Shader, entry: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            shader,
+            entry: entry.to_string(),
+            bind_group_layouts: vec![],
+        }
+    }
+}
+
+// 计算管线，创建时从着色器里读出入口函数的 @workgroup_size
+pub struct ComputePipeline {
+    pub desc: ComputeDescriptor,
+    pub pipeline: wgpu::ComputePipeline,
+    pub workgroup_size: [u32; 3],
+}
+
+impl ComputePipeline {
+    // 着色器解析失败、没有这个计算入口或者 wgpu 校验失败（比如绑定布局和着色器不一致）时返回错误
+    pub fn new(device: &wgpu::Device, desc: ComputeDescriptor) -> Result<Self, ShaderError> {
+        let workgroup_size = reflect_workgroup_size(&desc.shader, &desc.entry)?;
+        // 和热重载一样用错误作用域接住校验错误，不让它变成未捕获错误导致崩溃
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = desc.shader.create_module(device);
+        let bind_group_layouts: Vec<&wgpu::BindGroupLayout> =
+            desc.bind_group_layouts.iter().collect();
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&desc.label),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(&desc.label),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: Some(&desc.entry),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        if let Some(e) = pollster::block_on(device.pop_error_scope()) {
+            return Err(ShaderError::new(
+                &shader_path(&desc.shader),
+                None,
+                e.to_string(),
+            ));
+        }
+        Ok(Self {
+            desc,
+            pipeline,
+            workgroup_size,
+        })
+    }
+
+    // 覆盖 problem_size 个线程需要的工作组数量
+    pub fn workgroups(&self, problem_size: [u32; 3]) -> [u32; 3] {
+        workgroup_count(problem_size, self.workgroup_size)
+    }
+}
+
+// 每个维度向上取整，保证每个元素都有线程处理，着色器里要跳过超出范围的线程
+pub fn workgroup_count(problem_size: [u32; 3], workgroup_size: [u32; 3]) -> [u32; 3] {
+    std::array::from_fn(|i| problem_size[i].div_ceil(workgroup_size[i].max(1)))
+}
+
+// 错误信息里显示的路径，内嵌的着色器没有文件路径，用它的名字代替
+fn shader_path(shader: &Shader) -> PathBuf {
+    shader
+        .path
+        .clone()
+        .unwrap_or_else(|| Path::new(&shader.label).to_path_buf())
+}
+
+// 用 naga 找到计算入口函数，读出它声明的工作组大小
+pub fn reflect_workgroup_size(shader: &Shader, entry: &str) -> Result<[u32; 3], ShaderError> {
+    let path = shader_path(shader);
+    let module = shader_reload::parse_wgsl(&path, &shader.source)?;
+    module
+        .entry_points
+        .iter()
+        .find(|ep| ep.name == entry && ep.stage == naga::ShaderStage::Compute)
+        .map(|ep| ep.workgroup_size)
+        .ok_or_else(|| ShaderError {
+            path,
+            line: 0,
+            column: 0,
+            message: format!("找不到计算入口函数 {entry}"),
+        })
+}
+
+// 创建存储缓冲区并写入初始数据，可以被计算着色器读写，也可以作为回读的来源
+pub fn create_storage_buffer<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    label: &str,
+    contents: &[T],
+    extra_usage: wgpu::BufferUsages,
+) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: bytemuck::cast_slice(contents),
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_SRC
+            | wgpu::BufferUsages::COPY_DST
+            | extra_usage,
+    })
+}
+
+// 创建可以被计算着色器写入、被片元着色器采样的 2D 纹理
+pub fn create_storage_texture(
+    device: &wgpu::Device,
+    label: &str,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::STORAGE_BINDING
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ComputePipelineId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadbackId(pub usize);
+
+// 调度的规模
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchSize {
+    // 要处理的元素数量，按管线的工作组大小换算成工作组数量
+    Problem([u32; 3]),
+    // 直接指定工作组数量
+    Workgroups([u32; 3]),
+}
+
+impl DispatchSize {
+    // 一维问题，比如处理 n 个粒子
+    pub fn linear(n: u32) -> Self {
+        Self::Problem([n, 1, 1])
+    }
+}
+
+// 一次调度：用哪条管线、绑定哪些绑定组、调度多大规模
+#[derive(Debug, Clone)]
+pub struct Dispatch {
+    pub pipeline: ComputePipelineId,
+    // 按顺序绑定到 group 0、1、2...
+    pub bind_groups: Vec<wgpu::BindGroup>,
+    pub size: DispatchSize,
+}
+
+impl Dispatch {
+    pub fn new(
+        pipeline: ComputePipelineId,
+        bind_groups: Vec<wgpu::BindGroup>,
+        size: DispatchSize,
+    ) -> Self {
+        Self {
+            pipeline,
+            bind_groups,
+            size,
+        }
+    }
+}
+
+// 回读的进度
+enum ReadbackState {
+    // 复制命令还没有编码
+    Queued,
+    // 复制命令已经编码，等这一帧提交后开始映射
+    Copied,
+    // 已经调用 map_async，回调会把结果写进 result
+    Mapping,
+}
+
+struct Readback {
+    source: wgpu::Buffer,
+    staging: wgpu::Buffer,
+    state: ReadbackState,
+    result: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+}
+
+// 计算管线、每帧的调度列表和进行中的回读
+pub struct Compute {
+    pipelines: Vec<ComputePipeline>,
+    dispatches: Vec<Dispatch>,
+    // 回读完成并被取走后删除，所以用自增的编号而不是下标
+    readbacks: HashMap<usize, Readback>,
+    next_readback: usize,
+}
+
+impl Compute {
+    pub fn new() -> Self {
+        Self {
+            pipelines: vec![],
+            dispatches: vec![],
+            readbacks: HashMap::new(),
+            next_readback: 0,
+        }
+    }
+
+    pub fn add_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        desc: ComputeDescriptor,
+    ) -> Result<ComputePipelineId, ShaderError> {
+        self.pipelines.push(ComputePipeline::new(device, desc)?);
+        Ok(ComputePipelineId(self.pipelines.len() - 1))
+    }
+
+    pub fn pipeline(&self, id: ComputePipelineId) -> &ComputePipeline {
+        &self.pipelines[id.0]
+    }
+
+    // 添加一次调度，调度列表每一帧都会在场景渲染之前执行
+    pub fn add_dispatch(&mut self, dispatch: Dispatch) {
+        self.dispatches.push(dispatch);
+    }
+
+    pub fn clear_dispatches(&mut self) {
+        self.dispatches.clear();
+    }
+
+    // 在一个计算通道里按顺序执行调度列表
+    pub fn dispatch(&self, pass: &mut wgpu::ComputePass<'_>) {
+        for dispatch in &self.dispatches {
+            let pipeline = &self.pipelines[dispatch.pipeline.0];
+            let [x, y, z] = match dispatch.size {
+                DispatchSize::Problem(size) => pipeline.workgroups(size),
+                DispatchSize::Workgroups(count) => count,
+            };
+            if x == 0 || y == 0 || z == 0 {
+                continue;
+            }
+            pass.set_pipeline(&pipeline.pipeline);
+            for (i, bind_group) in dispatch.bind_groups.iter().enumerate() {
+                pass.set_bind_group(i as u32, bind_group, &[]);
+            }
+            pass.dispatch_workgroups(x, y, z);
+        }
+    }
+
+    // 请求读回 source 的全部内容（source 需要 COPY_SRC 用途）
+    // 复制在下一次 render() 的最后进行，所以读到的是那一帧全部命令执行之后的数据
+    pub fn read_buffer(&mut self, device: &wgpu::Device, source: &wgpu::Buffer) -> ReadbackId {
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Readback Buffer"),
+            size: source.size(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let id = self.next_readback;
+        self.next_readback += 1;
+        self.readbacks.insert(
+            id,
+            Readback {
+                source: source.clone(),
+                staging,
+                state: ReadbackState::Queued,
+                result: Arc::new(Mutex::new(None)),
+            },
+        );
+        ReadbackId(id)
+    }
+
+    // 在这一帧的最前面执行调度列表
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.dispatches.is_empty() {
+            return;
+        }
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute Pass"),
+            timestamp_writes: None,
+        });
+        self.dispatch(&mut pass);
+    }
+
+    // 在这一帧的最后把等待中的回读复制到暂存缓冲区，其他子系统的计算结果也能读到
+    pub fn encode_readbacks(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        for readback in self.readbacks.values_mut() {
+            if let ReadbackState::Queued = readback.state {
+                encoder.copy_buffer_to_buffer(&readback.source, 0, &readback.staging, 0, None);
+                readback.state = ReadbackState::Copied;
+            }
+        }
+    }
+
+    // 命令提交之后调用：开始映射这一帧复制好的暂存缓冲区
+    pub fn after_submit(&mut self) {
+        for readback in self.readbacks.values_mut() {
+            if let ReadbackState::Copied = readback.state {
+                let result = readback.result.clone();
+                readback
+                    .staging
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |r| {
+                        *result.lock().unwrap() = Some(r);
+                    });
+                readback.state = ReadbackState::Mapping;
+            }
+        }
+    }
+
+    // 不阻塞地检查回读是否完成，完成时取走数据；还没完成时返回 None
+    // 映射失败的回读会被丢弃并记录日志
+    pub fn try_take(&mut self, device: &wgpu::Device, id: ReadbackId) -> Option<Vec<u8>> {
+        let _ = device.poll(wgpu::PollType::Poll);
+        self.take_finished(id)
+    }
+
+    // 阻塞到回读完成，只用在测试和无窗口模式这样不在意等待的地方
+    // 回读还没有提交（没有经过 render()）时返回 None
+    pub fn wait(&mut self, device: &wgpu::Device, id: ReadbackId) -> Option<Vec<u8>> {
+        if !matches!(self.readbacks.get(&id.0)?.state, ReadbackState::Mapping) {
+            return None;
+        }
+        let _ = device.poll(wgpu::PollType::wait_indefinitely());
+        self.take_finished(id)
+    }
+
+    fn take_finished(&mut self, id: ReadbackId) -> Option<Vec<u8>> {
+        let finished = self.readbacks.get(&id.0)?.result.lock().unwrap().take()?;
+        let readback = self.readbacks.remove(&id.0)?;
+        if let Err(e) = finished {
+            log::error!("计算结果回读失败: {e}");
+            return None;
+        }
+        let data = readback.staging.slice(..).get_mapped_range().to_vec();
+        readback.staging.unmap();
+        Some(data)
+    }
+}
+
+impl Default for Compute {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workgroup_count_rounds_up() {
+        assert_eq!(workgroup_count([100, 1, 1], [64, 1, 1]), [2, 1, 1]);
+        assert_eq!(workgroup_count([64, 17, 1], [8, 8, 1]), [8, 3, 1]);
+        assert_eq!(workgroup_count([0, 1, 1], [64, 1, 1]), [0, 1, 1]);
+    }
+
+    #[test]
+    fn workgroup_size_is_read_from_the_entry_point() {
+        let shader = Shader::from_wgsl(
+            "test.wgsl",
+            "@compute @workgroup_size(8, 4) fn a() {}\n@compute @workgroup_size(64) fn b() {}",
+        );
+        assert_eq!(reflect_workgroup_size(&shader, "a").unwrap(), [8, 4, 1]);
+        assert_eq!(reflect_workgroup_size(&shader, "b").unwrap(), [64, 1, 1]);
+        let missing = reflect_workgroup_size(&shader, "c").unwrap_err();
+        assert!(missing.message.contains('c'));
+    }
+
+    const CHAIN_SHADER: &str = r#"
+@group(0) @binding(0)
+var<storage, read_write> values: array<u32>;
+
+@compute @workgroup_size(64)
+fn double(@builtin(global_invocation_id) id: vec3u) {
+    if id.x < arrayLength(&values) {
+        values[id.x] = values[id.x] * 2u;
+    }
+}
+
+@compute @workgroup_size(64)
+fn add_one(@builtin(global_invocation_id) id: vec3u) {
+    if id.x < arrayLength(&values) {
+        values[id.x] = values[id.x] + 1u;
+    }
+}
+"#;
+
+    // 着色器需要一个存储缓冲区，但管线布局里没有绑定组，wgpu 校验失败时返回错误而不是崩溃
+    #[test]
+    fn layout_mismatch_is_returned_as_an_error() {
+        use crate::headless;
+
+        let Some(app) = headless::test_device_app() else {
+            return;
+        };
+        let desc = ComputeDescriptor::new(
+            "Mismatch",
+            Shader::from_wgsl("chain.wgsl", CHAIN_SHADER),
+            "double",
+        );
+        let error = ComputePipeline::new(app.device(), desc)
+            .err()
+            .expect("缺少绑定组布局应该报错");
+        assert_eq!(error.path, Path::new("chain.wgsl"));
+        assert!(!error.message.is_empty());
+    }
+
+    // 同一帧里两次调度串联：先乘 2 再加 1，结果通过 render() 提交后异步读回
+    #[test]
+    fn chained_dispatches_are_read_back_after_render() {
+        use crate::headless;
+        use crate::texture::{BindingKind, BindingLayout};
+
+        let Some(mut app) = headless::test_device_app() else {
+            return;
+        };
+        let input: Vec<u32> = (0..1000).collect();
+        let buffer =
+            create_storage_buffer(app.device(), "Values", &input, wgpu::BufferUsages::empty());
+        let layout = BindingLayout::new(
+            app.device(),
+            "Values Layout",
+            &[(
+                wgpu::ShaderStages::COMPUTE,
+                BindingKind::Storage { read_only: false },
+            )],
+        );
+        let bind_group = layout.create_bind_group(app.device(), &[buffer.as_entire_binding()]);
+        let shader = Shader::from_wgsl("chain.wgsl", CHAIN_SHADER);
+        for entry in ["double", "add_one"] {
+            let mut desc = ComputeDescriptor::new(entry, shader.clone(), entry);
+            desc.bind_group_layouts = vec![layout.layout.clone()];
+            let device = app.device.clone();
+            let id = app.compute_mut().add_pipeline(&device, desc).unwrap();
+            assert_eq!(
+                app.compute.pipeline(id).workgroups([1000, 1, 1]),
+                [16, 1, 1]
+            );
+            app.compute_mut().add_dispatch(Dispatch::new(
+                id,
+                vec![bind_group.clone()],
+                DispatchSize::linear(input.len() as u32),
+            ));
+        }
+        let readback = app.read_buffer(&buffer);
+        // 还没有提交，不能等待
+        assert!(app.compute.wait(&app.device, readback).is_none());
+        app.render().unwrap();
+        let bytes = app.compute.wait(&app.device, readback).unwrap();
+        let output: &[u32] = bytemuck::cast_slice(&bytes);
+        assert!(output.iter().zip(&input).all(|(o, i)| *o == i * 2 + 1));
+        // 取走之后回读就不存在了
+        assert!(app.take_readback(readback).is_none());
+    }
+}
@@ -0,0 +1,431 @@
+// 立即模式的调试绘制
+// 在 update() 里随时调用 debug_draw() 画线段、箭头、包围盒、球、视锥、坐标轴和网格，
+// 所有形状都拆成线段，渲染时放进同一个动态顶点缓冲区：先是开启深度测试的线段，再是画在最上层的线段，
+// 两次绘制画完。每次调用可以选择只显示一帧还是显示几秒，显示时间在 update() 里倒数，
+// 到期的线段在渲染之后删除，所以每条线段至少会被画出一次。
+// 线段画在场景的渲染通道里，颜色是线性 HDR 值，和场景一起经过色调映射。
+
+use glam::{Mat4, Vec3, Vec4Swizzles};
+
+use crate::pipeline::{Pipeline, PipelineDescriptor, Shader, TargetState};
+
+// 球和圆分成的线段数
+const CIRCLE_SEGMENTS: usize = 32;
+
+// 一次调用画出的线段显示多久
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DebugLifetime {
+    // 只在下一次 render() 里显示
+    #[default]
+    Frame,
+    // 显示若干秒
+    Seconds(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DebugLine {
+    start: Vec3,
+    end: Vec3,
+    color: [f32; 4],
+    depth_test: bool,
+    lifetime: DebugLifetime,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl DebugVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<DebugVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// 画笔：记住这一次调用的显示时间和深度测试，形状的方法可以连着调用
+pub struct DebugPen<'a> {
+    lines: &'a mut Vec<DebugLine>,
+    lifetime: DebugLifetime,
+    depth_test: bool,
+}
+
+impl DebugPen<'_> {
+    pub fn with_lifetime(mut self, lifetime: DebugLifetime) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    // 关闭深度测试后线段不会被物体挡住
+    pub fn with_depth_test(mut self, depth_test: bool) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: [f32; 4]) -> &mut Self {
+        self.lines.push(DebugLine {
+            start,
+            end,
+            color,
+            depth_test: self.depth_test,
+            lifetime: self.lifetime,
+        });
+        self
+    }
+
+    // 箭头的头部是四条斜线，长度是箭身的五分之一
+    pub fn arrow(&mut self, from: Vec3, to: Vec3, color: [f32; 4]) -> &mut Self {
+        self.line(from, to, color);
+        let shaft = to - from;
+        let length = shaft.length();
+        if length <= f32::EPSILON {
+            return self;
+        }
+        let direction = shaft / length;
+        let (a, b) = direction.any_orthonormal_pair();
+        let head = length * 0.2;
+        let base = to - direction * head;
+        for side in [a, -a, b, -b] {
+            self.line(to, base + side * head * 0.5, color);
+        }
+        self
+    }
+
+    // 轴对齐包围盒的 12 条棱
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: [f32; 4]) -> &mut Self {
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        self.box_edges(std::array::from_fn(corner), color)
+    }
+
+    // 下标的三个二进制位分别表示 x、y、z 取较小还是较大的一侧
+    fn box_edges(&mut self, corners: [Vec3; 8], color: [f32; 4]) -> &mut Self {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corners[i], corners[i | bit], color);
+                }
+            }
+        }
+        self
+    }
+
+    pub fn circle(
+        &mut self,
+        center: Vec3,
+        normal: Vec3,
+        radius: f32,
+        color: [f32; 4],
+    ) -> &mut Self {
+        let (a, b) = normal.normalize().any_orthonormal_pair();
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (a * angle.cos() + b * angle.sin()) * radius
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+        self
+    }
+
+    // 球用三个互相垂直的大圆表示
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: [f32; 4]) -> &mut Self {
+        for normal in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.circle(center, normal, radius, color);
+        }
+        self
+    }
+
+    // 视锥的 8 个角是裁剪空间立方体的角经过 view_projection 的逆变换，深度范围是 wgpu 的 [0, 1]
+    pub fn frustum(&mut self, view_projection: Mat4, color: [f32; 4]) -> &mut Self {
+        let inverse = view_projection.inverse();
+        let corner = |i: usize| {
+            let ndc = Vec3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { 0.0 } else { 1.0 },
+            );
+            let world = inverse * ndc.extend(1.0);
+            world.xyz() / world.w
+        };
+        self.box_edges(std::array::from_fn(corner), color)
+    }
+
+    // 坐标轴：x 红、y 绿、z 蓝，长度是 transform 缩放之后的 length
+    pub fn axes(&mut self, transform: Mat4, length: f32) -> &mut Self {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        for (axis, color) in [
+            (Vec3::X, [1.0, 0.0, 0.0, 1.0]),
+            (Vec3::Y, [0.0, 1.0, 0.0, 1.0]),
+            (Vec3::Z, [0.0, 0.0, 1.0, 1.0]),
+        ] {
+            self.arrow(origin, transform.transform_point3(axis * length), color);
+        }
+        self
+    }
+
+    // XZ 平面上以 center 为中心的网格，每个方向 cells 格
+    pub fn grid(&mut self, center: Vec3, cell_size: f32, cells: u32, color: [f32; 4]) -> &mut Self {
+        let half = cells as f32 * cell_size / 2.0;
+        for i in 0..=cells {
+            let offset = i as f32 * cell_size - half;
+            self.line(
+                center + Vec3::new(offset, 0.0, -half),
+                center + Vec3::new(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + Vec3::new(-half, 0.0, offset),
+                center + Vec3::new(half, 0.0, offset),
+                color,
+            );
+        }
+        self
+    }
+}
+
+pub struct DebugDraw {
+    lines: Vec<DebugLine>,
+    // 开启深度测试的线段，深度比较方式和场景相同
+    depth_tested: Pipeline,
+    // 关闭深度测试的线段，总是画在最上层
+    overlay: Pipeline,
+    vertex_buffer: wgpu::Buffer,
+    // prepare() 上传的两段顶点的数量，顶点缓冲区里先是 depth_tested 再是 overlay
+    depth_tested_count: u32,
+    overlay_count: u32,
+}
+
+impl DebugDraw {
+    pub fn new(
+        device: &wgpu::Device,
+        targets: &TargetState,
+        camera_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let pipeline = |label: &str, depth_compare: Option<wgpu::CompareFunction>| {
+            let mut desc = PipelineDescriptor::new(
+                label,
+                Shader::from_wgsl("debug_lines.wgsl", include_str!("shaders/debug_lines.wgsl")),
+            );
+            desc.vertex_layouts = vec![DebugVertex::desc()];
+            desc.bind_group_layouts = vec![camera_layout.clone()];
+            desc.topology = wgpu::PrimitiveTopology::LineList;
+            desc.cull_mode = None;
+            desc.blend = Some(wgpu::BlendState::ALPHA_BLENDING);
+            // 线段不写深度，半透明的线段之间不会因为绘制顺序互相挡住
+            desc.depth_write_enabled = false;
+            desc.depth_compare = depth_compare;
+            Pipeline::new(device, desc, targets)
+        };
+        Self {
+            lines: Vec::new(),
+            depth_tested: pipeline("Debug Line Pipeline", None),
+            overlay: pipeline(
+                "Debug Line Overlay Pipeline",
+                Some(wgpu::CompareFunction::Always),
+            ),
+            vertex_buffer: Self::create_vertex_buffer(device, 0),
+            depth_tested_count: 0,
+            overlay_count: 0,
+        }
+    }
+
+    // 容量至少一条线段，避免创建大小为 0 的缓冲区
+    fn create_vertex_buffer(device: &wgpu::Device, vertices: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Line Vertex Buffer"),
+            size: (vertices.max(2) * std::mem::size_of::<DebugVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // 默认的画笔只显示一帧、开启深度测试
+    pub fn pen(&mut self) -> DebugPen<'_> {
+        DebugPen {
+            lines: &mut self.lines,
+            lifetime: DebugLifetime::Frame,
+            depth_test: true,
+        }
+    }
+
+    // 等待绘制的线段数量，包括还没有到期的
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    // 倒数显示时间，在 update() 里调用
+    pub fn update(&mut self, dt: f32) {
+        count_down(&mut self.lines, dt);
+    }
+
+    pub fn set_targets(&mut self, device: &wgpu::Device, targets: &TargetState) {
+        self.depth_tested.rebuild(device, targets);
+        self.overlay.rebuild(device, targets);
+    }
+
+    // 上传所有线段，然后删除只显示一帧的和已经到期的
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let (vertices, depth_tested_count) = build_vertices(&self.lines);
+        self.depth_tested_count = depth_tested_count;
+        self.overlay_count = vertices.len() as u32 - depth_tested_count;
+        let capacity = self.vertex_buffer.size() as usize / std::mem::size_of::<DebugVertex>();
+        if vertices.len() > capacity {
+            self.vertex_buffer =
+                Self::create_vertex_buffer(device, vertices.len().next_power_of_two());
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        retire(&mut self.lines);
+    }
+
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>, camera_bind_group: &wgpu::BindGroup) {
+        if self.depth_tested_count + self.overlay_count == 0 {
+            return;
+        }
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        if self.depth_tested_count > 0 {
+            pass.set_pipeline(&self.depth_tested.pipeline);
+            pass.draw(0..self.depth_tested_count, 0..1);
+        }
+        if self.overlay_count > 0 {
+            pass.set_pipeline(&self.overlay.pipeline);
+            let start = self.depth_tested_count;
+            pass.draw(start..start + self.overlay_count, 0..1);
+        }
+    }
+}
+
+fn count_down(lines: &mut [DebugLine], dt: f32) {
+    for line in lines {
+        if let DebugLifetime::Seconds(remaining) = &mut line.lifetime {
+            *remaining -= dt;
+        }
+    }
+}
+
+// 画过一次之后删除只显示一帧的和已经到期的线段
+fn retire(lines: &mut Vec<DebugLine>) {
+    lines.retain(|line| match line.lifetime {
+        DebugLifetime::Frame => false,
+        DebugLifetime::Seconds(remaining) => remaining > 0.0,
+    });
+}
+
+// 开启深度测试的线段排在前面，返回顶点和其中开启深度测试的顶点数量
+fn build_vertices(lines: &[DebugLine]) -> (Vec<DebugVertex>, u32) {
+    let mut vertices = Vec::with_capacity(lines.len() * 2);
+    for depth_test in [true, false] {
+        for line in lines.iter().filter(|line| line.depth_test == depth_test) {
+            for position in [line.start, line.end] {
+                vertices.push(DebugVertex {
+                    position: position.to_array(),
+                    color: line.color,
+                });
+            }
+        }
+    }
+    let depth_tested = lines.iter().filter(|line| line.depth_test).count() as u32 * 2;
+    (vertices, depth_tested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pen(lines: &mut Vec<DebugLine>) -> DebugPen<'_> {
+        DebugPen {
+            lines,
+            lifetime: DebugLifetime::Frame,
+            depth_test: true,
+        }
+    }
+
+    #[test]
+    fn shapes_expand_to_expected_line_counts() {
+        let mut lines = Vec::new();
+        let white = [1.0; 4];
+        pen(&mut lines)
+            .arrow(Vec3::ZERO, Vec3::X, white)
+            .aabb(Vec3::ZERO, Vec3::ONE, white)
+            .sphere(Vec3::ZERO, 1.0, white)
+            .axes(Mat4::IDENTITY, 1.0)
+            .grid(Vec3::ZERO, 1.0, 4, white);
+        assert_eq!(lines.len(), 5 + 12 + 3 * CIRCLE_SEGMENTS + 3 * 5 + 2 * 5);
+        // 包围盒的每条棱只沿一个轴
+        for line in &lines[5..17] {
+            let delta = (line.end - line.start).abs();
+            assert_eq!(delta.x + delta.y + delta.z, 1.0);
+        }
+    }
+
+    #[test]
+    fn frustum_corners_come_from_the_inverse_projection() {
+        let mut lines = Vec::new();
+        let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 10.0);
+        pen(&mut lines).frustum(projection, [1.0; 4]);
+        assert_eq!(lines.len(), 12);
+        // 近平面在 z = -1，半宽为 1；远平面在 z = -10，半宽为 10
+        let points: Vec<Vec3> = lines.iter().flat_map(|l| [l.start, l.end]).collect();
+        assert!(
+            points
+                .iter()
+                .any(|p| p.abs_diff_eq(Vec3::new(-1.0, -1.0, -1.0), 1e-4))
+        );
+        assert!(
+            points
+                .iter()
+                .any(|p| p.abs_diff_eq(Vec3::new(10.0, 10.0, -10.0), 1e-3))
+        );
+    }
+
+    #[test]
+    fn overlay_lines_follow_depth_tested_lines() {
+        let mut lines = Vec::new();
+        pen(&mut lines)
+            .with_depth_test(false)
+            .line(Vec3::ZERO, Vec3::X, [1.0; 4]);
+        pen(&mut lines).line(Vec3::Y, Vec3::Z, [0.5; 4]);
+        let (vertices, depth_tested) = build_vertices(&lines);
+        assert_eq!(depth_tested, 2);
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(vertices[0].position, [0.0, 1.0, 0.0]);
+        assert_eq!(vertices[2].position, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn lifetimes_count_down_and_frame_lines_last_one_frame() {
+        let mut lines = Vec::new();
+        pen(&mut lines).line(Vec3::ZERO, Vec3::X, [1.0; 4]);
+        pen(&mut lines)
+            .with_lifetime(DebugLifetime::Seconds(0.5))
+            .line(Vec3::ZERO, Vec3::Y, [1.0; 4]);
+        retire(&mut lines);
+        assert_eq!(lines.len(), 1);
+        count_down(&mut lines, 0.3);
+        retire(&mut lines);
+        assert_eq!(lines.len(), 1);
+        // 到期的线段在这一帧还会画出来，画完之后才删除
+        count_down(&mut lines, 0.3);
+        assert_eq!(build_vertices(&lines).0.len(), 2);
+        retire(&mut lines);
+        assert!(lines.is_empty());
+    }
+}
@@ -0,0 +1,438 @@
+// 教程各章的演示内容
+// 窗口模式和 --headless 模式都会加载这里的场景，黄金图像测试则按需单独加载。
+
+use std::path::Path;
+
+use glam::{Quat, Vec3, Vec4};
+
+use crate::camera::CameraBinding;
+use crate::ibl::Environment;
+use crate::instance::{Instance, InstanceBuffer, InstanceRaw};
+use crate::light::{Light, LightId, LightKind, Lights};
+use crate::material::{MaterialId, Materials};
+use crate::model::{Material, Model, ModelError, ModelVertex};
+use crate::particles::{
+    Attractor, Curve, EmitterId, EmitterSettings, ParticleBlend, ParticleSystem,
+};
+use crate::pipeline::{
+    DrawCall, InstanceBufferId, Mesh, MeshId, PipelineDescriptor, Renderer, Shader, TexturedVertex,
+    Vertex,
+};
+use crate::scene::Scene;
+use crate::texture::{BindingLayout, ColorSpace, Texture};
+
+// 第三章：一个三角形
+pub const TRIANGLE_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [0.0, 0.5, 0.0],
+        color: [1.0, 0.0, 0.0],
+    },
+    Vertex {
+        position: [-0.5, -0.5, 0.0],
+        color: [0.0, 1.0, 0.0],
+    },
+    Vertex {
+        position: [0.5, -0.5, 0.0],
+        color: [0.0, 0.0, 1.0],
+    },
+];
+
+// 第四章：用索引缓冲区绘制的五边形
+pub const PENTAGON_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-0.0868241, 0.49240386, 0.0],
+        color: [0.5, 0.0, 0.5],
+    },
+    Vertex {
+        position: [-0.49513406, 0.06958647, 0.0],
+        color: [0.5, 0.0, 0.5],
+    },
+    Vertex {
+        position: [-0.21918549, -0.44939706, 0.0],
+        color: [0.5, 0.0, 0.5],
+    },
+    Vertex {
+        position: [0.35966998, -0.3473291, 0.0],
+        color: [0.5, 0.0, 0.5],
+    },
+    Vertex {
+        position: [0.44147372, 0.2347359, 0.0],
+        color: [0.5, 0.0, 0.5],
+    },
+];
+
+pub const PENTAGON_INDICES: &[u32] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
+
+fn vertex_color_pipeline() -> PipelineDescriptor {
+    PipelineDescriptor::new(
+        "Vertex Color Pipeline",
+        Shader::from_wgsl(
+            "vertex_color.wgsl",
+            include_str!("shaders/vertex_color.wgsl"),
+        )
+        .with_path(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/shaders/vertex_color.wgsl"
+        )),
+    )
+}
+
+// 添加一个三角形
+pub fn add_triangle(renderer: &mut Renderer, device: &wgpu::Device) {
+    let pipeline = renderer.add_pipeline(device, vertex_color_pipeline());
+    let mesh = renderer.add_mesh(Mesh::new(device, "Triangle", TRIANGLE_VERTICES, &[]));
+    renderer.add_draw(DrawCall::new(pipeline, mesh));
+}
+
+// 添加一个五边形
+pub fn add_pentagon(renderer: &mut Renderer, device: &wgpu::Device) {
+    let pipeline = renderer.add_pipeline(device, vertex_color_pipeline());
+    let mesh = renderer.add_mesh(Mesh::new(
+        device,
+        "Pentagon",
+        PENTAGON_VERTICES,
+        PENTAGON_INDICES,
+    ));
+    renderer.add_draw(DrawCall::new(pipeline, mesh));
+}
+
+// 第五章：深度测试
+// 先提交近处的红色三角形，再提交远处更大的绿色三角形。
+// 没有深度缓冲区时后画的绿色会盖住红色，有深度测试时红色留在前面
+pub const DEPTH_TEST_VERTICES: &[Vertex] = &[
+    // 近处：z = 0.2
+    Vertex {
+        position: [0.0, 0.4, 0.2],
+        color: [1.0, 0.0, 0.0],
+    },
+    Vertex {
+        position: [-0.4, -0.4, 0.2],
+        color: [1.0, 0.0, 0.0],
+    },
+    Vertex {
+        position: [0.4, -0.4, 0.2],
+        color: [1.0, 0.0, 0.0],
+    },
+    // 远处：z = 0.6
+    Vertex {
+        position: [0.0, 0.8, 0.6],
+        color: [0.0, 1.0, 0.0],
+    },
+    Vertex {
+        position: [-0.8, -0.8, 0.6],
+        color: [0.0, 1.0, 0.0],
+    },
+    Vertex {
+        position: [0.8, -0.8, 0.6],
+        color: [0.0, 1.0, 0.0],
+    },
+];
+
+// 添加两个前后重叠的三角形
+pub fn add_depth_test_triangles(renderer: &mut Renderer, device: &wgpu::Device) {
+    let pipeline = renderer.add_pipeline(device, vertex_color_pipeline());
+    let mesh = renderer.add_mesh(Mesh::new(
+        device,
+        "Depth Test Triangles",
+        DEPTH_TEST_VERTICES,
+        &[],
+    ));
+    renderer.add_draw(DrawCall::new(pipeline, mesh));
+}
+
+// 第六章：纹理
+pub const QUAD_VERTICES: &[TexturedVertex] = &[
+    TexturedVertex {
+        position: [-0.5, 0.5, 0.0],
+        tex_coords: [0.0, 0.0],
+    },
+    TexturedVertex {
+        position: [-0.5, -0.5, 0.0],
+        tex_coords: [0.0, 1.0],
+    },
+    TexturedVertex {
+        position: [0.5, -0.5, 0.0],
+        tex_coords: [1.0, 1.0],
+    },
+    TexturedVertex {
+        position: [0.5, 0.5, 0.0],
+        tex_coords: [1.0, 0.0],
+    },
+];
+
+pub const QUAD_INDICES: &[u32] = &[0, 1, 2, 0, 2, 3];
+
+// 添加一个贴了棋盘格纹理的矩形
+pub fn add_textured_quad(
+    renderer: &mut Renderer,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    color_space: ColorSpace,
+) {
+    let texture = Texture::from_bytes(
+        device,
+        queue,
+        include_bytes!("../assets/checker.png"),
+        "checker.png",
+        color_space,
+    )
+    .unwrap();
+    let layout = BindingLayout::texture_sampler(device);
+    let mut desc = PipelineDescriptor::new(
+        "Textured Pipeline",
+        Shader::from_wgsl("textured.wgsl", include_str!("shaders/textured.wgsl")).with_path(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders/textured.wgsl"),
+        ),
+    );
+    desc.vertex_layouts = vec![TexturedVertex::desc()];
+    desc.bind_group_layouts = vec![layout.layout.clone()];
+    let pipeline = renderer.add_pipeline(device, desc);
+    let mesh = renderer.add_mesh(Mesh::new(device, "Quad", QUAD_VERTICES, QUAD_INDICES));
+    let mut draw = DrawCall::new(pipeline, mesh);
+    draw.bind_groups.push(texture.bind_group(device, &layout));
+    renderer.add_draw(draw);
+}
+
+// 第七章：相机
+// 每个面一种颜色的立方体，6 个面各 4 个顶点，从外面看是逆时针
+pub fn cube_geometry(half_extent: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let faces = [
+        (Vec3::X, [1.0, 0.2, 0.2]),
+        (Vec3::NEG_X, [0.2, 1.0, 1.0]),
+        (Vec3::Y, [0.2, 1.0, 0.2]),
+        (Vec3::NEG_Y, [1.0, 0.2, 1.0]),
+        (Vec3::Z, [0.2, 0.2, 1.0]),
+        (Vec3::NEG_Z, [1.0, 1.0, 0.2]),
+    ];
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for (normal, color) in faces {
+        // u × v = normal，按 (-u,-v) (u,-v) (u,v) (-u,v) 的顺序从外面看是逆时针
+        let v = if normal.y.abs() > 0.5 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        let u = v.cross(normal);
+        let base = vertices.len() as u32;
+        for (su, sv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            let position = (normal + u * su + v * sv) * half_extent;
+            vertices.push(Vertex {
+                position: position.to_array(),
+                color,
+            });
+        }
+        indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+    }
+    (vertices, indices)
+}
+
+// 添加一个用相机观察的立方体，相机绑定组放在 group(0)
+pub fn add_camera_cube(renderer: &mut Renderer, device: &wgpu::Device, camera: &CameraBinding) {
+    let mut desc = PipelineDescriptor::new(
+        "Camera Color Pipeline",
+        Shader::from_wgsl(
+            "camera_color.wgsl",
+            include_str!("shaders/camera_color.wgsl"),
+        )
+        .with_path(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/shaders/camera_color.wgsl"
+        )),
+    );
+    desc.bind_group_layouts = vec![camera.layout.layout.clone()];
+    let pipeline = renderer.add_pipeline(device, desc);
+    let (vertices, indices) = cube_geometry(0.5);
+    let mesh = renderer.add_mesh(Mesh::new(device, "Cube", &vertices, &indices));
+    let mut draw = DrawCall::new(pipeline, mesh);
+    draw.bind_groups.push(camera.bind_group.clone());
+    renderer.add_draw(draw);
+}
+
+// 第八章：实例化
+// 在 xz 平面上排成 count × count 的网格，颜色随位置渐变
+pub fn instance_grid(count: u32, spacing: f32) -> Vec<Instance> {
+    let offset = (count as f32 - 1.0) * spacing * 0.5;
+    (0..count * count)
+        .map(|i| {
+            let (x, z) = ((i % count) as f32, (i / count) as f32);
+            let t = |v: f32| v / (count as f32 - 1.0).max(1.0);
+            Instance {
+                position: Vec3::new(x * spacing - offset, 0.0, z * spacing - offset),
+                rotation: Quat::from_rotation_y(i as f32 * 0.3),
+                scale: Vec3::splat(spacing * 0.6),
+                color: [t(x), 0.5, 1.0 - t(z), 1.0],
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+// 添加一组实例化的立方体，返回实例缓冲区，之后可以在 update() 里修改
+pub fn add_instanced_cubes(
+    renderer: &mut Renderer,
+    device: &wgpu::Device,
+    camera: &CameraBinding,
+    instances: &[Instance],
+) -> InstanceBufferId {
+    let mut desc = PipelineDescriptor::new(
+        "Instanced Pipeline",
+        Shader::from_wgsl("instanced.wgsl", include_str!("shaders/instanced.wgsl")).with_path(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders/instanced.wgsl"),
+        ),
+    );
+    desc.vertex_layouts = vec![Vertex::desc(), InstanceRaw::desc()];
+    desc.bind_group_layouts = vec![camera.layout.layout.clone()];
+    let pipeline = renderer.add_pipeline(device, desc);
+    let (vertices, indices) = cube_geometry(0.5);
+    let mesh = renderer.add_mesh(Mesh::new(device, "Instanced Cube", &vertices, &indices));
+    let instance_buffer =
+        renderer.add_instance_buffer(InstanceBuffer::new(device, "Cubes", instances));
+    let mut draw = DrawCall::new(pipeline, mesh).with_instances(instance_buffer);
+    draw.bind_groups.push(camera.bind_group.clone());
+    renderer.add_draw(draw);
+    instance_buffer
+}
+
+// 第九章：模型加载
+// 加载 OBJ 模型，相机在 group(0)，材质在 group(1)
+pub fn add_obj_model(
+    renderer: &mut Renderer,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    camera: &CameraBinding,
+    lights: &Lights,
+    path: impl AsRef<Path>,
+) -> Result<Vec<MeshId>, ModelError> {
+    let layout = Material::layout(device);
+    let model = Model::load_obj(device, queue, &layout, path)?;
+    let mut desc = PipelineDescriptor::new(
+        "Model Pipeline",
+        Shader::from_wgsl("model.wgsl", include_str!("shaders/model.wgsl")).with_path(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/shaders/model.wgsl"
+        )),
+    );
+    desc.vertex_layouts = vec![ModelVertex::desc()];
+    desc.bind_group_layouts = vec![
+        camera.layout.layout.clone(),
+        layout.layout.clone(),
+        lights.layout.layout.clone(),
+    ];
+    let pipeline = renderer.add_pipeline(device, desc);
+    Ok(model.add_to_renderer(renderer, pipeline, &camera.bind_group, &lights.bind_group))
+}
+
+// 第十章：glTF 场景
+// 导入 glTF/GLB 场景，相机在 group(0)，PBR 材质在 group(1)，光源和阴影在 group(2)，
+// 环境光照在 group(3)，节点的世界矩阵作为实例数据
+pub fn add_gltf_scene(
+    renderer: &mut Renderer,
+    device: &wgpu::Device,
+    camera: &CameraBinding,
+    lights: &Lights,
+    environment: &Environment,
+    materials: &mut Materials,
+    scene: Scene,
+) -> Vec<MaterialId> {
+    let mut desc = PipelineDescriptor::new(
+        "Scene Pipeline",
+        Shader::from_wgsl("scene.wgsl", include_str!("shaders/scene.wgsl")).with_path(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/shaders/scene.wgsl"
+        )),
+    );
+    desc.vertex_layouts = vec![ModelVertex::desc(), InstanceRaw::desc()];
+    desc.bind_group_layouts = vec![
+        camera.layout.layout.clone(),
+        materials.layout.layout.clone(),
+        lights.layout.layout.clone(),
+        environment.layout.layout.clone(),
+    ];
+    // glTF 的材质可以是双面的，这里统一不剔除
+    desc.cull_mode = None;
+    let pipeline = renderer.add_pipeline(device, desc);
+    scene.add_to_renderer(
+        renderer,
+        device,
+        pipeline,
+        &camera.bind_group,
+        &[&lights.bind_group, &environment.bind_group],
+        materials,
+    )
+}
+
+// 第十一章：光照
+// 一盏偏暖的平行光、一个蓝色点光源和一盏从上往下照的品红色聚光灯，返回它们的 id 方便之后移动或删除
+pub fn add_demo_lights(lights: &mut Lights) -> Vec<LightId> {
+    [
+        Light::directional(Vec3::new(-0.4, -1.0, -0.6), Vec3::new(1.0, 0.95, 0.85), 0.6),
+        Light::point(Vec3::new(1.2, 0.6, 1.0), Vec3::new(0.2, 0.4, 1.0), 3.0, 4.0),
+        Light::spot(
+            Vec3::new(-0.8, 2.0, 0.5),
+            Vec3::new(0.3, -1.0, -0.2),
+            Vec3::new(1.0, 0.2, 0.8),
+            4.0,
+            6.0,
+            15f32.to_radians(),
+            25f32.to_radians(),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|light| lights.add(light))
+    .collect()
+}
+
+// 第十二章：阴影
+// 让平行光和聚光灯投射阴影，点光源没有阴影贴图，保持不变
+pub fn cast_demo_shadows(lights: &mut Lights, ids: &[LightId]) {
+    for &id in ids {
+        if let Some(light) = lights.get_mut(id)
+            && light.kind != LightKind::Point
+        {
+            light.cast_shadows = true;
+        }
+    }
+}
+
+// glTF 文件一般不带光源，没有光源时加一个投射阴影的太阳光
+pub fn add_scene_sun(lights: &mut Lights) -> Option<LightId> {
+    if !lights.is_empty() {
+        return None;
+    }
+    lights.add(Light::directional(Vec3::new(-0.5, -1.0, -0.4), Vec3::ONE, 3.0).with_shadows())
+}
+
+// 粒子喷泉：火花从物体上方喷出，受重力、阻力和一个吸引子的作用，亮度随年龄衰减
+// 发射率按容量和最长寿命算出，粒子池刚好保持装满
+pub fn add_particle_fountain(
+    particles: &mut ParticleSystem,
+    device: &wgpu::Device,
+    capacity: u32,
+) -> EmitterId {
+    let lifetime = (1.5, 2.5);
+    let settings = EmitterSettings {
+        position: Vec3::new(0.0, 0.6, 0.0),
+        radius: 0.05,
+        spawn_rate: capacity as f32 / lifetime.1,
+        lifetime,
+        direction: Vec3::Y,
+        spread: 0.4,
+        speed: (2.0, 3.0),
+        color: Curve::new(&[
+            (0.0, Vec4::new(4.0, 2.0, 0.6, 1.0)),
+            (0.4, Vec4::new(2.0, 0.6, 0.15, 0.8)),
+            (1.0, Vec4::new(0.3, 0.1, 0.05, 0.0)),
+        ]),
+        size: Curve::new(&[(0.0, 0.04), (1.0, 0.015)]),
+        speed_scale: Curve::constant(1.0),
+        gravity: Vec3::new(0.0, -4.0, 0.0),
+        drag: 0.4,
+        attractors: vec![Attractor {
+            position: Vec3::new(1.0, 1.0, 0.0),
+            strength: 0.8,
+        }],
+        blend: ParticleBlend::Additive,
+    };
+    particles.add_emitter(device, settings, capacity)
+}
@@ -0,0 +1,144 @@
+// 深度缓冲区
+// WgpuApp 持有一张深度纹理，尺寸跟随展示平面，在 resize_surface_if_needed 里重新创建，
+// 并作为主渲染通道的 depth_stencil_attachment。
+// 格式、清除值和比较函数都可以配置。反向 Z（DepthConfig::reverse_z）清除为 0.0、比较函数用 Greater，
+// 同时相机换成近平面在 1、远平面在 0 的投影矩阵，远处的深度精度更高。
+
+use crate::pipeline::{PipelineDescriptor, TargetState};
+
+// 远平面的比较函数和深度。天空和天空盒画在远平面上、在场景之后绘制，只通过深度还是清除值的像素：
+// 普通深度的远平面是 z = 1，用 LessEqual；反向 Z（清除为 0.0、比较函数用 Greater）的远平面是 z = 0，用 GreaterEqual
+pub fn far_plane(depth_compare: wgpu::CompareFunction) -> (wgpu::CompareFunction, f32) {
+    if is_reversed(depth_compare) {
+        (wgpu::CompareFunction::GreaterEqual, 0.0)
+    } else {
+        (wgpu::CompareFunction::LessEqual, 1.0)
+    }
+}
+
+// 比较函数是 Greater 系列时深度越大越近，也就是反向 Z
+pub fn is_reversed(depth_compare: wgpu::CompareFunction) -> bool {
+    matches!(
+        depth_compare,
+        wgpu::CompareFunction::Greater | wgpu::CompareFunction::GreaterEqual
+    )
+}
+
+// 让画在远平面上的管线跟随渲染目标的深度配置，着色器用 override FAR_DEPTH 接收远平面的深度；
+// 渲染目标变化时要在 rebuild 之前重新调用
+pub fn use_far_plane(desc: &mut PipelineDescriptor, targets: &TargetState) {
+    let (compare, depth) = far_plane(targets.depth_compare);
+    desc.depth_compare = Some(compare);
+    desc.constants = vec![("FAR_DEPTH".to_string(), depth as f64)];
+}
+
+// 深度缓冲区的配置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthConfig {
+    // 深度纹理格式，带模板的格式（比如 Depth24PlusStencil8）会同时启用模板缓冲区
+    pub format: wgpu::TextureFormat,
+    // 每帧开始时的深度清除值
+    pub clear_depth: f32,
+    // 管线默认使用的深度比较函数
+    pub compare: wgpu::CompareFunction,
+    // 每帧开始时的模板清除值，只在格式带模板时使用
+    pub clear_stencil: u32,
+}
+
+impl Default for DepthConfig {
+    fn default() -> Self {
+        Self {
+            format: wgpu::TextureFormat::Depth32Float,
+            clear_depth: 1.0,
+            compare: wgpu::CompareFunction::Less,
+            clear_stencil: 0,
+        }
+    }
+}
+
+impl DepthConfig {
+    // 带模板缓冲区的默认配置
+    pub fn with_stencil() -> Self {
+        Self {
+            format: wgpu::TextureFormat::Depth24PlusStencil8,
+            ..Default::default()
+        }
+    }
+
+    // 反向 Z 的默认配置：清除为 0.0，比较函数用 Greater
+    pub fn reverse_z() -> Self {
+        Self {
+            clear_depth: 0.0,
+            compare: wgpu::CompareFunction::Greater,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        is_reversed(self.compare)
+    }
+
+    pub fn has_stencil(&self) -> bool {
+        self.format.has_stencil_aspect()
+    }
+
+    pub fn far_plane(&self) -> (wgpu::CompareFunction, f32) {
+        far_plane(self.compare)
+    }
+}
+
+// 深度纹理及其视图
+pub struct DepthTexture {
+    pub config: DepthConfig,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl DepthTexture {
+    // sample_count 必须和颜色附件的采样数一致
+    pub fn new(
+        device: &wgpu::Device,
+        config: DepthConfig,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            // 深度纹理的尺寸必须和颜色附件一致
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            // 只作为渲染附件使用；多重采样的深度纹理在 GL 后端上不能同时作为采样纹理
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            config,
+            texture,
+            view,
+        }
+    }
+
+    // 主渲染通道使用的深度模板附件
+    pub fn attachment(&self) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        wgpu::RenderPassDepthStencilAttachment {
+            view: &self.view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(self.config.clear_depth),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: self.config.has_stencil().then_some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(self.config.clear_stencil),
+                store: wgpu::StoreOp::Store,
+            }),
+        }
+    }
+}
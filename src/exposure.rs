@@ -0,0 +1,325 @@
+// 自动曝光
+// 每帧用计算着色器统计 HDR 纹理的对数亮度直方图，求出平均亮度，再按适应速度向它靠近，
+// 结果留在 GPU 的存储缓冲区里，色调映射通道直接读取，不需要把数据读回 CPU，也就不会等待 GPU。
+// 亮度太低（接近纯黑）的像素单独放在第 0 个桶里，不参与平均，否则黑色背景会让画面过曝。
+
+use wgpu::util::DeviceExt;
+
+use crate::hdr::HdrTarget;
+use crate::pipeline::Shader;
+use crate::texture::{BindingKind, BindingLayout};
+
+// 直方图的桶数，等于求平均时一个工作组的线程数
+pub const HISTOGRAM_BINS: usize = 256;
+// 统计直方图时一个工作组覆盖 16×16 个像素
+const WORKGROUP_SIZE: u32 = 16;
+// 平均亮度的初始值，和色调映射里的中灰 0.18 相同，所以开启时第一帧的曝光是 1 倍
+const INITIAL_LUMINANCE: f32 = 0.18;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoExposureSettings {
+    // 直方图覆盖的对数亮度范围（以 2 为底），超出范围的像素放进两端的桶
+    pub min_log_luminance: f32,
+    pub max_log_luminance: f32,
+    // 适应速度，单位是 1/秒，画面变亮和变暗分别设置，人眼适应亮处比适应暗处快
+    // f32::INFINITY 表示立即适应
+    pub speed_up: f32,
+    pub speed_down: f32,
+}
+
+impl Default for AutoExposureSettings {
+    fn default() -> Self {
+        Self {
+            min_log_luminance: -10.0,
+            max_log_luminance: 4.0,
+            speed_up: 3.0,
+            speed_down: 1.0,
+        }
+    }
+}
+
+// 和着色器里的 ExposureParams 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ExposureParams {
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    // 这一帧向目标亮度靠近的比例，由帧间隔和适应速度在 CPU 上算好
+    blend_up: f32,
+    blend_down: f32,
+}
+
+impl ExposureParams {
+    fn new(settings: &AutoExposureSettings, dt: f32) -> Self {
+        // 指数衰减：和帧率无关，dt 秒后剩下 e^(-dt·speed) 的差距
+        let blend = |speed: f32| {
+            if speed.is_infinite() {
+                1.0
+            } else {
+                1.0 - (-dt * speed).exp()
+            }
+        };
+        Self {
+            min_log_luminance: settings.min_log_luminance,
+            log_luminance_range: (settings.max_log_luminance - settings.min_log_luminance)
+                .max(f32::EPSILON),
+            blend_up: blend(settings.speed_up),
+            blend_down: blend(settings.speed_down),
+        }
+    }
+}
+
+pub struct AutoExposure {
+    pub settings: AutoExposureSettings,
+    params: wgpu::Buffer,
+    histogram: wgpu::Buffer,
+    // 适应后的平均亮度，只有一个 f32，色调映射通道读取它计算曝光
+    luminance: wgpu::Buffer,
+    layout: BindingLayout,
+    bind_group: wgpu::BindGroup,
+    histogram_pipeline: wgpu::ComputePipeline,
+    average_pipeline: wgpu::ComputePipeline,
+    // 输入纹理的尺寸，用来计算工作组数量
+    size: (u32, u32),
+}
+
+impl AutoExposure {
+    pub fn new(device: &wgpu::Device, hdr: &HdrTarget, settings: AutoExposureSettings) -> Self {
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Auto Exposure Params"),
+            contents: bytemuck::bytes_of(&ExposureParams::new(&settings, 0.0)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // 直方图在求平均时被清零，所以只需要在创建时初始化一次
+        let histogram = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Luminance Histogram"),
+            contents: bytemuck::cast_slice(&[0u32; HISTOGRAM_BINS]),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let luminance = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Average Luminance"),
+            contents: bytemuck::bytes_of(&INITIAL_LUMINANCE),
+            // COPY_SRC 方便调试和测试时读回
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+        });
+        let layout = BindingLayout::new(
+            device,
+            "Auto Exposure Bind Group Layout",
+            &[
+                (
+                    wgpu::ShaderStages::COMPUTE,
+                    BindingKind::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        dimension: wgpu::TextureViewDimension::D2,
+                    },
+                ),
+                (wgpu::ShaderStages::COMPUTE, BindingKind::Uniform),
+                (
+                    wgpu::ShaderStages::COMPUTE,
+                    BindingKind::Storage { read_only: false },
+                ),
+                (
+                    wgpu::ShaderStages::COMPUTE,
+                    BindingKind::Storage { read_only: false },
+                ),
+            ],
+        );
+        let module = Shader::from_wgsl(
+            "auto_exposure.wgsl",
+            include_str!("shaders/auto_exposure.wgsl"),
+        )
+        .create_module(device);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Auto Exposure Pipeline Layout"),
+            bind_group_layouts: &[&layout.layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let histogram_pipeline =
+            compute_pipeline("Luminance Histogram Pipeline", "build_histogram");
+        let average_pipeline = compute_pipeline("Average Luminance Pipeline", "average_luminance");
+        let bind_group =
+            Self::create_bind_group(device, &layout, hdr, &params, &histogram, &luminance);
+        Self {
+            settings,
+            params,
+            histogram,
+            luminance,
+            layout,
+            bind_group,
+            histogram_pipeline,
+            average_pipeline,
+            size: (hdr.texture.width(), hdr.texture.height()),
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &BindingLayout,
+        hdr: &HdrTarget,
+        params: &wgpu::Buffer,
+        histogram: &wgpu::Buffer,
+        luminance: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        layout.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(&hdr.view),
+                params.as_entire_binding(),
+                histogram.as_entire_binding(),
+                luminance.as_entire_binding(),
+            ],
+        )
+    }
+
+    // 适应后的平均亮度所在的缓冲区，色调映射通道绑定它
+    pub fn luminance_buffer(&self) -> &wgpu::Buffer {
+        &self.luminance
+    }
+
+    // HDR 纹理重新创建后，绑定组要指向新的纹理
+    pub fn set_input(&mut self, device: &wgpu::Device, hdr: &HdrTarget) {
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.layout,
+            hdr,
+            &self.params,
+            &self.histogram,
+            &self.luminance,
+        );
+        self.size = (hdr.texture.width(), hdr.texture.height());
+    }
+
+    // 每帧在 update() 里调用，根据帧间隔更新适应比例
+    pub fn update(&self, queue: &wgpu::Queue, dt: f32) {
+        queue.write_buffer(
+            &self.params,
+            0,
+            bytemuck::bytes_of(&ExposureParams::new(&self.settings, dt)),
+        );
+    }
+
+    // 把平均亮度重置为初始值，比如切换场景之后
+    pub fn reset(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.luminance, 0, bytemuck::bytes_of(&INITIAL_LUMINANCE));
+    }
+
+    // 在场景渲染之后、色调映射之前调用：先统计直方图，再用一个工作组求平均并清空直方图
+    pub fn run(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Auto Exposure Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_pipeline(&self.histogram_pipeline);
+        let (width, height) = self.size;
+        pass.dispatch_workgroups(
+            width.div_ceil(WORKGROUP_SIZE),
+            height.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+        pass.set_pipeline(&self.average_pipeline);
+        pass.dispatch_workgroups(1, 1, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WgpuApp;
+    use crate::hdr::TonemapSettings;
+    use crate::headless::{self, HeadlessOptions};
+
+    // 测试里阻塞地读回平均亮度，正常渲染时不需要
+    fn read_luminance(app: &WgpuApp) -> f32 {
+        let buffer = app.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Luminance Readback"),
+            size: 4,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = app.device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(app.auto_exposure.luminance_buffer(), 0, &buffer, 0, 4);
+        app.queue.submit(Some(encoder.finish()));
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |r| r.unwrap());
+        app.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .unwrap();
+        *bytemuck::from_bytes::<f32>(&buffer.slice(..).get_mapped_range())
+    }
+
+    #[test]
+    fn average_matches_uniform_image() {
+        let Some(mut app) = headless::test_app(HeadlessOptions {
+            width: 100,
+            height: 70,
+            ..Default::default()
+        }) else {
+            return;
+        };
+        // 只有清除颜色的画面，每个像素的亮度都是 2.0，立即适应后平均亮度也应该是 2.0
+        app.clear_color = wgpu::Color {
+            r: 2.0,
+            g: 2.0,
+            b: 2.0,
+            a: 1.0,
+        };
+        app.set_tonemap_settings(TonemapSettings {
+            auto_exposure: true,
+            ..Default::default()
+        });
+        app.set_auto_exposure_settings(AutoExposureSettings {
+            speed_up: f32::INFINITY,
+            speed_down: f32::INFINITY,
+            ..Default::default()
+        });
+        app.update();
+        app.render().unwrap();
+        let luminance = read_luminance(&app);
+        assert!((luminance / 2.0 - 1.0).abs() < 0.03, "{luminance}");
+
+        // 第二帧变暗，速度为 0 时保持不变
+        app.clear_color = wgpu::Color::BLACK;
+        app.clear_color.r = 0.1;
+        app.set_auto_exposure_settings(AutoExposureSettings {
+            speed_down: 0.0,
+            ..Default::default()
+        });
+        app.update();
+        app.render().unwrap();
+        assert_eq!(read_luminance(&app), luminance);
+    }
+
+    #[test]
+    fn blend_factor_is_frame_rate_independent() {
+        let settings = AutoExposureSettings::default();
+        // 两个 1/60 秒的帧和一个 1/30 秒的帧剩下的差距一样
+        let short = ExposureParams::new(&settings, 1.0 / 60.0);
+        let long = ExposureParams::new(&settings, 1.0 / 30.0);
+        let remaining_short = (1.0 - short.blend_up) * (1.0 - short.blend_up);
+        assert!((remaining_short - (1.0 - long.blend_up)).abs() < 1e-6);
+        assert!(short.blend_down < short.blend_up);
+    }
+
+    #[test]
+    fn infinite_speed_adapts_immediately() {
+        let settings = AutoExposureSettings {
+            speed_up: f32::INFINITY,
+            ..Default::default()
+        };
+        assert_eq!(ExposureParams::new(&settings, 0.0).blend_up, 1.0);
+    }
+}
@@ -0,0 +1,1078 @@
+// 黄金图像（golden image）回归测试
+// 在软件适配器上以无窗口模式驱动 WgpuApp 渲染 N 帧，
+// 把结果和 tests/golden/ 下提交的参考图逐像素比较（带容差和感知差异），
+// 失败时把实际输出和差异图写到 target/golden-diff/ 下，方便查看。
+//
+// 更新参考图：UPDATE_GOLDEN=1 cargo test
+// 参考图不存在时会直接写入，并让测试失败提醒检查后再提交。
+// 没有可用的适配器时测试失败，确实没有 GPU 的环境可以用 SKIP_GPU_TESTS=1 cargo test 跳过。
+
+use std::path::PathBuf;
+
+use crate::WgpuApp;
+use crate::headless::{self, HeadlessOptions};
+
+// 比较时使用的容差
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    // 单个通道允许的最大差值（0-255）
+    pub per_channel: u8,
+    // 感知差异阈值：CIE76 ΔE，约 2.3 是人眼刚能察觉的差异
+    pub max_delta_e: f32,
+    // 允许超出容差的像素比例
+    pub max_failing_fraction: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            per_channel: 2,
+            max_delta_e: 1.0,
+            max_failing_fraction: 0.0,
+        }
+    }
+}
+
+// 一次比较的结果
+#[derive(Debug)]
+pub struct Comparison {
+    // 超出容差的像素数量
+    pub failing_pixels: usize,
+    pub total_pixels: usize,
+    // 所有像素里最大的通道差值和最大的 ΔE
+    pub max_channel_diff: u8,
+    pub max_delta_e: f32,
+    // 差异图：超出容差的像素标红，其余像素变暗显示
+    pub diff_image: Vec<u8>,
+}
+
+impl Comparison {
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        self.failing_pixels as f32 <= self.total_pixels as f32 * tolerance.max_failing_fraction
+    }
+}
+
+// sRGB 8 位颜色转换到 CIELAB（D65 白点）
+fn srgb_to_lab(px: &[u8]) -> [f32; 3] {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(px[0]), linear(px[1]), linear(px[2]));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn delta_e(a: &[u8], b: &[u8]) -> f32 {
+    let (la, lb) = (srgb_to_lab(a), srgb_to_lab(b));
+    ((la[0] - lb[0]).powi(2) + (la[1] - lb[1]).powi(2) + (la[2] - lb[2]).powi(2)).sqrt()
+}
+
+// 逐像素比较两张同尺寸的 RGBA8 图像
+// 一个像素只有在通道差值和感知差异都超出容差时才算失败，
+// 这样暗部里人眼看不出的微小舍入差异不会让测试失败
+pub fn compare_images(expected: &[u8], actual: &[u8], tolerance: &Tolerance) -> Comparison {
+    assert_eq!(expected.len(), actual.len(), "图像尺寸不一致");
+    let mut comparison = Comparison {
+        failing_pixels: 0,
+        total_pixels: expected.len() / 4,
+        max_channel_diff: 0,
+        max_delta_e: 0.0,
+        diff_image: Vec::with_capacity(expected.len()),
+    };
+    for (e, a) in expected.chunks_exact(4).zip(actual.chunks_exact(4)) {
+        let channel_diff = e.iter().zip(a).map(|(x, y)| x.abs_diff(*y)).max().unwrap();
+        let de = delta_e(e, a);
+        comparison.max_channel_diff = comparison.max_channel_diff.max(channel_diff);
+        comparison.max_delta_e = comparison.max_delta_e.max(de);
+        if channel_diff > tolerance.per_channel && de > tolerance.max_delta_e {
+            comparison.failing_pixels += 1;
+            comparison.diff_image.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            comparison
+                .diff_image
+                .extend_from_slice(&[a[0] / 4, a[1] / 4, a[2] / 4, 255]);
+        }
+    }
+    comparison
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn diff_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/golden-diff")
+}
+
+// 一个黄金图像测试用例
+pub struct GoldenTest {
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+    // 渲染的帧数，只比较最后一帧
+    pub frames: u32,
+    pub tolerance: Tolerance,
+}
+
+impl GoldenTest {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            width: 64,
+            height: 48,
+            frames: 1,
+            tolerance: Tolerance::default(),
+        }
+    }
+
+    pub fn frames(mut self, frames: u32) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    // 创建无窗口应用，setup 在第一帧之前调用，
+    // on_frame 在每一帧 update() 之前调用，参数是帧序号
+    pub fn run(
+        self,
+        setup: impl FnOnce(&mut WgpuApp),
+        mut on_frame: impl FnMut(&mut WgpuApp, u32),
+    ) {
+        let options = HeadlessOptions {
+            width: self.width,
+            height: self.height,
+            force_fallback_adapter: true,
+            system_fonts: false,
+        };
+        let Some(mut app) = headless::test_app(options) else {
+            return;
+        };
+        setup(&mut app);
+        for frame in 0..self.frames.max(1) {
+            on_frame(&mut app, frame);
+            app.resize_surface_if_needed();
+            app.update();
+            app.render().unwrap();
+        }
+        let actual = app.read_pixels().unwrap();
+        self.check(&actual);
+    }
+
+    fn check(&self, actual: &[u8]) {
+        let reference = golden_dir().join(format!("{}.png", self.name));
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+        if update || !reference.exists() {
+            std::fs::create_dir_all(golden_dir()).unwrap();
+            headless::save_png(&reference, self.width, self.height, actual).unwrap();
+            assert!(
+                update,
+                "参考图 {} 不存在，已写入当前输出，请检查后提交",
+                reference.display()
+            );
+            return;
+        }
+
+        let expected = image::open(&reference).unwrap().to_rgba8();
+        assert_eq!(
+            expected.dimensions(),
+            (self.width, self.height),
+            "参考图 {} 的尺寸与测试不一致",
+            reference.display()
+        );
+        let comparison = compare_images(expected.as_raw(), actual, &self.tolerance);
+        if comparison.passes(&self.tolerance) {
+            return;
+        }
+
+        let dir = diff_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let actual_path = dir.join(format!("{}.actual.png", self.name));
+        let diff_path = dir.join(format!("{}.diff.png", self.name));
+        headless::save_png(&actual_path, self.width, self.height, actual).unwrap();
+        headless::save_png(&diff_path, self.width, self.height, &comparison.diff_image).unwrap();
+        panic!(
+            "黄金图像 {} 不匹配: {}/{} 个像素超出容差（最大通道差 {}，最大 ΔE {:.2}），\
+             实际输出: {}，差异图: {}",
+            self.name,
+            comparison.failing_pixels,
+            comparison.total_pixels,
+            comparison.max_channel_diff,
+            comparison.max_delta_e,
+            actual_path.display(),
+            diff_path.display(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::dpi::PhysicalPosition;
+    use winit::dpi::PhysicalSize;
+    use winit::event::{ElementState, MouseButton, MouseScrollDelta};
+
+    use glam::{Mat4, Vec2, Vec3};
+
+    use super::*;
+    use crate::camera::{CameraController, OrbitController};
+    use crate::debug_draw::DebugLifetime;
+    use crate::demo;
+    use crate::depth::DepthConfig;
+    use crate::exposure::AutoExposureSettings;
+    use crate::hdr::{TonemapSettings, Tonemapper};
+    use crate::instance::Instance;
+    use crate::light::Light;
+    use crate::pipeline::{DrawCall, Mesh, PipelineDescriptor, Shader};
+    use crate::shader_reload::ShaderError;
+    use crate::shadow::ShadowSettings;
+    use crate::sky::SkySettings;
+    use crate::sprite::Sprite;
+    use crate::text::{TextAlign, TextSection};
+    use crate::texture::{ColorSpace, Texture};
+
+    #[test]
+    fn compare_ignores_differences_within_tolerance() {
+        let expected = [100, 150, 200, 255].repeat(4);
+        let actual = [101, 149, 200, 255].repeat(4);
+        let comparison = compare_images(&expected, &actual, &Tolerance::default());
+        assert_eq!(comparison.failing_pixels, 0);
+        assert_eq!(comparison.max_channel_diff, 1);
+    }
+
+    #[test]
+    fn compare_reports_visible_differences() {
+        let expected = [100, 150, 200, 255].repeat(4);
+        let mut actual = expected.clone();
+        actual[4..8].copy_from_slice(&[200, 50, 50, 255]);
+        let comparison = compare_images(&expected, &actual, &Tolerance::default());
+        assert_eq!(comparison.failing_pixels, 1);
+        assert!(!comparison.passes(&Tolerance::default()));
+        assert_eq!(&comparison.diff_image[4..8], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn clear_color_default() {
+        GoldenTest::new("clear_color_default").run(|_| {}, |_, _| {});
+    }
+
+    #[test]
+    fn clear_color_left_click() {
+        GoldenTest::new("clear_color_left_click").run(
+            |app| {
+                app.mouse_click(ElementState::Pressed, MouseButton::Left);
+            },
+            |_, _| {},
+        );
+    }
+
+    #[test]
+    fn clear_color_right_click_restores_default() {
+        // 第 0 帧左键按下，第 2 帧右键按下，最终应恢复为默认颜色
+        GoldenTest::new("clear_color_right_click").frames(3).run(
+            |_| {},
+            |app, frame| match frame {
+                0 => {
+                    app.mouse_click(ElementState::Pressed, MouseButton::Left);
+                }
+                2 => {
+                    app.mouse_click(ElementState::Pressed, MouseButton::Right);
+                }
+                _ => {}
+            },
+        );
+    }
+
+    #[test]
+    fn pipeline_triangle() {
+        GoldenTest::new("pipeline_triangle").run(
+            |app| crate::demo::add_triangle(&mut app.renderer, &app.device),
+            |_, _| {},
+        );
+    }
+
+    #[test]
+    fn pipeline_indexed_pentagon() {
+        GoldenTest::new("pipeline_indexed_pentagon").run(
+            |app| crate::demo::add_pentagon(&mut app.renderer, &app.device),
+            |_, _| {},
+        );
+    }
+
+    fn add_depth_test(app: &mut WgpuApp) {
+        crate::demo::add_depth_test_triangles(&mut app.renderer, &app.device);
+    }
+
+    #[test]
+    fn depth_near_over_far() {
+        GoldenTest::new("depth_near_over_far").run(add_depth_test, |_, _| {});
+    }
+
+    #[test]
+    fn depth_disabled_uses_submission_order() {
+        GoldenTest::new("depth_disabled").run(
+            |app| {
+                app.set_depth_config(None);
+                add_depth_test(app);
+            },
+            |_, _| {},
+        );
+    }
+
+    #[test]
+    fn depth_reverse_compare() {
+        // 清除为 0.0、比较函数用 Greater：三角形直接给出裁剪空间的深度，不经过相机投影，远处的绿色三角形留在前面
+        GoldenTest::new("depth_reverse_compare").run(
+            |app| {
+                app.set_depth_config(Some(DepthConfig {
+                    clear_depth: 0.0,
+                    compare: wgpu::CompareFunction::Greater,
+                    ..Default::default()
+                }));
+                add_depth_test(app);
+            },
+            |_, _| {},
+        );
+    }
+
+    // 反向 Z 的投影配合 Greater 比较，画面和普通深度完全一样：两次渲染和同一张参考图像比较
+    #[test]
+    fn reverse_z_projection_matches_standard_depth() {
+        for depth in [DepthConfig::default(), DepthConfig::reverse_z()] {
+            GoldenTest::new("reverse_z_crate").run(
+                |app| {
+                    app.set_depth_config(Some(depth));
+                    assert_eq!(app.camera.reverse_z, depth.is_reversed());
+                    add_lit_crate(app);
+                    app.lights.add(Light::directional(
+                        Vec3::new(-0.4, -1.0, -0.6),
+                        Vec3::ONE,
+                        2.0,
+                    ));
+                },
+                |_, _| {},
+            );
+        }
+    }
+
+    // 反向 Z 时天空画在 z = 0 上、比较函数用 GreaterEqual，仍然填满没有被物体覆盖的像素
+    #[test]
+    fn procedural_sky_with_reverse_depth() {
+        GoldenTest::new("sky_reverse_depth").run(
+            |app| {
+                let config = DepthConfig {
+                    clear_depth: 0.0,
+                    compare: wgpu::CompareFunction::Greater,
+                    ..Default::default()
+                };
+                assert_eq!(
+                    config.far_plane(),
+                    (wgpu::CompareFunction::GreaterEqual, 0.0)
+                );
+                app.set_depth_config(Some(config));
+                app.set_procedural_sky(Some(SkySettings::default()));
+                look_at_horizon(app);
+                use_aces(app);
+                add_depth_test(app);
+            },
+            |_, _| {},
+        );
+    }
+
+    #[test]
+    fn depth_with_stencil_survives_resize() {
+        GoldenTest::new("depth_stencil_resized").frames(2).run(
+            |app| {
+                app.set_depth_config(Some(DepthConfig::with_stencil()));
+                add_depth_test(app);
+            },
+            // 先缩小再恢复，深度纹理每次都要跟着重新创建，否则尺寸不一致会报错
+            |app, frame| match frame {
+                0 => app.set_window_resized(PhysicalSize::new(32, 24)),
+                _ => app.set_window_resized(PhysicalSize::new(64, 48)),
+            },
+        );
+    }
+
+    #[test]
+    fn msaa_4x_triangle() {
+        // 4x 是 WebGPU 保证所有格式都支持的采样数，边缘应当被平滑
+        GoldenTest::new("msaa_4x_triangle").run(
+            |app| {
+                assert!(app.supported_sample_counts().contains(&4));
+                assert!(app.set_sample_count(4));
+                crate::demo::add_triangle(&mut app.renderer, &app.device);
+            },
+            |_, _| {},
+        );
+    }
+
+    #[test]
+    fn msaa_switch_at_runtime_follows_resize() {
+        // 运行中切换采样数并调整尺寸，最后切回 1x，结果应当和没有开启 MSAA 时一样
+        GoldenTest::new("pipeline_triangle").frames(3).run(
+            |app| crate::demo::add_triangle(&mut app.renderer, &app.device),
+            |app, frame| match frame {
+                0 => {
+                    assert!(app.set_sample_count(4));
+                    app.set_window_resized(PhysicalSize::new(32, 24));
+                }
+                1 => app.set_window_resized(PhysicalSize::new(64, 48)),
+                _ => assert!(app.set_sample_count(1)),
+            },
+        );
+    }
+
+    #[test]
+    fn msaa_rejects_unsupported_sample_count() {
+        GoldenTest::new("pipeline_triangle").run(
+            |app| {
+                assert!(!app.set_sample_count(3));
+                assert_eq!(app.sample_count, 1);
+                crate::demo::add_triangle(&mut app.renderer, &app.device);
+            },
+            |_, _| {},
+        );
+    }
+
+    #[test]
+    fn texture_srgb_quad() {
+        GoldenTest::new("texture_srgb_quad").run(
+            |app| {
+                crate::demo::add_textured_quad(
+                    &mut app.renderer,
+                    &app.device,
+                    &app.queue,
+                    ColorSpace::Srgb,
+                )
+            },
+            |_, _| {},
+        );
+    }
+
+    #[test]
+    fn texture_linear_quad() {
+        // 线性纹理写入 sRGB 目标时会被再次编码，颜色比 sRGB 纹理更亮
+        GoldenTest::new("texture_linear_quad").run(
+            |app| {
+                crate::demo::add_textured_quad(
+                    &mut app.renderer,
+                    &app.device,
+                    &app.queue,
+                    ColorSpace::Linear,
+                )
+            },
+            |_, _| {},
+        );
+    }
+
+    // 把三角形的着色器复制到临时文件，返回文件路径，测试里可以随意改写
+    fn temp_triangle_shader(name: &str) -> PathBuf {
+        // 目录名带进程号避免同时运行的测试互相干扰，文件名固定，错误提示条上的文字才和参考图一致
+        let dir = std::env::temp_dir().join(format!("my-wgpu-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{name}.wgsl"));
+        std::fs::write(&path, include_str!("shaders/vertex_color.wgsl")).unwrap();
+        path
+    }
+
+    fn add_triangle_from(app: &mut WgpuApp, path: &PathBuf) {
+        let desc = PipelineDescriptor::new("Reload Test", Shader::from_path(path).unwrap());
+        let pipeline = app.renderer.add_pipeline(&app.device, desc);
+        let mesh = app.renderer.add_mesh(Mesh::new(
+            &app.device,
+            "Triangle",
+            crate::demo::TRIANGLE_VERTICES,
+            &[],
+        ));
+        app.renderer.add_draw(DrawCall::new(pipeline, mesh));
+    }
+
+    #[test]
+    fn shader_reload_swaps_pipeline() {
+        let path = temp_triangle_shader("swap");
+        GoldenTest::new("shader_reload_swapped").frames(2).run(
+            |app| add_triangle_from(app, &path),
+            |app, frame| {
+                if frame == 1 {
+                    let source = include_str!("shaders/vertex_color.wgsl")
+                        .replace("vec4f(in.color, 1.0)", "vec4f(1.0, 1.0, 1.0, 1.0)");
+                    std::fs::write(&path, source).unwrap();
+                    app.reload_shader(&path);
+                    assert!(app.shader_error.is_none());
+                }
+            },
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn shader_reload_error_keeps_previous_pipeline() {
+        let path = temp_triangle_shader("error");
+        GoldenTest::new("shader_reload_error").frames(2).run(
+            |app| add_triangle_from(app, &path),
+            |app, frame| {
+                if frame == 1 {
+                    let source = include_str!("shaders/vertex_color.wgsl").replace(
+                        "return vec4f(in.color, 1.0);",
+                        "return vec4f(in.color, 1.0)",
+                    );
+                    std::fs::write(&path, source).unwrap();
+                    app.reload_shader(&path);
+                    let error = app.shader_error.as_ref().expect("应当记录着色器错误");
+                    assert_eq!(error.path, path);
+                    assert_eq!(error.line, 24);
+                }
+            },
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn clear_color_release_is_ignored() {
+        GoldenTest::new("clear_color_release").run(
+            |app| {
+                app.mouse_click(ElementState::Released, MouseButton::Left);
+            },
+            |_, _| {},
+        );
+    }
+
+    #[test]
+    fn camera_cube_default_view() {
+        GoldenTest::new("camera_cube").run(
+            |app| demo::add_camera_cube(&mut app.renderer, &app.device, &app.camera_binding),
+            |_, _| {},
+        );
+    }
+
+    #[test]
+    fn camera_orbit_drag_and_zoom() {
+        GoldenTest::new("camera_cube_orbit").frames(2).run(
+            |app| demo::add_camera_cube(&mut app.renderer, &app.device, &app.camera_binding),
+            |app, frame| {
+                if frame == 1 {
+                    // 左键拖动旋转，松开后右键点击恢复默认的清除颜色，再向上滚动两行拉近
+                    app.cursor_move(PhysicalPosition::new(10.0, 10.0));
+                    app.mouse_click(ElementState::Pressed, MouseButton::Left);
+                    app.cursor_move(PhysicalPosition::new(60.0, 30.0));
+                    app.mouse_click(ElementState::Released, MouseButton::Left);
+                    app.mouse_click(ElementState::Pressed, MouseButton::Right);
+                    app.mouse_wheel(
+                        MouseScrollDelta::LineDelta(0.0, 2.0),
+                        winit::event::TouchPhase::Moved,
+                    );
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn instancing_grid_with_partial_update_and_growth() {
+        let cubes = std::cell::Cell::new(None);
+        GoldenTest::new("instancing_grid").frames(2).run(
+            |app| {
+                let instances = demo::instance_grid(4, 0.6);
+                cubes.set(Some(demo::add_instanced_cubes(
+                    &mut app.renderer,
+                    &app.device,
+                    &app.camera_binding,
+                    &instances,
+                )));
+            },
+            |app, frame| {
+                if frame == 1 {
+                    let buffer = app.renderer.instance_buffer_mut(cubes.get().unwrap());
+                    // 只修改第一个实例：变成白色并用自定义属性放大
+                    buffer.update(
+                        0,
+                        &[Instance {
+                            position: Vec3::new(-0.9, 0.0, -0.9),
+                            scale: Vec3::splat(0.36),
+                            custom: [0.5, 0.0, 0.0, 0.0],
+                            ..Default::default()
+                        }],
+                    );
+                    // 超过容量，渲染前会扩容
+                    buffer.extend(
+                        &demo::instance_grid(2, 0.6)
+                            .iter()
+                            .map(|i| Instance {
+                                position: i.position + Vec3::Y * 0.6,
+                                ..*i
+                            })
+                            .collect::<Vec<_>>(),
+                    );
+                    assert_eq!(buffer.len(), 20);
+                }
+            },
+        );
+    }
+
+    // 带材质的木箱模型，用到 cube.obj 的测试都从它开始，光源由各个测试自己添加
+    fn add_lit_crate(app: &mut WgpuApp) {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/cube.obj");
+        demo::add_obj_model(
+            &mut app.renderer,
+            &app.device,
+            &app.queue,
+            &app.camera_binding,
+            &app.lights,
+            path,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn obj_model_with_materials() {
+        GoldenTest::new("obj_model").run(
+            |app| {
+                add_lit_crate(app);
+                app.lights.add(Light::directional(
+                    Vec3::new(-0.4, -1.0, -0.6),
+                    Vec3::ONE,
+                    1.0,
+                ));
+            },
+            |_, _| {},
+        );
+    }
+
+    // 第一帧三种光源都在；第二帧移动点光源、删除聚光灯
+    #[test]
+    fn lights_added_moved_and_removed() {
+        let ids = std::cell::RefCell::new(Vec::new());
+        GoldenTest::new("lights").frames(2).run(
+            |app| {
+                add_lit_crate(app);
+                let lights = demo::add_demo_lights(app.lights_mut());
+                demo::cast_demo_shadows(app.lights_mut(), &lights);
+                *ids.borrow_mut() = lights;
+            },
+            |app, frame| {
+                if frame == 1 {
+                    let ids = ids.borrow();
+                    let lights = app.lights_mut();
+                    lights.get_mut(ids[1]).unwrap().position = Vec3::new(-1.2, 0.6, 1.0);
+                    assert!(lights.remove(ids[2]).is_some());
+                    assert!(lights.remove(ids[2]).is_none());
+                    assert_eq!(lights.len(), 2);
+                }
+            },
+        );
+    }
+
+    // 很亮的点光源照在模型上，高光远超 1.0，比较各个色调映射算子
+    #[test]
+    fn tonemap_operators() {
+        for (name, tonemapper) in [
+            ("tonemap_none", Tonemapper::None),
+            ("tonemap_reinhard", Tonemapper::Reinhard),
+            ("tonemap_aces", Tonemapper::Aces),
+            ("tonemap_agx", Tonemapper::AgX),
+        ] {
+            GoldenTest::new(name).run(
+                |app| {
+                    add_lit_crate(app);
+                    app.lights.add(Light::point(
+                        Vec3::new(0.8, 1.2, 1.2),
+                        Vec3::new(1.0, 0.9, 0.7),
+                        20.0,
+                        5.0,
+                    ));
+                    app.set_tonemap_settings(TonemapSettings {
+                        tonemapper,
+                        exposure: 0.5,
+                        ..Default::default()
+                    });
+                },
+                |_, _| {},
+            );
+        }
+    }
+
+    // 只有很暗的环境光，自动曝光立即适应后画面应当接近正常亮度
+    #[test]
+    fn auto_exposure_brightens_dark_scene() {
+        GoldenTest::new("auto_exposure_dark").run(
+            |app| {
+                add_lit_crate(app);
+                app.lights.set_ambient(Vec3::splat(0.02));
+                app.clear_color = wgpu::Color::BLACK;
+                app.set_tonemap_settings(TonemapSettings {
+                    tonemapper: Tonemapper::Aces,
+                    auto_exposure: true,
+                    ..Default::default()
+                });
+                app.set_auto_exposure_settings(AutoExposureSettings {
+                    speed_up: f32::INFINITY,
+                    speed_down: f32::INFINITY,
+                    ..Default::default()
+                });
+            },
+            |_, _| {},
+        );
+    }
+
+    // 平行光的级联阴影和聚光灯的阴影都落在地面上
+    #[test]
+    fn shadows_directional_and_spot() {
+        GoldenTest::new("shadows").run(
+            |app| {
+                add_lit_crate(app);
+                app.lights.add(
+                    Light::directional(Vec3::new(-0.6, -1.0, -0.3), Vec3::ONE, 1.0).with_shadows(),
+                );
+                app.lights.add(
+                    Light::spot(
+                        Vec3::new(1.5, 2.0, 1.0),
+                        Vec3::new(-1.0, -1.2, -0.8),
+                        Vec3::new(1.0, 0.6, 0.2),
+                        6.0,
+                        8.0,
+                        20f32.to_radians(),
+                        35f32.to_radians(),
+                    )
+                    .with_shadows(),
+                );
+            },
+            |_, _| {},
+        );
+    }
+
+    // 调试视图按级联着色，第二帧减少到两个级联
+    #[test]
+    fn shadow_cascade_debug_view() {
+        GoldenTest::new("shadow_cascades").frames(2).run(
+            |app| {
+                add_lit_crate(app);
+                app.lights.add(
+                    Light::directional(Vec3::new(-0.6, -1.0, -0.3), Vec3::ONE, 1.0).with_shadows(),
+                );
+                app.set_shadow_settings(ShadowSettings {
+                    debug_cascades: true,
+                    max_distance: 6.0,
+                    split_lambda: 0.0,
+                    ..Default::default()
+                });
+            },
+            |app, frame| {
+                if frame == 1 {
+                    let settings = app.lights.shadow_settings();
+                    app.set_shadow_settings(ShadowSettings {
+                        cascade_count: 2,
+                        ..settings
+                    });
+                }
+            },
+        );
+    }
+
+    // .gltf 的缓冲区是 data URI、贴图是外部文件，.glb 全部内嵌，渲染结果应当一样
+    #[test]
+    fn gltf_scene_external_and_embedded() {
+        for file in ["scene.gltf", "scene.glb"] {
+            GoldenTest::new("gltf_scene").run(
+                |app| {
+                    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                        .join("assets")
+                        .join(file);
+                    app.load_scene(&path).unwrap();
+                },
+                |_, _| {},
+            );
+        }
+    }
+
+    // 运行时修改共享材质：第二帧把红色金属改成粗糙的金色，用同一个材质的图元一起变化
+    #[test]
+    fn pbr_material_edited_at_runtime() {
+        GoldenTest::new("pbr_material_edit").frames(2).run(
+            |app| {
+                let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/scene.gltf");
+                app.load_scene(&PathBuf::from(path)).unwrap();
+            },
+            |app, frame| {
+                if frame == 1 {
+                    let materials = app.materials_mut();
+                    let id = materials.find("red metal").unwrap();
+                    let params = materials.params_mut(id).unwrap();
+                    params.base_color = [1.0, 0.75, 0.3, 1.0];
+                    params.roughness = 0.7;
+                }
+            },
+        );
+    }
+
+    // 基于图像的光照：天空盒在场景后面，金属表面反射出环境，环境光来自辐照度贴图
+    #[test]
+    fn ibl_environment() {
+        GoldenTest::new("ibl_environment").run(
+            |app| {
+                let assets = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets");
+                app.load_scene(&assets.join("scene.gltf")).unwrap();
+                app.load_environment(&assets.join("sky.hdr")).unwrap();
+                use_aces(app);
+            },
+            |_, _| {},
+        );
+    }
+
+    // 把相机放低、看向地平线，这样画面上半部分是天空
+    fn look_at_horizon(app: &mut WgpuApp) {
+        app.camera.eye = Vec3::new(0.0, 0.8, 3.5);
+        app.camera.target = Vec3::new(0.0, 0.7, 0.0);
+        app.camera_controller = CameraController::Orbit(OrbitController::from_camera(&app.camera));
+    }
+
+    // 默认的色调映射是直接截断，HDR 相关的图像用 ACES 压缩高光，参考图像不随默认值变化
+    fn use_aces(app: &mut WgpuApp) {
+        app.set_tonemap_settings(TonemapSettings {
+            tonemapper: Tonemapper::Aces,
+            ..Default::default()
+        });
+    }
+
+    // 上午的程序化天空：天顶偏蓝、地平线发白，平行光跟着太阳投下阴影
+    #[test]
+    fn procedural_sky_day() {
+        GoldenTest::new("procedural_sky_day").run(
+            |app| {
+                add_lit_crate(app);
+                look_at_horizon(app);
+                use_aces(app);
+                app.set_procedural_sky(Some(SkySettings::default()));
+            },
+            |_, _| {},
+        );
+    }
+
+    // 第一帧是正午，第二帧调到日落：太阳在物体正后方，天空和平行光都变成橙红色
+    #[test]
+    fn procedural_sky_sunset() {
+        GoldenTest::new("procedural_sky_sunset").frames(2).run(
+            |app| {
+                add_lit_crate(app);
+                look_at_horizon(app);
+                use_aces(app);
+                app.set_procedural_sky(Some(SkySettings {
+                    time_of_day: 12.0,
+                    // 太阳从 +Z 升起、向 -Z 落下
+                    sun_azimuth: std::f32::consts::FRAC_PI_2,
+                    ..Default::default()
+                }));
+            },
+            |app, frame| {
+                if frame == 1 {
+                    let settings = app.sky_settings().unwrap();
+                    app.set_procedural_sky(Some(SkySettings {
+                        time_of_day: 17.7,
+                        ..settings
+                    }));
+                }
+            },
+        );
+    }
+
+    // 粒子喷泉：固定步长模拟一秒，火花在物体上方散开、在重力作用下落回，加色混合叠加发亮
+    #[test]
+    fn particles_fountain() {
+        GoldenTest::new("particles_fountain").frames(30).run(
+            |app| {
+                add_lit_crate(app);
+                app.particles_mut().fixed_time_step = Some(1.0 / 30.0);
+                app.add_particle_fountain(4000);
+                use_aces(app);
+            },
+            |_, _| {},
+        );
+    }
+
+    // 泛光和 FXAA：强光照亮的立方体顶面超过阈值，在周围发出光晕，边缘的锯齿被柔化
+    #[test]
+    fn post_bloom_and_fxaa() {
+        GoldenTest::new("post_bloom_fxaa").run(
+            |app| {
+                add_lit_crate(app);
+                app.lights.add(Light::directional(
+                    Vec3::new(-0.3, -1.0, -0.4),
+                    Vec3::ONE,
+                    3.0,
+                ));
+                let mut settings = app.post_settings().clone();
+                settings.bloom.enabled = true;
+                settings.fxaa.enabled = true;
+                app.set_post_settings(settings);
+                use_aces(app);
+            },
+            |_, _| {},
+        );
+    }
+
+    // 文字：三种对齐方式和颜色、默认字体链里的中文，加上着色器错误提示条上的错误信息，所有字形一次绘制
+    #[test]
+    fn text_alignment_and_error_overlay() {
+        GoldenTest {
+            width: 160,
+            height: 120,
+            ..GoldenTest::new("text")
+        }
+        .run(
+            |app| {
+                app.shader_error = Some(ShaderError {
+                    path: "shaders/broken.wgsl".into(),
+                    line: 3,
+                    column: 7,
+                    message: "unknown type".to_string(),
+                });
+            },
+            |app, _| {
+                app.draw_text(TextSection::new("Left", Vec2::new(4.0, 30.0)).with_size(18.0));
+                app.draw_text(
+                    TextSection::new("中文字体 wgpu", Vec2::new(4.0, 100.0))
+                        .with_size(14.0)
+                        .with_color([0.6, 0.8, 1.0, 1.0]),
+                );
+                app.draw_text(
+                    TextSection::new("Center\nwgpu", Vec2::new(80.0, 52.0))
+                        .with_size(14.0)
+                        .with_color([1.0, 0.8, 0.2, 1.0])
+                        .with_align(TextAlign::Center),
+                );
+                app.draw_text(
+                    TextSection::new("Right", Vec2::new(156.0, 96.0))
+                        .with_size(12.0)
+                        .with_color([0.3, 1.0, 0.4, 0.8])
+                        .with_align(TextAlign::Right),
+                );
+            },
+        );
+    }
+
+    // 中文窗口标题：不加载任何字体，只用默认字体链，汉字来自内嵌的中文子集字体，英文和数字来自等宽字体
+    #[test]
+    fn text_window_title_with_default_fonts() {
+        GoldenTest {
+            width: 200,
+            height: 48,
+            ..GoldenTest::new("text_window_title")
+        }
+        .run(
+            |_| {},
+            |app, _| {
+                app.draw_text(
+                    TextSection::new("第二章 - wgpu 27", Vec2::new(6.0, 12.0)).with_size(20.0),
+                );
+            },
+        );
+    }
+
+    // 精灵：旋转、缩放、着色和纹理坐标矩形，后提交但层级高的红色半透明矩形盖在层级低的棋盘格上面
+    #[test]
+    fn sprites_layers_and_transforms() {
+        GoldenTest {
+            width: 128,
+            height: 96,
+            ..GoldenTest::new("sprites")
+        }
+        .run(
+            |_| {},
+            |app, _| {
+                let checker = image::RgbaImage::from_fn(8, 8, |x, y| {
+                    if (x / 2 + y / 2) % 2 == 0 {
+                        image::Rgba([255, 255, 255, 255])
+                    } else {
+                        image::Rgba([40, 40, 200, 255])
+                    }
+                });
+                let color_space = ColorSpace::matching(app.config.format);
+                let checker = Texture::from_image(
+                    &app.device,
+                    &app.queue,
+                    &image::DynamicImage::ImageRgba8(checker),
+                    "Checker",
+                    color_space,
+                );
+                let white =
+                    Texture::solid_color(&app.device, &app.queue, [255; 4], color_space, "White");
+                let checker = app.add_sprite_texture(&checker);
+                let white = app.add_sprite_texture(&white);
+                app.draw_sprite(
+                    Sprite::new(white, Vec2::new(64.0, 72.0))
+                        .with_scale(Vec2::new(48.0, 16.0))
+                        .with_tint([1.0, 0.1, 0.1, 0.7])
+                        .with_layer(1),
+                );
+                app.draw_sprite(
+                    Sprite::new(checker, Vec2::new(30.0, 30.0))
+                        .with_scale(Vec2::splat(4.0))
+                        .with_rotation(0.4),
+                );
+                app.draw_sprite(
+                    Sprite::new(checker, Vec2::new(96.0, 30.0))
+                        .with_scale(Vec2::splat(6.0))
+                        .with_uv_rect([0.0, 0.0, 0.5, 0.5])
+                        .with_tint([1.0, 0.6, 0.2, 1.0]),
+                );
+                app.draw_sprite(
+                    Sprite::new(checker, Vec2::new(64.0, 68.0)).with_scale(Vec2::splat(3.0)),
+                );
+            },
+        );
+    }
+
+    // 调试绘制：第一帧加的单帧线段在第二帧消失，显示几秒的线段保留；
+    // 包围盒、球和视锥被立方体挡住的部分看不见，关闭深度测试的坐标轴画在立方体上面
+    #[test]
+    fn debug_draw_gizmos_and_lifetimes() {
+        GoldenTest {
+            width: 128,
+            height: 96,
+            ..GoldenTest::new("debug_draw")
+        }
+        .frames(2)
+        .run(add_lit_crate, |app, frame| {
+            if frame == 0 {
+                app.debug_draw().line(
+                    Vec3::new(-2.0, 1.0, 0.0),
+                    Vec3::new(2.0, 1.0, 0.0),
+                    [1.0; 4],
+                );
+                app.debug_draw()
+                    .with_lifetime(DebugLifetime::Seconds(60.0))
+                    .arrow(
+                        Vec3::new(-1.0, 0.9, 0.0),
+                        Vec3::new(1.0, 0.9, 0.0),
+                        [1.0, 0.0, 1.0, 1.0],
+                    );
+                return;
+            }
+            let camera = Mat4::perspective_rh(0.8, 1.3, 0.2, 0.8)
+                * Mat4::look_at_rh(
+                    Vec3::new(-1.0, 0.2, 0.8),
+                    Vec3::new(-0.2, 0.0, 0.0),
+                    Vec3::Y,
+                );
+            app.debug_draw()
+                .grid(Vec3::new(0.0, -0.5, 0.0), 0.5, 6, [0.5, 0.5, 0.5, 1.0])
+                .aabb(Vec3::splat(-0.55), Vec3::splat(0.55), [1.0, 1.0, 0.0, 1.0])
+                .sphere(Vec3::new(0.9, 0.0, -0.3), 0.35, [0.0, 1.0, 1.0, 1.0])
+                .frustum(camera, [1.0, 0.5, 0.0, 1.0]);
+            app.debug_draw()
+                .with_depth_test(false)
+                .axes(Mat4::IDENTITY, 0.8);
+        });
+    }
+}
@@ -0,0 +1,252 @@
+// HDR 渲染目标和色调映射
+// 场景先渲染到 Rgba16Float 的中间纹理上，颜色可以超过 1.0 而不会被截断，
+// 最后由一个全屏的色调映射通道乘以曝光、压缩到 [0, 1]，再写入展示平面（或离屏纹理）。
+// 开启了后处理效果时，色调映射读取的是后处理的输出，而不是场景纹理本身。
+// 开启自动曝光时，曝光由 exposure 模块在 GPU 上算出的平均亮度决定，手动曝光只作为补偿。
+// 展示平面的格式不一定是 sRGB，不是的时候在着色器里手动做 sRGB 编码，保证各个平台的伽马一致。
+
+use wgpu::util::DeviceExt;
+
+use crate::pipeline::{Pipeline, PipelineDescriptor, Shader, TargetState};
+use crate::texture::{BindingKind, BindingLayout};
+
+// 中间渲染目标的格式，场景里的所有管线都以它为颜色格式
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// 色调映射算子，和着色器里的常量一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tonemapper {
+    // 不做映射，超过 1.0 的部分直接截断；默认值，没有超过 1.0 的颜色时和没有 HDR 之前的输出一样
+    #[default]
+    None = 0,
+    // x / (1 + x)，简单但高光偏灰
+    Reinhard = 1,
+    // ACES 电影曲线的拟合，对比度高，高光偏暖
+    Aces = 2,
+    // AgX，高光向白色过渡更自然，饱和色不容易偏色
+    AgX = 3,
+}
+
+impl Tonemapper {
+    pub const ALL: [Tonemapper; 4] = [
+        Tonemapper::None,
+        Tonemapper::Reinhard,
+        Tonemapper::Aces,
+        Tonemapper::AgX,
+    ];
+
+    // 按 ALL 的顺序切换到下一个算子
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&t| t == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TonemapSettings {
+    pub tonemapper: Tonemapper,
+    // 曝光补偿，单位是档（EV），每加 1 亮度翻倍
+    pub exposure: f32,
+    // 是否根据画面的平均亮度自动曝光，把平均亮度映射到中灰
+    pub auto_exposure: bool,
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self {
+            tonemapper: Tonemapper::default(),
+            exposure: 0.0,
+            auto_exposure: false,
+        }
+    }
+}
+
+// 和着色器里的 TonemapUniform 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TonemapUniform {
+    // 线性的曝光倍数，等于 2^exposure
+    exposure: f32,
+    tonemapper: u32,
+    // 输出格式不是 sRGB 时为 1，在着色器里手动编码
+    encode_srgb: u32,
+    auto_exposure: u32,
+}
+
+impl TonemapUniform {
+    fn new(settings: &TonemapSettings, output_format: wgpu::TextureFormat) -> Self {
+        Self {
+            exposure: settings.exposure.exp2(),
+            tonemapper: settings.tonemapper as u32,
+            encode_srgb: (!output_format.is_srgb()) as u32,
+            auto_exposure: settings.auto_exposure as u32,
+        }
+    }
+}
+
+// HDR 中间纹理，尺寸跟随展示平面，开启 MSAA 时作为解析目标
+pub struct HdrTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl HdrTarget {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HDR Color Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            // 之后的色调映射通道要读取它
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { texture, view }
+    }
+}
+
+// 把 HDR 纹理映射到展示平面的全屏通道
+pub struct TonemapPass {
+    pub settings: TonemapSettings,
+    output_format: wgpu::TextureFormat,
+    pipeline: Pipeline,
+    layout: BindingLayout,
+    buffer: wgpu::Buffer,
+    // 自动曝光算出的平均亮度
+    luminance: wgpu::Buffer,
+}
+
+impl TonemapPass {
+    pub fn new(
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        luminance: &wgpu::Buffer,
+        settings: TonemapSettings,
+    ) -> Self {
+        let layout = BindingLayout::new(
+            device,
+            "Tonemap Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::TEXTURE_2D),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::Uniform),
+                (
+                    wgpu::ShaderStages::FRAGMENT,
+                    BindingKind::Storage { read_only: true },
+                ),
+            ],
+        );
+        let mut desc = PipelineDescriptor::new(
+            "Tonemap Pipeline",
+            Shader::from_wgsl("tonemap.wgsl", include_str!("shaders/tonemap.wgsl")),
+        );
+        // 全屏三角形由 vertex_index 生成
+        desc.vertex_layouts.clear();
+        desc.bind_group_layouts = vec![layout.layout.clone()];
+        desc.cull_mode = None;
+        let targets = TargetState {
+            color_format: output_format,
+            depth_format: None,
+            depth_compare: wgpu::CompareFunction::Always,
+            sample_count: 1,
+        };
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tonemap Uniform Buffer"),
+            contents: bytemuck::bytes_of(&TonemapUniform::new(&settings, output_format)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            settings,
+            output_format,
+            pipeline: Pipeline::new(device, desc, &targets),
+            layout,
+            buffer,
+            luminance: luminance.clone(),
+        }
+    }
+
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: TonemapSettings) {
+        self.settings = settings;
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::bytes_of(&TonemapUniform::new(&settings, self.output_format)),
+        );
+    }
+
+    // 在 encoder 里开启一个新的渲染通道，把 input 映射后写入 output；
+    // 输入可能是渲染图每帧分配的临时纹理，绑定组在这里创建
+    pub fn run(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+    ) {
+        let bind_group = self.layout.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(input),
+                self.buffer.as_entire_binding(),
+                self.luminance.as_entire_binding(),
+            ],
+        );
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tonemap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    // 全屏三角形会覆盖每一个像素，清除只是为了不依赖上一帧的内容
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_cycles_through_all_operators() {
+        let mut t = Tonemapper::None;
+        for expected in [
+            Tonemapper::Reinhard,
+            Tonemapper::Aces,
+            Tonemapper::AgX,
+            Tonemapper::None,
+        ] {
+            t = t.next();
+            assert_eq!(t, expected);
+        }
+    }
+
+    #[test]
+    fn srgb_encoding_only_for_linear_outputs() {
+        let settings = TonemapSettings {
+            tonemapper: Tonemapper::AgX,
+            exposure: 1.0,
+            auto_exposure: false,
+        };
+        let srgb = TonemapUniform::new(&settings, wgpu::TextureFormat::Bgra8UnormSrgb);
+        let linear = TonemapUniform::new(&settings, wgpu::TextureFormat::Bgra8Unorm);
+        assert_eq!(srgb.encode_srgb, 0);
+        assert_eq!(linear.encode_srgb, 1);
+        assert_eq!(srgb.exposure, 2.0);
+        assert_eq!(srgb.tonemapper, 3);
+    }
+}
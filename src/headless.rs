@@ -0,0 +1,286 @@
+// 无窗口（headless）离屏渲染
+// 没有 winit::Window 和 wgpu::Surface 时，渲染到自有的 wgpu::Texture 上，
+// 再通过映射缓冲区把像素拷回 CPU，最后写成 PNG 文件。
+// 适用于 CI、服务器等没有显示器的环境。
+
+use std::path::Path;
+
+// 离屏渲染使用的纹理格式，和常见的 sRGB 展示平面保持一致
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+// 创建无窗口应用时的选项
+#[derive(Debug, Clone, Copy)]
+pub struct HeadlessOptions {
+    // 渲染目标的宽高
+    pub width: u32,
+    pub height: u32,
+    // 是否强制使用回退（软件）适配器，CI 上通常只有它
+    pub force_fallback_adapter: bool,
+    // 是否在系统里查找中文字体放进默认字体链，黄金图像测试关掉它，结果不随机器上装的字体变化
+    pub system_fonts: bool,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            force_fallback_adapter: true,
+            system_fonts: true,
+        }
+    }
+}
+
+// 创建无窗口应用时可能出现的错误
+#[derive(Debug)]
+pub enum HeadlessError {
+    // 找不到满足条件的适配器
+    Adapter(wgpu::RequestAdapterError),
+    // 适配器无法创建设备
+    Device(wgpu::RequestDeviceError),
+}
+
+impl std::fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeadlessError::Adapter(e) => write!(f, "请求适配器失败: {e}"),
+            HeadlessError::Device(e) => write!(f, "请求设备失败: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<wgpu::RequestAdapterError> for HeadlessError {
+    fn from(e: wgpu::RequestAdapterError) -> Self {
+        HeadlessError::Adapter(e)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for HeadlessError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        HeadlessError::Device(e)
+    }
+}
+
+// 把渲染结果读回 CPU 时可能出现的错误
+#[derive(Debug)]
+pub enum ReadbackError {
+    // 有窗口的应用渲染到展示平面上，没有可以回读的离屏目标
+    NoOffscreenTarget,
+    // 只支持 Rgba8 与 Bgra8 系列格式
+    UnsupportedFormat(wgpu::TextureFormat),
+    // 映射回读缓冲区失败
+    Map(wgpu::BufferAsyncError),
+}
+
+impl std::fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadbackError::NoOffscreenTarget => write!(f, "只有无窗口模式的应用可以回读像素"),
+            ReadbackError::UnsupportedFormat(format) => {
+                write!(f, "不支持回读的纹理格式: {format:?}")
+            }
+            ReadbackError::Map(e) => write!(f, "映射回读缓冲区失败: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadbackError {}
+
+impl From<wgpu::BufferAsyncError> for ReadbackError {
+    fn from(e: wgpu::BufferAsyncError) -> Self {
+        ReadbackError::Map(e)
+    }
+}
+
+// save_png 返回 image 的错误类型，回读失败也要能表示成它
+impl From<ReadbackError> for image::ImageError {
+    fn from(e: ReadbackError) -> Self {
+        let message = e.to_string();
+        match e {
+            ReadbackError::UnsupportedFormat(_) => image::ImageError::Unsupported(
+                image::error::UnsupportedError::from_format_and_kind(
+                    image::error::ImageFormatHint::Unknown,
+                    image::error::UnsupportedErrorKind::GenericFeature(message),
+                ),
+            ),
+            _ => image::ImageError::Parameter(image::error::ParameterError::from_kind(
+                image::error::ParameterErrorKind::Generic(message),
+            )),
+        }
+    }
+}
+
+// 离屏渲染目标：代替 surface.get_current_texture() 返回的纹理
+pub struct OffscreenTarget {
+    pub texture: wgpu::Texture,
+}
+
+impl OffscreenTarget {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            // RENDER_ATTACHMENT: 作为渲染目标；COPY_SRC: 用于回读到缓冲区
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        Self { texture }
+    }
+}
+
+// 纹理拷贝到缓冲区时，每行的字节数必须是 COPY_BYTES_PER_ROW_ALIGNMENT（256）的整数倍
+pub fn padded_bytes_per_row(unpadded_bytes_per_row: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    unpadded_bytes_per_row.div_ceil(align) * align
+}
+
+// 把一张 2D 颜色纹理读回 CPU，返回紧密排列的 RGBA8 像素
+// 只支持 Rgba8 与 Bgra8 系列格式，Bgra8 会被转换为 RGBA 顺序，其他格式返回错误
+pub fn read_texture_rgba8(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, ReadbackError> {
+    let format = texture.format();
+    let is_bgra = match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        other => return Err(ReadbackError::UnsupportedFormat(other)),
+    };
+    let width = texture.width();
+    let height = texture.height();
+    let unpadded = width * 4;
+    let padded = padded_bytes_per_row(unpadded);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback buffer"),
+        size: (padded * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    // map_async 是异步的，需要 poll 设备直到回调被调用
+    let slice = buffer.slice(..);
+    let (tx, rx) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = tx.send(result);
+    });
+    device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+    rx.recv().unwrap()?;
+
+    // 去掉每行末尾的对齐填充
+    let mut pixels = Vec::with_capacity((unpadded * height) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded as usize) {
+            pixels.extend_from_slice(&row[..unpadded as usize]);
+        }
+    }
+    buffer.unmap();
+
+    if is_bgra {
+        for px in pixels.chunks_exact_mut(4) {
+            px.swap(0, 2);
+        }
+    }
+    Ok(pixels)
+}
+
+// 把 RGBA8 像素写成 PNG 文件
+pub fn save_png(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> image::ImageResult<()> {
+    image::save_buffer(path, rgba, width, height, image::ExtendedColorType::Rgba8)
+}
+
+// 测试用的无窗口应用，所有需要 GPU 的测试都通过它创建。
+// 创建失败时测试直接失败，免得没有适配器的机器上测试什么都没渲染就通过了；
+// 只有显式设置 SKIP_GPU_TESTS=1 时才返回 None，由调用的测试跳过
+#[cfg(test)]
+pub(crate) fn test_app(options: HeadlessOptions) -> Option<crate::WgpuApp> {
+    match pollster::block_on(crate::WgpuApp::new_headless(options)) {
+        Ok(app) => Some(app),
+        Err(e) if std::env::var_os("SKIP_GPU_TESTS").is_some_and(|v| v == "1") => {
+            eprintln!("SKIP_GPU_TESTS=1，跳过需要 GPU 的测试: {e}");
+            None
+        }
+        Err(e) => {
+            panic!("无法创建无窗口应用: {e}（没有可用的适配器时可以设置 SKIP_GPU_TESTS=1 跳过）")
+        }
+    }
+}
+
+// 只需要设备、队列和 render() 的测试用最小的渲染目标
+#[cfg(test)]
+pub(crate) fn test_device_app() -> Option<crate::WgpuApp> {
+    test_app(HeadlessOptions {
+        width: 16,
+        height: 16,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_readback_format_is_an_error() {
+        let Some(app) = test_device_app() else {
+            return;
+        };
+        let texture = app.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HDR readback"),
+            size: wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        assert!(matches!(
+            read_texture_rgba8(&app.device, &app.queue, &texture),
+            Err(ReadbackError::UnsupportedFormat(
+                wgpu::TextureFormat::Rgba16Float
+            ))
+        ));
+        // 回读失败变成 image 的错误，save_png 不会让程序崩溃
+        let error: image::ImageError = ReadbackError::NoOffscreenTarget.into();
+        assert!(matches!(error, image::ImageError::Parameter(_)));
+    }
+}
@@ -0,0 +1,713 @@
+// 基于图像的光照（IBL）
+// 读取等距柱状投影的 .hdr 全景图，在 GPU 上用计算着色器依次生成：
+// 环境立方体贴图和它的 mip 链（天空盒和后面的采样都用它）、漫反射用的辐照度贴图、
+// 镜面反射用的预过滤贴图（每一级 mip 对应一个粗糙度），以及和环境无关的 BRDF 查找表。
+// 这些纹理在创建时就按固定尺寸分配好，绑定组会被克隆进绘制调用，所以换环境时只重新写入内容；
+// 生成用的管线和 BRDF 查找表也在创建时准备好，换环境时不再重新编译。
+// 没有加载环境时 enabled 为 0，PBR 着色器退回到光源的常量环境光，也不绘制天空盒。
+
+use std::path::Path;
+
+use wgpu::util::DeviceExt;
+
+use crate::depth;
+use crate::pipeline::{Pipeline, PipelineDescriptor, Shader, TargetState};
+use crate::texture::{BindingKind, BindingLayout, ColorSpace, Texture, TextureError};
+
+// 环境立方体贴图每个面的边长
+pub const ENVIRONMENT_SIZE: u32 = 256;
+// 辐照度变化很平缓，很小的贴图就够了
+pub const IRRADIANCE_SIZE: u32 = 32;
+pub const PREFILTERED_SIZE: u32 = 128;
+// 预过滤贴图的 mip 数量，第 0 级粗糙度为 0，最后一级粗糙度为 1
+pub const PREFILTERED_MIPS: u32 = 5;
+pub const BRDF_LUT_SIZE: u32 = 128;
+// 立方体贴图的格式，可以作为存储纹理写入
+const IBL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const WORKGROUP_SIZE: u32 = 8;
+// 每个纹素的采样数
+const IRRADIANCE_SAMPLES: u32 = 256;
+const PREFILTER_SAMPLES: u32 = 128;
+
+// 和着色器里的 EnvironmentUniform 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct EnvironmentUniform {
+    // 环境光的强度倍数
+    intensity: f32,
+    // 预过滤贴图最后一级 mip，粗糙度乘以它就是采样的 mip 级别
+    max_lod: f32,
+    enabled: u32,
+    _padding: u32,
+}
+
+// 和 ibl_filter.wgsl 里的 FilterParams 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FilterParams {
+    roughness: f32,
+    sample_count: u32,
+    source_size: f32,
+    _padding: f32,
+}
+
+// 创建可以被计算着色器写入、被片元着色器采样的立方体贴图
+fn create_cube_texture(
+    device: &wgpu::Device,
+    label: &str,
+    size: u32,
+    mips: u32,
+    extra_usage: wgpu::TextureUsages,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        mip_level_count: mips,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: IBL_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::STORAGE_BINDING
+            | extra_usage,
+        view_formats: &[],
+    })
+}
+
+// 立方体贴图的 mip 范围作为立方体视图，采样时使用
+fn cube_view(texture: &wgpu::Texture, mips: std::ops::Range<u32>) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        base_mip_level: mips.start,
+        mip_level_count: Some(mips.end - mips.start),
+        ..Default::default()
+    })
+}
+
+// 立方体贴图的一级 mip 作为 6 层的 2D 数组视图，存储纹理不能是立方体视图
+fn storage_view(texture: &wgpu::Texture, mip: u32) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        base_mip_level: mip,
+        mip_level_count: Some(1),
+        ..Default::default()
+    })
+}
+
+// 计算着色器输出的存储纹理绑定
+fn storage_binding(dimension: wgpu::TextureViewDimension) -> BindingKind {
+    BindingKind::StorageTexture {
+        access: wgpu::StorageTextureAccess::WriteOnly,
+        format: IBL_FORMAT,
+        dimension,
+    }
+}
+
+fn create_compute_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &BindingLayout,
+    module: &wgpu::ShaderModule,
+    entry_point: &str,
+) -> wgpu::ComputePipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts: &[&layout.layout],
+        push_constant_ranges: &[],
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(label),
+        layout: Some(&pipeline_layout),
+        module,
+        entry_point: Some(entry_point),
+        compilation_options: Default::default(),
+        cache: None,
+    })
+}
+
+// 第 mip 级的边长，最小为 1
+fn mip_size(size: u32, mip: u32) -> u32 {
+    (size >> mip).max(1)
+}
+
+// 一次调度覆盖立方体的 6 个面
+fn dispatch_cube(pass: &mut wgpu::ComputePass<'_>, size: u32) {
+    let groups = size.div_ceil(WORKGROUP_SIZE);
+    pass.dispatch_workgroups(groups, groups, 6);
+}
+
+// 生成 IBL 纹理用到的绑定组布局和管线，和具体的环境无关，
+// 在 Environment::new 里创建一次，换环境时直接复用，不用重新编译着色器
+struct IblPipelines {
+    equirect_layout: BindingLayout,
+    equirect: wgpu::ComputePipeline,
+    // 经度方向要能绕回去，不然接缝处会出现一条线
+    equirect_sampler: wgpu::Sampler,
+    downsample_layout: BindingLayout,
+    downsample: Pipeline,
+    // 每个面一个 uniform 缓冲区，内容是面的序号
+    downsample_faces: Vec<wgpu::Buffer>,
+    filter_layout: BindingLayout,
+    irradiance: wgpu::ComputePipeline,
+    prefilter: wgpu::ComputePipeline,
+}
+
+impl IblPipelines {
+    fn new(device: &wgpu::Device) -> Self {
+        let cube = BindingKind::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            dimension: wgpu::TextureViewDimension::Cube,
+        };
+
+        let equirect_layout = BindingLayout::new(
+            device,
+            "Equirect To Cube Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::COMPUTE, BindingKind::TEXTURE_2D),
+                (wgpu::ShaderStages::COMPUTE, BindingKind::FILTERING_SAMPLER),
+                (
+                    wgpu::ShaderStages::COMPUTE,
+                    storage_binding(wgpu::TextureViewDimension::D2Array),
+                ),
+            ],
+        );
+        let module = Shader::from_wgsl(
+            "equirect_to_cube.wgsl",
+            include_str!("shaders/equirect_to_cube.wgsl"),
+        )
+        .create_module(device);
+        let equirect = create_compute_pipeline(
+            device,
+            "Equirect To Cube Pipeline",
+            &equirect_layout,
+            &module,
+            "main",
+        );
+        let equirect_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Equirect Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let downsample_layout = BindingLayout::new(
+            device,
+            "Cubemap Downsample Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::FRAGMENT, cube),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::FILTERING_SAMPLER),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::Uniform),
+            ],
+        );
+        let mut desc = PipelineDescriptor::new(
+            "Cubemap Downsample Pipeline",
+            Shader::from_wgsl(
+                "cubemap_downsample.wgsl",
+                include_str!("shaders/cubemap_downsample.wgsl"),
+            ),
+        );
+        desc.vertex_layouts.clear();
+        desc.bind_group_layouts = vec![downsample_layout.layout.clone()];
+        desc.cull_mode = None;
+        let targets = TargetState {
+            color_format: IBL_FORMAT,
+            depth_format: None,
+            depth_compare: wgpu::CompareFunction::Always,
+            sample_count: 1,
+        };
+        let downsample = Pipeline::new(device, desc, &targets);
+        let downsample_faces = (0..6u32)
+            .map(|face| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Cubemap Downsample Face"),
+                    contents: bytemuck::bytes_of(&[face, 0, 0, 0]),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                })
+            })
+            .collect();
+
+        let filter_layout = BindingLayout::new(
+            device,
+            "IBL Filter Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::COMPUTE, cube),
+                (wgpu::ShaderStages::COMPUTE, BindingKind::FILTERING_SAMPLER),
+                (
+                    wgpu::ShaderStages::COMPUTE,
+                    storage_binding(wgpu::TextureViewDimension::D2Array),
+                ),
+                (wgpu::ShaderStages::COMPUTE, BindingKind::Uniform),
+            ],
+        );
+        let module = Shader::from_wgsl("ibl_filter.wgsl", include_str!("shaders/ibl_filter.wgsl"))
+            .create_module(device);
+        let irradiance = create_compute_pipeline(
+            device,
+            "Irradiance Pipeline",
+            &filter_layout,
+            &module,
+            "irradiance",
+        );
+        let prefilter = create_compute_pipeline(
+            device,
+            "Prefilter Pipeline",
+            &filter_layout,
+            &module,
+            "prefilter",
+        );
+
+        Self {
+            equirect_layout,
+            equirect,
+            equirect_sampler,
+            downsample_layout,
+            downsample,
+            downsample_faces,
+            filter_layout,
+            irradiance,
+            prefilter,
+        }
+    }
+}
+
+// 环境光照需要的全部纹理
+pub struct Environment {
+    intensity: f32,
+    loaded: bool,
+    environment: wgpu::Texture,
+    irradiance: wgpu::Texture,
+    prefiltered: wgpu::Texture,
+    // BRDF 查找表和环境无关，创建时计算一次
+    brdf_lut: wgpu::Texture,
+    pipelines: IblPipelines,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    // 绑定组：环境贴图、辐照度、预过滤贴图、BRDF 查找表、采样器、参数
+    pub layout: BindingLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Environment {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let environment_mips = ENVIRONMENT_SIZE.ilog2() + 1;
+        let environment = create_cube_texture(
+            device,
+            "Environment Cubemap",
+            ENVIRONMENT_SIZE,
+            environment_mips,
+            // mip 链由渲染通道生成
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+        let irradiance = create_cube_texture(
+            device,
+            "Irradiance Cubemap",
+            IRRADIANCE_SIZE,
+            1,
+            wgpu::TextureUsages::empty(),
+        );
+        let prefiltered = create_cube_texture(
+            device,
+            "Prefiltered Cubemap",
+            PREFILTERED_SIZE,
+            PREFILTERED_MIPS,
+            wgpu::TextureUsages::empty(),
+        );
+        let brdf_lut = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("BRDF LUT"),
+            size: wgpu::Extent3d {
+                width: BRDF_LUT_SIZE,
+                height: BRDF_LUT_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: IBL_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+            view_formats: &[],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            // 预过滤贴图的粗糙度在 mip 之间插值
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let intensity = 1.0;
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Environment Uniform Buffer"),
+            contents: bytemuck::bytes_of(&Self::uniform(intensity, false)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let cube = BindingKind::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            dimension: wgpu::TextureViewDimension::Cube,
+        };
+        let layout = BindingLayout::new(
+            device,
+            "Environment Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::FRAGMENT, cube),
+                (wgpu::ShaderStages::FRAGMENT, cube),
+                (wgpu::ShaderStages::FRAGMENT, cube),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::TEXTURE_2D),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::FILTERING_SAMPLER),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::Uniform),
+            ],
+        );
+        let brdf_lut_view = brdf_lut.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = layout.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(&cube_view(&environment, 0..environment_mips)),
+                wgpu::BindingResource::TextureView(&cube_view(&irradiance, 0..1)),
+                wgpu::BindingResource::TextureView(&cube_view(&prefiltered, 0..PREFILTERED_MIPS)),
+                wgpu::BindingResource::TextureView(&brdf_lut_view),
+                wgpu::BindingResource::Sampler(&sampler),
+                buffer.as_entire_binding(),
+            ],
+        );
+        let environment = Self {
+            intensity,
+            loaded: false,
+            environment,
+            irradiance,
+            prefiltered,
+            brdf_lut,
+            pipelines: IblPipelines::new(device),
+            sampler,
+            buffer,
+            layout,
+            bind_group,
+        };
+        environment.integrate_brdf(device, queue);
+        environment
+    }
+
+    fn uniform(intensity: f32, enabled: bool) -> EnvironmentUniform {
+        EnvironmentUniform {
+            intensity,
+            max_lod: (PREFILTERED_MIPS - 1) as f32,
+            enabled: enabled as u32,
+            _padding: 0,
+        }
+    }
+
+    // 是否已经加载了环境，没有加载时不绘制天空盒
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    // 同时影响天空盒的亮度和环境光照的强度
+    pub fn set_intensity(&mut self, queue: &wgpu::Queue, intensity: f32) {
+        self.intensity = intensity;
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::bytes_of(&Self::uniform(intensity, self.loaded)),
+        );
+    }
+
+    // 加载 .hdr 全景图并在 GPU 上生成所有 IBL 纹理
+    pub fn load_hdr(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+    ) -> Result<(), TextureError> {
+        let equirect = Texture::from_path(device, queue, path, ColorSpace::Linear)?;
+        self.load_equirect(device, queue, &equirect);
+        Ok(())
+    }
+
+    // 从已经上传的等距柱状投影纹理生成所有 IBL 纹理，命令提交后立即返回，不等待 GPU
+    pub fn load_equirect(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        equirect: &Texture,
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("IBL Encoder"),
+        });
+        self.convert_equirect(device, &mut encoder, equirect);
+        self.generate_mips(device, &mut encoder);
+        self.filter(device, &mut encoder);
+        queue.submit(Some(encoder.finish()));
+        self.loaded = true;
+        self.set_intensity(queue, self.intensity);
+    }
+
+    // 全景图转换到环境立方体贴图的第 0 级
+    fn convert_equirect(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        equirect: &Texture,
+    ) {
+        let pipelines = &self.pipelines;
+        let bind_group = pipelines.equirect_layout.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(&equirect.view),
+                wgpu::BindingResource::Sampler(&pipelines.equirect_sampler),
+                wgpu::BindingResource::TextureView(&storage_view(&self.environment, 0)),
+            ],
+        );
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Equirect To Cube Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipelines.equirect);
+        pass.set_bind_group(0, &bind_group, &[]);
+        dispatch_cube(&mut pass, ENVIRONMENT_SIZE);
+    }
+
+    // 逐级缩小环境贴图，每一级 mip 的每个面一个渲染通道
+    fn generate_mips(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        let pipelines = &self.pipelines;
+        for mip in 1..self.environment.mip_level_count() {
+            // 读取上一级、写入这一级，两个视图不能覆盖同一级 mip
+            let source = cube_view(&self.environment, mip - 1..mip);
+            for (face, buffer) in pipelines.downsample_faces.iter().enumerate() {
+                let output = self.environment.create_view(&wgpu::TextureViewDescriptor {
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_mip_level: mip,
+                    mip_level_count: Some(1),
+                    base_array_layer: face as u32,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                });
+                let bind_group = pipelines.downsample_layout.create_bind_group(
+                    device,
+                    &[
+                        wgpu::BindingResource::TextureView(&source),
+                        wgpu::BindingResource::Sampler(&self.sampler),
+                        buffer.as_entire_binding(),
+                    ],
+                );
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Cubemap Downsample Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &output,
+                        resolve_target: None,
+                        depth_slice: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    ..Default::default()
+                });
+                pass.set_pipeline(&pipelines.downsample.pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+        }
+    }
+
+    // 用带 mip 链的环境贴图卷积出辐照度贴图和每一级预过滤贴图
+    fn filter(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        let pipelines = &self.pipelines;
+        // 每次调度的参数不同，写入同一个缓冲区的话提交时只剩最后一次的值，所以各用一个缓冲区
+        let bind_group = |source: &wgpu::TextureView, output: &wgpu::TextureView, params| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("IBL Filter Params"),
+                contents: bytemuck::bytes_of(&params),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            pipelines.filter_layout.create_bind_group(
+                device,
+                &[
+                    wgpu::BindingResource::TextureView(source),
+                    wgpu::BindingResource::Sampler(&self.sampler),
+                    wgpu::BindingResource::TextureView(output),
+                    buffer.as_entire_binding(),
+                ],
+            )
+        };
+        let params = |roughness, sample_count| FilterParams {
+            roughness,
+            sample_count,
+            source_size: ENVIRONMENT_SIZE as f32,
+            _padding: 0.0,
+        };
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("IBL Filter Pass"),
+            timestamp_writes: None,
+        });
+        let source = cube_view(&self.environment, 0..self.environment.mip_level_count());
+        pass.set_pipeline(&pipelines.irradiance);
+        let output = storage_view(&self.irradiance, 0);
+        pass.set_bind_group(
+            0,
+            &bind_group(&source, &output, params(0.0, IRRADIANCE_SAMPLES)),
+            &[],
+        );
+        dispatch_cube(&mut pass, IRRADIANCE_SIZE);
+
+        pass.set_pipeline(&pipelines.prefilter);
+        for mip in 0..PREFILTERED_MIPS {
+            let roughness = mip as f32 / (PREFILTERED_MIPS - 1) as f32;
+            let output = storage_view(&self.prefiltered, mip);
+            pass.set_bind_group(
+                0,
+                &bind_group(&source, &output, params(roughness, PREFILTER_SAMPLES)),
+                &[],
+            );
+            dispatch_cube(&mut pass, mip_size(PREFILTERED_SIZE, mip));
+        }
+    }
+
+    // 只在创建时调用一次，管线用完就丢掉
+    fn integrate_brdf(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let layout = BindingLayout::new(
+            device,
+            "BRDF LUT Bind Group Layout",
+            &[(
+                wgpu::ShaderStages::COMPUTE,
+                storage_binding(wgpu::TextureViewDimension::D2),
+            )],
+        );
+        let module = Shader::from_wgsl("brdf_lut.wgsl", include_str!("shaders/brdf_lut.wgsl"))
+            .create_module(device);
+        let pipeline =
+            create_compute_pipeline(device, "BRDF LUT Pipeline", &layout, &module, "main");
+        let view = self
+            .brdf_lut
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group =
+            layout.create_bind_group(device, &[wgpu::BindingResource::TextureView(&view)]);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("BRDF LUT Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("BRDF LUT Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let groups = BRDF_LUT_SIZE.div_ceil(WORKGROUP_SIZE);
+            pass.dispatch_workgroups(groups, groups, 1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+// 天空盒：在场景之后画在远平面上，填充没有被物体覆盖的像素
+pub struct Skybox {
+    pipeline: Pipeline,
+}
+
+impl Skybox {
+    pub fn new(
+        device: &wgpu::Device,
+        targets: &TargetState,
+        camera_layout: &wgpu::BindGroupLayout,
+        environment: &Environment,
+    ) -> Self {
+        let mut desc = PipelineDescriptor::new(
+            "Skybox Pipeline",
+            Shader::from_wgsl("skybox.wgsl", include_str!("shaders/skybox.wgsl")),
+        );
+        // 全屏三角形由 vertex_index 生成
+        desc.vertex_layouts.clear();
+        desc.bind_group_layouts = vec![camera_layout.clone(), environment.layout.layout.clone()];
+        desc.cull_mode = None;
+        // 只画在深度还是清除值的像素上，被物体挡住的像素不用再跑片元着色器
+        desc.depth_write_enabled = false;
+        depth::use_far_plane(&mut desc, targets);
+        Self {
+            pipeline: Pipeline::new(device, desc, targets),
+        }
+    }
+
+    pub fn set_targets(&mut self, device: &wgpu::Device, targets: &TargetState) {
+        depth::use_far_plane(&mut self.pipeline.desc, targets);
+        self.pipeline.rebuild(device, targets);
+    }
+
+    pub fn draw(
+        &self,
+        pass: &mut wgpu::RenderPass<'_>,
+        camera_bind_group: &wgpu::BindGroup,
+        environment: &Environment,
+    ) {
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        pass.set_bind_group(1, &environment.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
+
+    #[test]
+    fn prefiltered_mips_match_the_requested_resolution() {
+        let Some(app) = headless::test_device_app() else {
+            return;
+        };
+        let environment = Environment::new(&app.device, &app.queue);
+        let prefiltered = &environment.prefiltered;
+        assert_eq!(prefiltered.mip_level_count(), PREFILTERED_MIPS);
+        for mip in 0..PREFILTERED_MIPS {
+            let size = prefiltered
+                .size()
+                .mip_level_size(mip, wgpu::TextureDimension::D2);
+            assert_eq!(size.width, PREFILTERED_SIZE >> mip);
+            assert_eq!(size.width, mip_size(PREFILTERED_SIZE, mip));
+            assert_eq!(size.height, size.width);
+            assert_eq!(size.depth_or_array_layers, 6);
+        }
+        // 粗糙度为 1 的最后一级至少要有一个工作组那么大
+        assert!(mip_size(PREFILTERED_SIZE, PREFILTERED_MIPS - 1) >= WORKGROUP_SIZE);
+        // 环境贴图的 mip 链一直缩小到 1×1
+        let environment_mips = environment.environment.mip_level_count();
+        assert_eq!(mip_size(ENVIRONMENT_SIZE, environment_mips - 1), 1);
+        assert_eq!(environment.irradiance.width(), IRRADIANCE_SIZE);
+    }
+
+    #[test]
+    fn environments_can_be_swapped_with_the_same_pipelines() {
+        let Some(app) = headless::test_device_app() else {
+            return;
+        };
+        let mut environment = Environment::new(&app.device, &app.queue);
+        assert!(!environment.is_loaded());
+        app.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        for color in [[255u8, 0, 0, 255], [0, 0, 255, 255]] {
+            let pixels = color.repeat(8);
+            let equirect = Texture::from_pixels(
+                &app.device,
+                &app.queue,
+                &pixels,
+                4,
+                2,
+                wgpu::TextureFormat::Rgba8Unorm,
+                "Test Equirect",
+            );
+            environment.load_equirect(&app.device, &app.queue, &equirect);
+        }
+        let error = pollster::block_on(app.device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+        assert!(environment.is_loaded());
+    }
+}
@@ -0,0 +1,218 @@
+// GPU 实例化
+// 同一个网格画成千上万份时，每份的变换、颜色和自定义属性放在实例缓冲区里，
+// 以 VertexStepMode::Instance 的方式绑定到顶点缓冲区槽位 1，一次绘制调用画完所有实例。
+// CPU 端保留一份实例数据，修改只标记脏区间，渲染前只上传变化的部分；
+// 实例数量超过容量时按倍数扩容并重新创建缓冲区。
+
+use std::ops::Range;
+
+use glam::{Mat4, Quat, Vec3};
+
+// 实例的 CPU 端描述
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instance {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    pub color: [f32; 4],
+    // 自定义属性，着色器自己决定含义
+    pub custom: [f32; 4],
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+            color: [1.0; 4],
+            custom: [0.0; 4],
+        }
+    }
+}
+
+impl Instance {
+    pub fn to_raw(&self) -> InstanceRaw {
+        InstanceRaw {
+            model: Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+                .to_cols_array_2d(),
+            color: self.color,
+            custom: self.custom,
+        }
+    }
+}
+
+// 上传到 GPU 的实例数据
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceRaw {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+    pub custom: [f32; 4],
+}
+
+impl InstanceRaw {
+    // 顶点属性最多只能是 vec4，所以模型矩阵拆成 4 个 vec4
+    // 从 location 5 开始，给网格的顶点属性留出位置
+    const ATTRIBS: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x4,
+        10 => Float32x4,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            // step_mode: 每个实例前进一次，而不是每个顶点
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// 实例缓冲区
+pub struct InstanceBuffer {
+    label: String,
+    buffer: wgpu::Buffer,
+    // GPU 缓冲区能容纳的实例数量
+    capacity: usize,
+    instances: Vec<InstanceRaw>,
+    // 还没有上传的实例区间
+    dirty: Option<Range<usize>>,
+}
+
+impl InstanceBuffer {
+    pub fn new(device: &wgpu::Device, label: &str, instances: &[Instance]) -> Self {
+        let capacity = instances.len().max(1);
+        let mut buffer = Self {
+            label: label.to_string(),
+            buffer: Self::create_buffer(device, label, capacity),
+            capacity,
+            instances: Vec::new(),
+            dirty: None,
+        };
+        buffer.extend(instances);
+        buffer
+    }
+
+    fn create_buffer(device: &wgpu::Device, label: &str, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{label} Instance Buffer")),
+            size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn instances(&self) -> &[InstanceRaw] {
+        &self.instances
+    }
+
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range,
+        });
+    }
+
+    // 在末尾追加实例
+    pub fn extend(&mut self, instances: &[Instance]) {
+        let start = self.instances.len();
+        self.instances
+            .extend(instances.iter().map(Instance::to_raw));
+        self.mark_dirty(start..self.instances.len());
+    }
+
+    pub fn push(&mut self, instance: Instance) {
+        self.extend(&[instance]);
+    }
+
+    // 从 start 开始覆盖一段实例，只有这一段会被重新上传
+    pub fn update(&mut self, start: usize, instances: &[Instance]) {
+        let end = start + instances.len();
+        assert!(end <= self.instances.len(), "实例下标越界: {end}");
+        for (raw, instance) in self.instances[start..end].iter_mut().zip(instances) {
+            *raw = instance.to_raw();
+        }
+        self.mark_dirty(start..end);
+    }
+
+    // 删掉 len 之后的实例，不会缩小 GPU 缓冲区
+    pub fn truncate(&mut self, len: usize) {
+        self.instances.truncate(len);
+        self.dirty = self
+            .dirty
+            .take()
+            .map(|d| d.start.min(len)..d.end.min(len))
+            .filter(|d| !d.is_empty());
+    }
+
+    // 把脏区间上传到 GPU，容量不够时先扩容（扩容后整个缓冲区重新上传）
+    pub fn flush(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.instances.len() > self.capacity {
+            self.capacity = self.instances.len().max(self.capacity * 2);
+            self.buffer = Self::create_buffer(device, &self.label, self.capacity);
+            log::debug!("{} 实例缓冲区扩容到 {}", self.label, self.capacity);
+            self.dirty = Some(0..self.instances.len());
+        }
+        let Some(dirty) = self.dirty.take() else {
+            return;
+        };
+        let offset = dirty.start * std::mem::size_of::<InstanceRaw>();
+        queue.write_buffer(
+            &self.buffer,
+            offset as wgpu::BufferAddress,
+            bytemuck::cast_slice(&self.instances[dirty]),
+        );
+    }
+
+    // 绑定到渲染通道的顶点缓冲区槽位，返回要绘制的实例范围
+    pub fn bind(&self, pass: &mut wgpu::RenderPass<'_>, slot: u32) -> Range<u32> {
+        if self.instances.is_empty() {
+            return 0..0;
+        }
+        let size = self.instances.len() * std::mem::size_of::<InstanceRaw>();
+        pass.set_vertex_buffer(slot, self.buffer.slice(..size as wgpu::BufferAddress));
+        0..self.instances.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_model_matrix_applies_scale_rotation_translation() {
+        let instance = Instance {
+            position: Vec3::new(1.0, 2.0, 3.0),
+            rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            scale: Vec3::splat(2.0),
+            ..Default::default()
+        };
+        let model = Mat4::from_cols_array_2d(&instance.to_raw().model);
+        let p = model.transform_point3(Vec3::X);
+        assert!((p - Vec3::new(1.0, 4.0, 3.0)).length() < 1e-5, "{p}");
+    }
+
+    #[test]
+    fn layout_steps_per_instance() {
+        let layout = InstanceRaw::desc();
+        assert_eq!(layout.step_mode, wgpu::VertexStepMode::Instance);
+        assert_eq!(layout.array_stride, 96);
+    }
+}
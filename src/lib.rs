@@ -0,0 +1,30 @@
+mod app;
+pub mod camera;
+pub mod compute;
+pub mod debug_draw;
+pub mod demo;
+pub mod depth;
+pub mod exposure;
+#[cfg(test)]
+mod golden;
+pub mod hdr;
+pub mod headless;
+pub mod ibl;
+pub mod instance;
+pub mod light;
+pub mod material;
+pub mod model;
+pub mod msaa;
+pub mod particles;
+pub mod pipeline;
+pub mod post;
+pub mod render_graph;
+pub mod scene;
+pub mod shader_reload;
+pub mod shadow;
+pub mod sky;
+pub mod sprite;
+pub mod text;
+pub mod texture;
+
+pub use app::WgpuApp;
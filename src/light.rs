@@ -0,0 +1,361 @@
+// 光源子系统
+// 点光源、平行光和聚光灯放在同一个 uniform 缓冲区里，着色器按 kind 区分，用 Blinn-Phong 模型着色。
+// 光源可以在 update() 里随时添加、修改和删除，修改只标记为脏，渲染前统一上传。
+// 使用 uniform 而不是存储缓冲区，这样 WebGL2 等不支持存储缓冲区的后端也能用，代价是数量有上限。
+// 光源的绑定组里还有阴影贴图，平行光和聚光灯设置 cast_shadows 后投射阴影，见 shadow 模块。
+
+use glam::Vec3;
+
+use crate::camera::Camera;
+use crate::shadow::{
+    MAX_CASCADES, MAX_SPOT_SHADOWS, ShadowCasters, ShadowMaps, ShadowSettings, spot_view_projection,
+};
+use crate::texture::{BindingKind, BindingLayout};
+
+// 同时生效的光源数量上限，和着色器里的 MAX_LIGHTS 一致
+pub const MAX_LIGHTS: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightKind {
+    // 平行光：只有方向，没有位置和衰减，比如太阳光
+    Directional = 0,
+    // 点光源：向四周发光，在 range 处衰减到 0
+    Point = 1,
+    // 聚光灯：点光源加上锥形范围，内锥角以内全亮，外锥角以外全暗
+    Spot = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: Vec3,
+    // 光照射的方向，平行光和聚光灯使用
+    pub direction: Vec3,
+    // 线性空间的颜色
+    pub color: Vec3,
+    pub intensity: f32,
+    // 点光源和聚光灯的影响范围
+    pub range: f32,
+    // 聚光灯的内、外锥角（半角），弧度
+    pub inner_cone: f32,
+    pub outer_cone: f32,
+    // 是否投射阴影，只对平行光（只有第一个）和聚光灯（最多 MAX_SPOT_SHADOWS 个）有效
+    pub cast_shadows: bool,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Vec3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Directional,
+            position: Vec3::ZERO,
+            direction: direction.normalize_or(Vec3::NEG_Y),
+            color,
+            intensity,
+            range: 0.0,
+            inner_cone: 0.0,
+            outer_cone: 0.0,
+            cast_shadows: false,
+        }
+    }
+
+    pub fn point(position: Vec3, color: Vec3, intensity: f32, range: f32) -> Self {
+        Self {
+            kind: LightKind::Point,
+            position,
+            range,
+            ..Self::directional(Vec3::NEG_Y, color, intensity)
+        }
+    }
+
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        color: Vec3,
+        intensity: f32,
+        range: f32,
+        inner_cone: f32,
+        outer_cone: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot,
+            position,
+            range,
+            inner_cone,
+            outer_cone: outer_cone.max(inner_cone),
+            ..Self::directional(direction, color, intensity)
+        }
+    }
+
+    pub fn with_shadows(mut self) -> Self {
+        self.cast_shadows = true;
+        self
+    }
+
+    // shadow_layer 由 Lights 分配，这里先填 -1（不投射阴影）
+    pub fn to_raw(&self) -> LightRaw {
+        LightRaw {
+            position: self.position.to_array(),
+            kind: self.kind as u32,
+            direction: self.direction.normalize_or(Vec3::NEG_Y).to_array(),
+            range: self.range,
+            color: self.color.to_array(),
+            intensity: self.intensity,
+            // 着色器里比较的是余弦值，提前算好
+            cos_inner: self.inner_cone.cos(),
+            cos_outer: self.outer_cone.cos(),
+            shadow_layer: -1,
+            _padding: 0.0,
+        }
+    }
+}
+
+// 上传到 GPU 的光源，和着色器里的 Light 对应，64 字节
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightRaw {
+    pub position: [f32; 3],
+    pub kind: u32,
+    pub direction: [f32; 3],
+    pub range: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub cos_inner: f32,
+    pub cos_outer: f32,
+    // 阴影贴图数组的层，平行光是第一个级联的层，-1 表示没有阴影
+    pub shadow_layer: i32,
+    pub _padding: f32,
+}
+
+// 光源数组前面的头部，和着色器里 Lights 的前两个字段对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightsHeader {
+    ambient: [f32; 4],
+    count: u32,
+    _padding: [u32; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightId(pub usize);
+
+// 场景里的所有光源
+pub struct Lights {
+    // 删除的光源留下空位，下次添加时复用，所以 LightId 在光源删除之前一直有效
+    slots: Vec<Option<Light>>,
+    // 环境光颜色
+    ambient: Vec3,
+    dirty: bool,
+    pub buffer: wgpu::Buffer,
+    pub shadows: ShadowMaps,
+    // 绑定组：光源数组、阴影贴图、比较采样器、阴影参数
+    pub layout: BindingLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Lights {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let size =
+            std::mem::size_of::<LightsHeader>() + MAX_LIGHTS * std::mem::size_of::<LightRaw>();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Lights Buffer"),
+            size: size as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let shadows = ShadowMaps::new(device, ShadowSettings::default());
+        let layout = BindingLayout::new(
+            device,
+            "Lights Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::Uniform),
+                (
+                    wgpu::ShaderStages::FRAGMENT,
+                    BindingKind::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        dimension: wgpu::TextureViewDimension::D2Array,
+                    },
+                ),
+                (
+                    wgpu::ShaderStages::FRAGMENT,
+                    BindingKind::Sampler(wgpu::SamplerBindingType::Comparison),
+                ),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::Uniform),
+            ],
+        );
+        let bind_group = layout.create_bind_group(
+            device,
+            &[
+                buffer.as_entire_binding(),
+                wgpu::BindingResource::TextureView(&shadows.view),
+                wgpu::BindingResource::Sampler(&shadows.sampler),
+                shadows.buffer.as_entire_binding(),
+            ],
+        );
+        Self {
+            slots: Vec::new(),
+            ambient: Vec3::splat(0.1),
+            // 第一次渲染前也要上传一次头部
+            dirty: true,
+            buffer,
+            shadows,
+            layout,
+            bind_group,
+        }
+    }
+
+    // 添加光源，数量达到上限时返回 None
+    pub fn add(&mut self, light: Light) -> Option<LightId> {
+        self.dirty = true;
+        if let Some(i) = self.slots.iter().position(Option::is_none) {
+            self.slots[i] = Some(light);
+            return Some(LightId(i));
+        }
+        if self.slots.len() >= MAX_LIGHTS {
+            log::warn!("光源数量已达到上限 {MAX_LIGHTS}");
+            return None;
+        }
+        self.slots.push(Some(light));
+        Some(LightId(self.slots.len() - 1))
+    }
+
+    pub fn get(&self, id: LightId) -> Option<&Light> {
+        self.slots.get(id.0)?.as_ref()
+    }
+
+    // 修改光源，比如移动位置或者改变颜色
+    pub fn get_mut(&mut self, id: LightId) -> Option<&mut Light> {
+        let light = self.slots.get_mut(id.0)?.as_mut()?;
+        self.dirty = true;
+        Some(light)
+    }
+
+    pub fn remove(&mut self, id: LightId) -> Option<Light> {
+        let light = self.slots.get_mut(id.0)?.take()?;
+        self.dirty = true;
+        Some(light)
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.dirty = true;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LightId, &Light)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, l)| l.as_ref().map(|l| (LightId(i), l)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn ambient(&self) -> Vec3 {
+        self.ambient
+    }
+
+    pub fn set_ambient(&mut self, ambient: Vec3) {
+        self.ambient = ambient;
+        self.dirty = true;
+    }
+
+    // 按顺序给投射阴影的光源分配阴影贴图的层：第一个平行光使用级联的层，聚光灯依次使用后面的层
+    fn assign_shadow_layers(&self) -> (Vec<LightRaw>, ShadowCasters) {
+        let mut casters = ShadowCasters {
+            directional: None,
+            spots: Vec::new(),
+        };
+        let raws = self
+            .iter()
+            .map(|(_, light)| {
+                let mut raw = light.to_raw();
+                if !light.cast_shadows {
+                    return raw;
+                }
+                match light.kind {
+                    LightKind::Directional if casters.directional.is_none() => {
+                        casters.directional = Some(light.direction);
+                        raw.shadow_layer = 0;
+                    }
+                    LightKind::Spot if casters.spots.len() < MAX_SPOT_SHADOWS => {
+                        raw.shadow_layer = (MAX_CASCADES + casters.spots.len()) as i32;
+                        casters.spots.push(spot_view_projection(
+                            light.position,
+                            light.direction,
+                            light.outer_cone,
+                            light.range,
+                        ));
+                    }
+                    _ => {}
+                }
+                raw
+            })
+            .collect();
+        (raws, casters)
+    }
+
+    pub fn shadow_settings(&self) -> ShadowSettings {
+        self.shadows.settings
+    }
+
+    pub fn set_shadow_settings(&mut self, device: &wgpu::Device, settings: ShadowSettings) {
+        self.shadows.set_settings(device, settings);
+    }
+
+    // 有修改时把生效的光源紧密排列后上传；阴影的矩阵跟随相机，每帧都要更新
+    pub fn flush(&mut self, queue: &wgpu::Queue, camera: &Camera) {
+        let (raws, casters) = self.assign_shadow_layers();
+        self.shadows.update(queue, camera, &casters);
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let header = LightsHeader {
+            ambient: self.ambient.extend(1.0).to_array(),
+            count: raws.len() as u32,
+            _padding: [0; 3],
+        };
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&header));
+        if !raws.is_empty() {
+            queue.write_buffer(
+                &self.buffer,
+                std::mem::size_of::<LightsHeader>() as wgpu::BufferAddress,
+                bytemuck::cast_slice(&raws),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_layout_matches_wgsl() {
+        assert_eq!(std::mem::size_of::<LightRaw>(), 64);
+        assert_eq!(std::mem::size_of::<LightsHeader>(), 32);
+    }
+
+    #[test]
+    fn spot_cone_is_stored_as_cosines() {
+        let raw = Light::spot(
+            Vec3::ZERO,
+            Vec3::new(0.0, -2.0, 0.0),
+            Vec3::ONE,
+            1.0,
+            10.0,
+            0.0,
+            std::f32::consts::FRAC_PI_3,
+        )
+        .to_raw();
+        assert_eq!(raw.kind, LightKind::Spot as u32);
+        assert_eq!(raw.direction, [0.0, -1.0, 0.0]);
+        assert!((raw.cos_inner - 1.0).abs() < 1e-6);
+        assert!((raw.cos_outer - 0.5).abs() < 1e-6);
+    }
+}
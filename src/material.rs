@@ -0,0 +1,192 @@
+// PBR 材质
+// 金属度-粗糙度工作流的材质参数和贴图槽位，和 glTF 2.0 的材质模型一致：
+// 基础颜色、金属度/粗糙度（B 通道金属度，G 通道粗糙度）、法线、环境光遮蔽和自发光。
+// 缺失的贴图用纯色纹理代替，这样着色器里总是可以直接采样再乘以参数。
+// 材质放在 Materials 里统一管理，多个网格的绘制调用共用同一个绑定组，
+// 运行时修改参数后只需要重新上传一次 uniform，所有使用它的网格都会变化。
+
+use wgpu::util::DeviceExt;
+
+use crate::texture::{BindingKind, BindingLayout, ColorSpace, Texture};
+
+// 材质参数，和着色器里的 PbrParams 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PbrParams {
+    // 线性空间的基础颜色，a 是不透明度
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    // 法线贴图 xy 分量的缩放
+    pub normal_scale: f32,
+    // 环境光遮蔽的强度，0 表示不使用
+    pub occlusion_strength: f32,
+    pub _padding: f32,
+}
+
+impl Default for PbrParams {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            emissive: [0.0; 3],
+            metallic: 1.0,
+            roughness: 1.0,
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+            _padding: 0.0,
+        }
+    }
+}
+
+// 材质的贴图槽位，None 表示使用默认的纯色纹理
+#[derive(Default)]
+pub struct PbrTextures {
+    pub base_color: Option<Texture>,
+    pub metallic_roughness: Option<Texture>,
+    pub normal: Option<Texture>,
+    pub occlusion: Option<Texture>,
+    pub emissive: Option<Texture>,
+}
+
+pub struct PbrMaterial {
+    pub name: String,
+    pub params: PbrParams,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    // params 修改后还没有上传
+    dirty: bool,
+}
+
+impl PbrMaterial {
+    // 绑定组布局：5 个 纹理 + 采样器，最后是材质参数
+    pub fn layout(device: &wgpu::Device) -> BindingLayout {
+        let mut entries = Vec::with_capacity(11);
+        for _ in 0..5 {
+            entries.push((wgpu::ShaderStages::FRAGMENT, BindingKind::TEXTURE_2D));
+            entries.push((wgpu::ShaderStages::FRAGMENT, BindingKind::FILTERING_SAMPLER));
+        }
+        entries.push((wgpu::ShaderStages::FRAGMENT, BindingKind::Uniform));
+        BindingLayout::new(device, "PBR Material Bind Group Layout", &entries)
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &BindingLayout,
+        name: &str,
+        params: PbrParams,
+        textures: PbrTextures,
+    ) -> Self {
+        let solid = |rgba, color_space, label| {
+            Texture::solid_color(device, queue, rgba, color_space, label)
+        };
+        let base_color = textures
+            .base_color
+            .unwrap_or_else(|| solid([255; 4], ColorSpace::Srgb, "Default Base Color"));
+        let metallic_roughness = textures
+            .metallic_roughness
+            .unwrap_or_else(|| solid([255; 4], ColorSpace::Linear, "Default Metallic Roughness"));
+        let normal = textures
+            .normal
+            .unwrap_or_else(|| solid([128, 128, 255, 255], ColorSpace::Linear, "Default Normal"));
+        let occlusion = textures
+            .occlusion
+            .unwrap_or_else(|| solid([255; 4], ColorSpace::Linear, "Default Occlusion"));
+        let emissive = textures
+            .emissive
+            .unwrap_or_else(|| solid([255; 4], ColorSpace::Srgb, "Default Emissive"));
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} PBR Params")),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let mut resources = Vec::with_capacity(11);
+        for texture in [
+            &base_color,
+            &metallic_roughness,
+            &normal,
+            &occlusion,
+            &emissive,
+        ] {
+            resources.push(wgpu::BindingResource::TextureView(&texture.view));
+            resources.push(wgpu::BindingResource::Sampler(&texture.sampler));
+        }
+        resources.push(buffer.as_entire_binding());
+        let bind_group = layout.create_bind_group(device, &resources);
+        Self {
+            name: name.to_string(),
+            params,
+            buffer,
+            bind_group,
+            dirty: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub usize);
+
+// 所有 PBR 材质的集合，绘制调用通过克隆绑定组共享材质
+pub struct Materials {
+    materials: Vec<PbrMaterial>,
+    pub layout: BindingLayout,
+}
+
+impl Materials {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            materials: Vec::new(),
+            layout: PbrMaterial::layout(device),
+        }
+    }
+
+    // 材质必须是用 self.layout 创建的
+    pub fn add(&mut self, material: PbrMaterial) -> MaterialId {
+        self.materials.push(material);
+        MaterialId(self.materials.len() - 1)
+    }
+
+    pub fn get(&self, id: MaterialId) -> Option<&PbrMaterial> {
+        self.materials.get(id.0)
+    }
+
+    // 按名字查找材质，有重名时返回第一个
+    pub fn find(&self, name: &str) -> Option<MaterialId> {
+        self.materials
+            .iter()
+            .position(|m| m.name == name)
+            .map(MaterialId)
+    }
+
+    // 修改材质参数，渲染前由 flush 上传
+    pub fn params_mut(&mut self, id: MaterialId) -> Option<&mut PbrParams> {
+        let material = self.materials.get_mut(id.0)?;
+        material.dirty = true;
+        Some(&mut material.params)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MaterialId, &PbrMaterial)> {
+        self.materials
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (MaterialId(i), m))
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    // 上传修改过的材质参数
+    pub fn flush(&mut self, queue: &wgpu::Queue) {
+        for material in self.materials.iter_mut().filter(|m| m.dirty) {
+            queue.write_buffer(&material.buffer, 0, bytemuck::bytes_of(&material.params));
+            material.dirty = false;
+        }
+    }
+}
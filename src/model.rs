@@ -0,0 +1,529 @@
+// 模型加载
+// 用 tobj 读取 Wavefront OBJ 和它引用的 MTL 材质，每个 OBJ 对象/组成为一个子网格，
+// 材质的漫反射贴图和法线贴图通过纹理子系统加载（路径相对于 OBJ 文件所在目录）。
+// 文件里没有法线时按面积加权生成平滑法线，再根据纹理坐标计算法线贴图需要的切线。
+// 文件格式错误、索引越界、贴图缺失等情况都返回 ModelError，不会让程序崩溃。
+
+use std::path::{Path, PathBuf};
+
+use glam::{Vec2, Vec3};
+use wgpu::util::DeviceExt;
+
+use crate::pipeline::{DrawCall, Mesh, MeshId, PipelineId, Renderer};
+use crate::texture::{BindingKind, BindingLayout, ColorSpace, Texture, TextureError};
+
+// 模型的顶点：位置、纹理坐标、法线和切线
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ModelVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    // w 分量是副切线的方向（±1），bitangent = cross(normal, tangent) * w
+    pub tangent: [f32; 4],
+}
+
+impl ModelVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x2,
+        2 => Float32x3,
+        3 => Float32x4,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// 加载模型时可能出现的错误，都带上出错的文件路径
+#[derive(Debug)]
+pub enum ModelError {
+    // OBJ 文件打不开或者格式错误
+    Obj {
+        path: PathBuf,
+        error: tobj::LoadError,
+    },
+    // MTL 文件打不开或者格式错误
+    Material {
+        path: PathBuf,
+        error: tobj::LoadError,
+    },
+    // 材质引用的贴图加载失败
+    Texture {
+        path: PathBuf,
+        error: TextureError,
+    },
+    // 能解析但数据不一致，比如纹理坐标数量和顶点数量不匹配
+    Invalid {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for ModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelError::Obj { path, error } => {
+                write!(f, "加载模型 {} 失败: {error}", path.display())
+            }
+            ModelError::Material { path, error } => {
+                write!(f, "加载模型 {} 的材质失败: {error}", path.display())
+            }
+            ModelError::Texture { path, error } => {
+                write!(f, "加载贴图 {} 失败: {error}", path.display())
+            }
+            ModelError::Invalid { path, message } => {
+                write!(f, "模型 {} 的数据有误: {message}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+// 材质参数，和着色器里的 MaterialUniform 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialUniform {
+    // 漫反射颜色，和漫反射贴图相乘，a 是不透明度
+    pub diffuse: [f32; 4],
+    // Blinn-Phong 的高光颜色和高光指数，指数越大高光越小越亮
+    pub specular: [f32; 3],
+    pub shininess: f32,
+}
+
+impl Default for MaterialUniform {
+    fn default() -> Self {
+        Self {
+            diffuse: [1.0; 4],
+            specular: [0.0; 3],
+            shininess: 32.0,
+        }
+    }
+}
+
+// 材质：漫反射贴图、法线贴图和参数
+pub struct Material {
+    pub name: String,
+    pub params: MaterialUniform,
+    pub diffuse_texture: Texture,
+    pub normal_texture: Texture,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Material {
+    // 材质的绑定组布局：漫反射贴图、采样器、法线贴图、采样器、材质参数
+    pub fn layout(device: &wgpu::Device) -> BindingLayout {
+        BindingLayout::new(
+            device,
+            "Material Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::TEXTURE_2D),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::FILTERING_SAMPLER),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::TEXTURE_2D),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::FILTERING_SAMPLER),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::Uniform),
+            ],
+        )
+    }
+
+    // 缺失的贴图用纯色纹理代替：漫反射为白色，法线为切线空间的 (0, 0, 1)
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &BindingLayout,
+        name: &str,
+        params: MaterialUniform,
+        diffuse_texture: Option<Texture>,
+        normal_texture: Option<Texture>,
+    ) -> Self {
+        let diffuse_texture = diffuse_texture.unwrap_or_else(|| {
+            Texture::solid_color(device, queue, [255; 4], ColorSpace::Srgb, "Default Diffuse")
+        });
+        let normal_texture = normal_texture.unwrap_or_else(|| {
+            Texture::solid_color(
+                device,
+                queue,
+                [128, 128, 255, 255],
+                ColorSpace::Linear,
+                "Default Normal",
+            )
+        });
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Material Buffer")),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = layout.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+                wgpu::BindingResource::TextureView(&normal_texture.view),
+                wgpu::BindingResource::Sampler(&normal_texture.sampler),
+                buffer.as_entire_binding(),
+            ],
+        );
+        Self {
+            name: name.to_string(),
+            params,
+            diffuse_texture,
+            normal_texture,
+            buffer,
+            bind_group,
+        }
+    }
+}
+
+// 模型里的一个子网格，material 是它在 Model::materials 里的下标
+pub struct ModelMesh {
+    pub name: String,
+    pub mesh: Mesh,
+    pub material: usize,
+}
+
+pub struct Model {
+    pub meshes: Vec<ModelMesh>,
+    pub materials: Vec<Material>,
+}
+
+impl Model {
+    // 加载 OBJ 文件和它引用的 MTL 材质
+    pub fn load_obj(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &BindingLayout,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        let (obj_models, obj_materials) =
+            tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS).map_err(|error| ModelError::Obj {
+                path: path.to_path_buf(),
+                error,
+            })?;
+        // 没有 mtllib 时 tobj 返回空列表；引用了但打不开或格式错误时返回错误
+        let obj_materials = obj_materials.map_err(|error| ModelError::Material {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let load_texture = |name: &Option<String>, color_space| {
+            name.as_ref()
+                .map(|name| {
+                    let texture_path = dir.join(name);
+                    Texture::from_path(device, queue, &texture_path, color_space).map_err(|error| {
+                        ModelError::Texture {
+                            path: texture_path,
+                            error,
+                        }
+                    })
+                })
+                .transpose()
+        };
+
+        let mut materials = Vec::with_capacity(obj_materials.len() + 1);
+        for m in &obj_materials {
+            let [r, g, b] = m.diffuse.unwrap_or([1.0; 3]);
+            let params = MaterialUniform {
+                diffuse: [r, g, b, m.dissolve.unwrap_or(1.0)],
+                // MTL 里 Ks 默认是黑色，也就是没有高光
+                specular: m.specular.unwrap_or([0.0; 3]),
+                shininess: m.shininess.unwrap_or(32.0).max(1.0),
+            };
+            materials.push(Material::new(
+                device,
+                queue,
+                layout,
+                &m.name,
+                params,
+                load_texture(&m.diffuse_texture, ColorSpace::Srgb)?,
+                load_texture(&m.normal_texture, ColorSpace::Linear)?,
+            ));
+        }
+
+        let mut meshes = Vec::with_capacity(obj_models.len());
+        let mut default_material = None;
+        // 没有任何面的对象（比如只有注释的文件）直接跳过
+        for m in obj_models.iter().filter(|m| !m.mesh.indices.is_empty()) {
+            let vertices = build_vertices(&m.mesh).map_err(|message| ModelError::Invalid {
+                path: path.to_path_buf(),
+                message: format!("{}: {message}", m.name),
+            })?;
+            let material = match m.mesh.material_id {
+                Some(id) if id < materials.len() => id,
+                // 没有指定材质的子网格共用一个默认材质
+                _ => *default_material.get_or_insert_with(|| {
+                    materials.push(Material::new(
+                        device,
+                        queue,
+                        layout,
+                        "Default",
+                        MaterialUniform::default(),
+                        None,
+                        None,
+                    ));
+                    materials.len() - 1
+                }),
+            };
+            meshes.push(ModelMesh {
+                name: m.name.clone(),
+                mesh: Mesh::new(device, &m.name, &vertices, &m.mesh.indices),
+                material,
+            });
+        }
+        if meshes.is_empty() {
+            return Err(ModelError::Invalid {
+                path: path.to_path_buf(),
+                message: "没有任何网格".to_string(),
+            });
+        }
+        Ok(Self { meshes, materials })
+    }
+
+    // 把所有子网格交给 renderer 绘制，group(0) 是 scene_bind_group（一般是相机），group(1) 是材质，
+    // group(2) 是光源
+    pub fn add_to_renderer(
+        self,
+        renderer: &mut Renderer,
+        pipeline: PipelineId,
+        scene_bind_group: &wgpu::BindGroup,
+        lights_bind_group: &wgpu::BindGroup,
+    ) -> Vec<MeshId> {
+        let Self { meshes, materials } = self;
+        meshes
+            .into_iter()
+            .map(|m| {
+                let mesh = renderer.add_mesh(m.mesh);
+                let mut draw = DrawCall::new(pipeline, mesh).with_shadows();
+                draw.bind_groups = vec![
+                    scene_bind_group.clone(),
+                    materials[m.material].bind_group.clone(),
+                    lights_bind_group.clone(),
+                ];
+                renderer.add_draw(draw);
+                mesh
+            })
+            .collect()
+    }
+}
+
+// 把 tobj 的扁平数组转换成顶点，检查各个数组的长度和索引范围
+fn build_vertices(mesh: &tobj::Mesh) -> Result<Vec<ModelVertex>, String> {
+    if !mesh.positions.len().is_multiple_of(3) {
+        return Err(format!(
+            "顶点坐标数量 {} 不是 3 的倍数",
+            mesh.positions.len()
+        ));
+    }
+    let count = mesh.positions.len() / 3;
+    if !mesh.indices.len().is_multiple_of(3) {
+        return Err(format!("索引数量 {} 不是 3 的倍数", mesh.indices.len()));
+    }
+    if let Some(&index) = mesh.indices.iter().find(|&&i| i as usize >= count) {
+        return Err(format!("索引 {index} 超出顶点数量 {count}"));
+    }
+    if !mesh.normals.is_empty() && mesh.normals.len() != count * 3 {
+        return Err(format!(
+            "法线数量 {} 和顶点数量 {count} 不匹配",
+            mesh.normals.len() / 3
+        ));
+    }
+    if !mesh.texcoords.is_empty() && mesh.texcoords.len() != count * 2 {
+        return Err(format!(
+            "纹理坐标数量 {} 和顶点数量 {count} 不匹配",
+            mesh.texcoords.len() / 2
+        ));
+    }
+
+    let positions: Vec<Vec3> = mesh
+        .positions
+        .chunks_exact(3)
+        .map(Vec3::from_slice)
+        .collect();
+    let normals = if mesh.normals.is_empty() {
+        generate_normals(&positions, &mesh.indices)
+    } else {
+        mesh.normals.chunks_exact(3).map(Vec3::from_slice).collect()
+    };
+    // OBJ 的纹理坐标原点在左下角，wgpu 在左上角，所以翻转 v
+    let tex_coords: Vec<Vec2> = if mesh.texcoords.is_empty() {
+        vec![Vec2::ZERO; count]
+    } else {
+        mesh.texcoords
+            .chunks_exact(2)
+            .map(|t| Vec2::new(t[0], 1.0 - t[1]))
+            .collect()
+    };
+    let tangents = compute_tangents(&positions, &normals, &tex_coords, &mesh.indices);
+    Ok((0..count)
+        .map(|i| ModelVertex {
+            position: positions[i].to_array(),
+            tex_coords: tex_coords[i].to_array(),
+            normal: normals[i].to_array(),
+            tangent: tangents[i],
+        })
+        .collect())
+}
+
+// 生成平滑法线：每个三角形的法线按面积加权累加到它的三个顶点上
+pub fn generate_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for tri in indices.chunks_exact(3) {
+        let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| positions[i as usize]);
+        // 叉乘的长度是三角形面积的两倍，不归一化就是面积加权
+        let face = (b - a).cross(c - a);
+        for &i in tri {
+            normals[i as usize] += face;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|n| n.normalize_or(Vec3::Y))
+        .collect()
+}
+
+// 根据纹理坐标计算切线，没有有效纹理坐标时选一个和法线垂直的方向
+pub fn compute_tangents(
+    positions: &[Vec3],
+    normals: &[Vec3],
+    tex_coords: &[Vec2],
+    indices: &[u32],
+) -> Vec<[f32; 4]> {
+    let mut tangents = vec![Vec3::ZERO; positions.len()];
+    let mut bitangents = vec![Vec3::ZERO; positions.len()];
+    for tri in indices.chunks_exact(3) {
+        let [i0, i1, i2] = [tri[0], tri[1], tri[2]].map(|i| i as usize);
+        let (e1, e2) = (positions[i1] - positions[i0], positions[i2] - positions[i0]);
+        let (d1, d2) = (
+            tex_coords[i1] - tex_coords[i0],
+            tex_coords[i2] - tex_coords[i0],
+        );
+        let det = d1.x * d2.y - d2.x * d1.y;
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+        let r = 1.0 / det;
+        let tangent = (e1 * d2.y - e2 * d1.y) * r;
+        let bitangent = (e2 * d1.x - e1 * d2.x) * r;
+        for i in [i0, i1, i2] {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+    (0..positions.len())
+        .map(|i| {
+            let n = normals[i];
+            // Gram-Schmidt 正交化
+            let t = tangents[i] - n * n.dot(tangents[i]);
+            let t = t
+                .try_normalize()
+                .unwrap_or_else(|| n.any_orthonormal_vector());
+            let w = if n.cross(t).dot(bitangents[i]) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            t.extend(w).to_array()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{self, HeadlessOptions};
+
+    #[test]
+    fn generated_normals_point_out_of_the_face() {
+        let positions = [Vec3::ZERO, Vec3::X, Vec3::Z];
+        // 从 +Y 往下看是逆时针
+        let normals = generate_normals(&positions, &[0, 2, 1]);
+        for n in normals {
+            assert!((n - Vec3::Y).length() < 1e-6, "{n}");
+        }
+    }
+
+    #[test]
+    fn tangents_follow_texture_u() {
+        let positions = [Vec3::ZERO, Vec3::X, Vec3::Y];
+        let normals = [Vec3::Z; 3];
+        let tex_coords = [
+            Vec2::new(0.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 0.0),
+        ];
+        for t in compute_tangents(&positions, &normals, &tex_coords, &[0, 1, 2]) {
+            assert!((Vec3::from_slice(&t) - Vec3::X).length() < 1e-6);
+        }
+    }
+
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("my-wgpu-model-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn loads_sub_meshes_and_materials() {
+        let Some(app) = headless::test_app(HeadlessOptions::default()) else {
+            return;
+        };
+        let layout = Material::layout(&app.device);
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/cube.obj");
+        let model = Model::load_obj(&app.device, &app.queue, &layout, path).unwrap();
+        let names: Vec<&str> = model.meshes.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["crate", "floor"]);
+        let material_names: Vec<&str> = model.materials.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(material_names, ["checker", "red"]);
+        assert_eq!(model.meshes[1].material, 1);
+        // 立方体 6 个面、每个面 2 个三角形，位置和纹理坐标都相同的角会被合并
+        assert_eq!(model.meshes[0].mesh.index_count, 36);
+        assert!(model.meshes[0].mesh.vertex_count <= 24);
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        let Some(app) = headless::test_app(HeadlessOptions::default()) else {
+            return;
+        };
+        let layout = Material::layout(&app.device);
+        let load = |path: &Path| Model::load_obj(&app.device, &app.queue, &layout, path);
+
+        let bad_vertex = write_temp("bad_vertex.obj", "v 1.0 abc 0.0\nf 1 1 1\n");
+        assert!(matches!(load(&bad_vertex), Err(ModelError::Obj { .. })));
+
+        let out_of_range = write_temp("out_of_range.obj", "v 0 0 0\nv 1 0 0\nf 1 2 7\n");
+        assert!(load(&out_of_range).is_err());
+
+        let missing_mtl = write_temp("missing_mtl.obj", "mtllib nope.mtl\nv 0 0 0\n");
+        assert!(matches!(
+            load(&missing_mtl),
+            Err(ModelError::Material { .. })
+        ));
+
+        write_temp("missing_texture.mtl", "newmtl m\nmap_Kd nope.png\n");
+        let missing_texture = write_temp(
+            "missing_texture.obj",
+            "mtllib missing_texture.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl m\nf 1 2 3\n",
+        );
+        let err = load(&missing_texture).err().unwrap();
+        assert!(matches!(err, ModelError::Texture { .. }));
+        assert!(err.to_string().contains("nope.png"));
+
+        let empty = write_temp("empty.obj", "# 什么都没有\n");
+        let result = load(&empty);
+        assert!(
+            matches!(result, Err(ModelError::Invalid { .. })),
+            "{:?}",
+            result.err()
+        );
+    }
+}
@@ -0,0 +1,72 @@
+// 多重采样抗锯齿（MSAA）
+// 场景先渲染到多重采样的颜色纹理（和深度纹理）上，
+// 渲染通道结束时通过 resolve_target 解析到展示平面的纹理里。
+// 采样数可以在运行时修改，修改后多重采样纹理和管线都会重新创建。
+
+// 查询颜色格式（以及深度格式）都支持的采样数，结果从小到大排列，总是包含 1
+// 没有开启 TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES 时，WebGPU 只允许 1 和 4
+pub fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    device_features: wgpu::Features,
+    color_format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+) -> Vec<u32> {
+    let adapter_specific =
+        device_features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+    let flags_of = |format: wgpu::TextureFormat| {
+        if adapter_specific {
+            adapter.get_texture_format_features(format).flags
+        } else {
+            format.guaranteed_format_features(device_features).flags
+        }
+    };
+    let color = flags_of(color_format);
+    let depth = depth_format.map(flags_of);
+    let mut counts: Vec<u32> = color
+        .supported_sample_counts()
+        .into_iter()
+        .filter(|&count| depth.is_none_or(|d| d.sample_count_supported(count)))
+        .collect();
+    if !counts.contains(&1) {
+        counts.insert(0, 1);
+    }
+    counts
+}
+
+// 多重采样的颜色纹理，尺寸跟随展示平面，格式和解析目标（HDR 纹理）一致
+pub struct MsaaTarget {
+    pub sample_count: u32,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl MsaaTarget {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Multisampled Color Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            sample_count,
+            texture,
+            view,
+        }
+    }
+}
@@ -0,0 +1,603 @@
+// GPU 粒子系统
+// 粒子的发射、受力和死亡全部在计算着色器里完成，CPU 每帧只写一个发射器参数的 uniform：
+// 每个发射器有固定容量的粒子池，空闲的槽位放在死亡列表里，发射时弹出、死亡时压回，不需要 CPU 参与回收。
+// 模拟阶段把存活的粒子追加到存活列表，同时累加间接绘制参数的实例数量，
+// 渲染时用 draw_indirect 为每个存活粒子画一个朝向相机的公告板，实例数量不用回读到 CPU。
+// 颜色、大小和速度随归一化年龄按曲线变化；受力有重力、阻力和吸引子（强度为负时排斥）。
+// 加法混合和顺序无关，适合火花、魔法效果；alpha 混合的粒子没有排序，按存活列表的顺序绘制。
+
+use std::ops::{Add, Mul, Sub};
+
+use glam::{Vec3, Vec4};
+
+use crate::compute::{self, ComputeDescriptor, ComputePipeline};
+use crate::pipeline::{Pipeline, PipelineDescriptor, Shader, TargetState};
+use crate::shader_reload::ShaderError;
+use crate::texture::{BindingKind, BindingLayout};
+
+// 曲线的关键帧和吸引子数量上限，和着色器里的数组长度一致
+pub const MAX_CURVE_KEYS: usize = 4;
+pub const MAX_ATTRACTORS: usize = 4;
+// 一帧的时间步长上限，卡顿时避免一次发射太多、粒子一步飞出太远
+const MAX_TIME_STEP: f32 = 0.1;
+// 着色器里 Particle 结构的大小：位置、年龄、速度、寿命
+const PARTICLE_SIZE: wgpu::BufferAddress = 32;
+
+// 按归一化年龄（0 是刚发射，1 是寿命结束）取值的分段线性曲线
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve<T> {
+    keys: [(f32, T); MAX_CURVE_KEYS],
+    len: usize,
+}
+
+impl<T: Copy> Curve<T> {
+    pub fn constant(value: T) -> Self {
+        Self::new(&[(0.0, value)])
+    }
+
+    // 关键帧按时间从小到大排列，至少一个，超过 MAX_CURVE_KEYS 的部分被忽略
+    pub fn new(keys: &[(f32, T)]) -> Self {
+        assert!(!keys.is_empty(), "曲线至少需要一个关键帧");
+        if keys.len() > MAX_CURVE_KEYS {
+            log::warn!("曲线最多 {MAX_CURVE_KEYS} 个关键帧，多出的被忽略");
+        }
+        let len = keys.len().min(MAX_CURVE_KEYS);
+        let mut all = [keys[0]; MAX_CURVE_KEYS];
+        all[..len].copy_from_slice(&keys[..len]);
+        Self { keys: all, len }
+    }
+
+    pub fn keys(&self) -> &[(f32, T)] {
+        &self.keys[..self.len]
+    }
+}
+
+impl<T> Curve<T>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    // 和着色器里的 sample_curve 相同
+    pub fn sample(&self, t: f32) -> T {
+        let keys = self.keys();
+        if t <= keys[0].0 {
+            return keys[0].1;
+        }
+        for pair in keys.windows(2) {
+            let ((t0, a), (t1, b)) = (pair[0], pair[1]);
+            if t <= t1 {
+                let f = (t - t0) / (t1 - t0).max(1e-5);
+                return a + (b - a) * f;
+            }
+        }
+        keys[keys.len() - 1].1
+    }
+}
+
+// 吸引子：按距离平方衰减的力，strength 为负时排斥
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attractor {
+    pub position: Vec3,
+    pub strength: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleBlend {
+    // 颜色叠加，越密越亮，和绘制顺序无关
+    Additive,
+    // 普通的半透明混合
+    Alpha,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitterSettings {
+    pub position: Vec3,
+    // 在这个半径的球内随机选择发射位置
+    pub radius: f32,
+    // 每秒发射的粒子数量
+    pub spawn_rate: f32,
+    // 寿命的随机范围（秒）
+    pub lifetime: (f32, f32),
+    // 初速度的方向、圆锥半角（弧度）和大小的随机范围
+    pub direction: Vec3,
+    pub spread: f32,
+    pub speed: (f32, f32),
+    // 随年龄变化的线性空间颜色（alpha 是不透明度）、公告板边长和速度倍数
+    pub color: Curve<Vec4>,
+    pub size: Curve<f32>,
+    pub speed_scale: Curve<f32>,
+    pub gravity: Vec3,
+    // 阻力系数，每秒速度衰减为 e^(-drag)
+    pub drag: f32,
+    // 最多 MAX_ATTRACTORS 个
+    pub attractors: Vec<Attractor>,
+    pub blend: ParticleBlend,
+}
+
+impl Default for EmitterSettings {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            radius: 0.0,
+            spawn_rate: 100.0,
+            lifetime: (1.0, 2.0),
+            direction: Vec3::Y,
+            spread: 0.3,
+            speed: (1.0, 2.0),
+            color: Curve::new(&[(0.0, Vec4::ONE), (1.0, Vec4::new(1.0, 1.0, 1.0, 0.0))]),
+            size: Curve::constant(0.05),
+            speed_scale: Curve::constant(1.0),
+            gravity: Vec3::new(0.0, -9.8, 0.0),
+            drag: 0.0,
+            attractors: Vec::new(),
+            blend: ParticleBlend::Additive,
+        }
+    }
+}
+
+// 和着色器里的 EmitterUniform 对应，304 字节
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct EmitterUniform {
+    position_radius: [f32; 4],
+    direction_spread: [f32; 4],
+    gravity_drag: [f32; 4],
+    speed_lifetime: [f32; 4],
+    color_keys: [[f32; 4]; MAX_CURVE_KEYS],
+    color_times: [f32; 4],
+    size_keys: [f32; 4],
+    size_times: [f32; 4],
+    speed_keys: [f32; 4],
+    speed_times: [f32; 4],
+    attractors: [[f32; 4]; MAX_ATTRACTORS],
+    counts: [u32; 4],
+    spawn_count: u32,
+    seed: u32,
+    capacity: u32,
+    dt: f32,
+}
+
+impl EmitterUniform {
+    fn new(
+        settings: &EmitterSettings,
+        capacity: u32,
+        spawn_count: u32,
+        seed: u32,
+        dt: f32,
+    ) -> Self {
+        // 曲线拆成时间和值两个数组
+        fn split<T: Copy, U: Copy + Default>(
+            curve: &Curve<T>,
+            value: impl Fn(T) -> U,
+        ) -> ([f32; MAX_CURVE_KEYS], [U; MAX_CURVE_KEYS]) {
+            let mut times = [0.0; MAX_CURVE_KEYS];
+            let mut values = [U::default(); MAX_CURVE_KEYS];
+            for (i, (t, v)) in curve.keys().iter().enumerate() {
+                times[i] = *t;
+                values[i] = value(*v);
+            }
+            (times, values)
+        }
+        let (color_times, color_keys) = split(&settings.color, |c| c.to_array());
+        let (size_times, size_keys) = split(&settings.size, |s| s);
+        let (speed_times, speed_keys) = split(&settings.speed_scale, |s| s);
+        let mut attractors = [[0.0; 4]; MAX_ATTRACTORS];
+        let attractor_count = settings.attractors.len().min(MAX_ATTRACTORS);
+        for (raw, attractor) in attractors.iter_mut().zip(&settings.attractors) {
+            *raw = attractor.position.extend(attractor.strength).to_array();
+        }
+        Self {
+            position_radius: settings.position.extend(settings.radius).to_array(),
+            direction_spread: settings
+                .direction
+                .normalize_or(Vec3::Y)
+                .extend(settings.spread)
+                .to_array(),
+            gravity_drag: settings.gravity.extend(settings.drag).to_array(),
+            speed_lifetime: [
+                settings.speed.0,
+                settings.speed.1,
+                settings.lifetime.0,
+                settings.lifetime.1,
+            ],
+            color_keys,
+            color_times,
+            size_keys,
+            size_times,
+            speed_keys,
+            speed_times,
+            attractors,
+            counts: [
+                settings.color.keys().len() as u32,
+                settings.size.keys().len() as u32,
+                settings.speed_scale.keys().len() as u32,
+                attractor_count as u32,
+            ],
+            spawn_count,
+            seed,
+            capacity,
+            dt,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmitterId(pub usize);
+
+// 一个发射器的粒子池和它的 GPU 缓冲区
+struct Emitter {
+    settings: EmitterSettings,
+    capacity: u32,
+    // 发射数量的小数部分留到下一帧，低发射率时也不会丢粒子
+    spawn_accumulator: f32,
+    spawn_count: u32,
+    uniform: wgpu::Buffer,
+    // 间接绘制参数，实例数量由模拟阶段累加
+    draw_args: wgpu::Buffer,
+    compute_bind_group: wgpu::BindGroup,
+    render_bind_group: wgpu::BindGroup,
+}
+
+pub struct ParticleSystem {
+    emitters: Vec<Emitter>,
+    // 设置后每帧使用固定的时间步长，测试里用它得到确定的结果
+    pub fixed_time_step: Option<f32>,
+    frame: u32,
+    compute_layout: BindingLayout,
+    render_layout: BindingLayout,
+    emit: ComputePipeline,
+    simulate: ComputePipeline,
+    additive: Pipeline,
+    alpha: Pipeline,
+}
+
+impl ParticleSystem {
+    pub fn new(
+        device: &wgpu::Device,
+        targets: &TargetState,
+        camera_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let storage = BindingKind::Storage { read_only: false };
+        let compute_layout = BindingLayout::new(
+            device,
+            "Particle Compute Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::COMPUTE, BindingKind::Uniform),
+                (wgpu::ShaderStages::COMPUTE, storage),
+                (wgpu::ShaderStages::COMPUTE, storage),
+                (wgpu::ShaderStages::COMPUTE, storage),
+                (wgpu::ShaderStages::COMPUTE, storage),
+                (wgpu::ShaderStages::COMPUTE, storage),
+            ],
+        );
+        let read_only = BindingKind::Storage { read_only: true };
+        let render_layout = BindingLayout::new(
+            device,
+            "Particle Render Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::VERTEX, BindingKind::Uniform),
+                (wgpu::ShaderStages::VERTEX, read_only),
+                (wgpu::ShaderStages::VERTEX, read_only),
+            ],
+        );
+
+        let sim_shader = Shader::from_wgsl(
+            "particles_sim.wgsl",
+            include_str!("shaders/particles_sim.wgsl"),
+        );
+        let compute_pipeline = |entry: &str| {
+            let mut desc = ComputeDescriptor::new(
+                &format!("Particle {entry} Pipeline"),
+                sim_shader.clone(),
+                entry,
+            );
+            desc.bind_group_layouts = vec![compute_layout.layout.clone()];
+            ComputePipeline::new(device, desc)
+                .unwrap_or_else(|e: ShaderError| panic!("内置的粒子着色器有错误: {e}"))
+        };
+        let emit = compute_pipeline("emit");
+        let simulate = compute_pipeline("simulate");
+
+        let render_pipeline = |label: &str, blend: wgpu::BlendState| {
+            let mut desc = PipelineDescriptor::new(
+                label,
+                Shader::from_wgsl("particles.wgsl", include_str!("shaders/particles.wgsl")),
+            );
+            desc.vertex_layouts.clear();
+            desc.bind_group_layouts = vec![camera_layout.clone(), render_layout.layout.clone()];
+            desc.cull_mode = None;
+            desc.blend = Some(blend);
+            // 粒子之间不互相遮挡，但会被场景里的物体挡住
+            desc.depth_write_enabled = false;
+            Pipeline::new(device, desc, targets)
+        };
+        let additive = render_pipeline(
+            "Particle Additive Pipeline",
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+        );
+        let alpha = render_pipeline("Particle Alpha Pipeline", wgpu::BlendState::ALPHA_BLENDING);
+        Self {
+            emitters: Vec::new(),
+            fixed_time_step: None,
+            frame: 0,
+            compute_layout,
+            render_layout,
+            emit,
+            simulate,
+            additive,
+            alpha,
+        }
+    }
+
+    // 添加一个最多同时存在 capacity 个粒子的发射器，粒子池在这里一次分配好
+    pub fn add_emitter(
+        &mut self,
+        device: &wgpu::Device,
+        settings: EmitterSettings,
+        capacity: u32,
+    ) -> EmitterId {
+        let capacity = capacity.max(1);
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Emitter Uniform"),
+            size: std::mem::size_of::<EmitterUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // 新建的缓冲区内容是 0，lifetime 为 0 表示所有槽位都是空闲的
+        let particles = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Buffer"),
+            size: capacity as wgpu::BufferAddress * PARTICLE_SIZE,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        // 倒序放入，弹出时先用小的下标
+        let free: Vec<u32> = (0..capacity).rev().collect();
+        let dead_list = compute::create_storage_buffer(
+            device,
+            "Particle Dead List",
+            &free,
+            wgpu::BufferUsages::empty(),
+        );
+        let counters = compute::create_storage_buffer(
+            device,
+            "Particle Counters",
+            &[capacity as i32],
+            wgpu::BufferUsages::empty(),
+        );
+        let alive_list = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Alive List"),
+            size: capacity as wgpu::BufferAddress * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        // 每个公告板 6 个顶点，实例数量每帧清零后由模拟阶段累加
+        let draw_args = compute::create_storage_buffer(
+            device,
+            "Particle Draw Args",
+            &[6u32, 0, 0, 0],
+            wgpu::BufferUsages::INDIRECT,
+        );
+        let compute_bind_group = self.compute_layout.create_bind_group(
+            device,
+            &[
+                uniform.as_entire_binding(),
+                particles.as_entire_binding(),
+                dead_list.as_entire_binding(),
+                counters.as_entire_binding(),
+                alive_list.as_entire_binding(),
+                draw_args.as_entire_binding(),
+            ],
+        );
+        let render_bind_group = self.render_layout.create_bind_group(
+            device,
+            &[
+                uniform.as_entire_binding(),
+                particles.as_entire_binding(),
+                alive_list.as_entire_binding(),
+            ],
+        );
+        self.emitters.push(Emitter {
+            settings,
+            capacity,
+            spawn_accumulator: 0.0,
+            spawn_count: 0,
+            uniform,
+            draw_args,
+            compute_bind_group,
+            render_bind_group,
+        });
+        EmitterId(self.emitters.len() - 1)
+    }
+
+    pub fn emitter(&self, id: EmitterId) -> Option<&EmitterSettings> {
+        self.emitters.get(id.0).map(|e| &e.settings)
+    }
+
+    // 修改发射器参数，比如移动位置或者把发射率设为 0 停止发射，下一帧生效
+    pub fn emitter_mut(&mut self, id: EmitterId) -> Option<&mut EmitterSettings> {
+        self.emitters.get_mut(id.0).map(|e| &mut e.settings)
+    }
+
+    pub fn capacity(&self, id: EmitterId) -> Option<u32> {
+        self.emitters.get(id.0).map(|e| e.capacity)
+    }
+
+    // 间接绘制参数，第二个 u32 是上一次模拟后存活的粒子数量，可以用 read_buffer 读回
+    pub fn draw_args(&self, id: EmitterId) -> Option<&wgpu::Buffer> {
+        self.emitters.get(id.0).map(|e| &e.draw_args)
+    }
+
+    // 每帧在 update() 里调用：算出这一帧每个发射器发射多少个粒子，写入发射器参数
+    pub fn update(&mut self, queue: &wgpu::Queue, dt: f32) {
+        let dt = self.fixed_time_step.unwrap_or(dt).clamp(0.0, MAX_TIME_STEP);
+        self.frame = self.frame.wrapping_add(1);
+        for emitter in &mut self.emitters {
+            emitter.spawn_accumulator += emitter.settings.spawn_rate.max(0.0) * dt;
+            let spawn = emitter.spawn_accumulator.floor();
+            emitter.spawn_accumulator -= spawn;
+            emitter.spawn_count = (spawn as u32).min(emitter.capacity);
+            let uniform = EmitterUniform::new(
+                &emitter.settings,
+                emitter.capacity,
+                emitter.spawn_count,
+                self.frame,
+                dt,
+            );
+            queue.write_buffer(&emitter.uniform, 0, bytemuck::bytes_of(&uniform));
+        }
+    }
+
+    // 在 render() 的命令编码器里运行发射和模拟
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.emitters.is_empty() {
+            return;
+        }
+        // 清零实例数量（第二个 u32），顶点数量保持 6
+        for emitter in &self.emitters {
+            encoder.clear_buffer(&emitter.draw_args, 4, Some(4));
+        }
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Particle Simulation Pass"),
+            timestamp_writes: None,
+        });
+        for emitter in &self.emitters {
+            pass.set_bind_group(0, &emitter.compute_bind_group, &[]);
+            if emitter.spawn_count > 0 {
+                let [x, y, z] = self.emit.workgroups([emitter.spawn_count, 1, 1]);
+                pass.set_pipeline(&self.emit.pipeline);
+                pass.dispatch_workgroups(x, y, z);
+            }
+            let [x, y, z] = self.simulate.workgroups([emitter.capacity, 1, 1]);
+            pass.set_pipeline(&self.simulate.pipeline);
+            pass.dispatch_workgroups(x, y, z);
+        }
+    }
+
+    // 在主渲染通道里、不透明物体之后绘制所有发射器的粒子
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>, camera_bind_group: &wgpu::BindGroup) {
+        for emitter in &self.emitters {
+            let pipeline = match emitter.settings.blend {
+                ParticleBlend::Additive => &self.additive,
+                ParticleBlend::Alpha => &self.alpha,
+            };
+            pass.set_pipeline(&pipeline.pipeline);
+            pass.set_bind_group(0, camera_bind_group, &[]);
+            pass.set_bind_group(1, &emitter.render_bind_group, &[]);
+            pass.draw_indirect(&emitter.draw_args, 0);
+        }
+    }
+
+    pub fn set_targets(&mut self, device: &wgpu::Device, targets: &TargetState) {
+        self.additive.rebuild(device, targets);
+        self.alpha.rebuild(device, targets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_layout_matches_wgsl() {
+        assert_eq!(std::mem::size_of::<EmitterUniform>(), 304);
+    }
+
+    #[test]
+    fn curve_interpolates_between_keys() {
+        let curve = Curve::new(&[(0.0, 1.0), (0.5, 3.0), (1.0, 0.0)]);
+        assert_eq!(curve.sample(-1.0), 1.0);
+        assert_eq!(curve.sample(0.25), 2.0);
+        assert_eq!(curve.sample(0.75), 1.5);
+        assert_eq!(curve.sample(2.0), 0.0);
+        let color = Curve::new(&[(0.0, Vec4::ZERO), (1.0, Vec4::ONE)]);
+        assert_eq!(color.sample(0.5), Vec4::splat(0.5));
+        assert_eq!(Curve::constant(0.1).sample(0.7), 0.1);
+    }
+
+    #[test]
+    fn extra_curve_keys_are_ignored() {
+        let keys: Vec<(f32, f32)> = (0..6).map(|i| (i as f32 / 5.0, i as f32)).collect();
+        let curve = Curve::new(&keys);
+        assert_eq!(curve.keys().len(), MAX_CURVE_KEYS);
+        let uniform = EmitterUniform::new(
+            &EmitterSettings {
+                size: curve,
+                ..Default::default()
+            },
+            16,
+            0,
+            0,
+            0.0,
+        );
+        assert_eq!(uniform.counts[1], MAX_CURVE_KEYS as u32);
+        assert_eq!(uniform.size_keys, [0.0, 1.0, 2.0, 3.0]);
+    }
+
+    // 用间接绘制参数里的实例数量检查发射、死亡回收和容量上限
+    #[test]
+    fn particles_are_spawned_recycled_and_capped() {
+        use crate::WgpuApp;
+        use crate::headless;
+
+        let Some(mut app) = headless::test_device_app() else {
+            return;
+        };
+        let settings = EmitterSettings {
+            spawn_rate: 100.0,
+            lifetime: (0.45, 0.45),
+            ..Default::default()
+        };
+        let device = app.device.clone();
+        let particles = app.particles_mut();
+        particles.fixed_time_step = Some(0.1);
+        let id = particles.add_emitter(&device, settings, 1000);
+        let alive = |app: &mut WgpuApp| {
+            let buffer = app.particles.draw_args(id).unwrap().clone();
+            let readback = app.read_buffer(&buffer);
+            app.update();
+            app.render().unwrap();
+            let bytes = app.compute.wait(&app.device, readback).unwrap();
+            bytemuck::cast_slice::<u8, u32>(&bytes)[1]
+        };
+        // 每帧发射 10 个，第一帧发射后立刻模拟一步
+        assert_eq!(alive(&mut app), 10);
+        assert_eq!(alive(&mut app), 20);
+        // 寿命 0.45 秒：第 5 帧年龄到 0.5 的第一批粒子死亡，之后发射和死亡持平
+        for _ in 0..5 {
+            alive(&mut app);
+        }
+        assert_eq!(alive(&mut app), 40);
+
+        // 容量只有 15 时，死亡列表空了就不再发射
+        app.particles_mut().emitter_mut(id).unwrap().spawn_rate = 0.0;
+        let capped = app.particles_mut().add_emitter(
+            &device,
+            EmitterSettings {
+                spawn_rate: 100.0,
+                lifetime: (10.0, 10.0),
+                ..Default::default()
+            },
+            15,
+        );
+        let buffer = app.particles.draw_args(capped).unwrap().clone();
+        for _ in 0..3 {
+            app.update();
+            app.render().unwrap();
+        }
+        let readback = app.read_buffer(&buffer);
+        app.update();
+        app.render().unwrap();
+        let bytes = app.compute.wait(&app.device, readback).unwrap();
+        assert_eq!(bytemuck::cast_slice::<u8, u32>(&bytes)[1], 15);
+    }
+}
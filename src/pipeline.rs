@@ -0,0 +1,576 @@
+// 渲染管线子系统
+// 负责加载 WGSL 着色器、针对渲染目标格式创建 wgpu::RenderPipeline，
+// 持有顶点/索引缓冲区，并在 render() 已经开始的渲染通道里发出绘制命令。
+// 管线描述会被保存下来，渲染目标变化（格式、采样数等）时可以原地重建。
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use wgpu::util::DeviceExt;
+
+use crate::instance::InstanceBuffer;
+use crate::shader_reload::{self, ShaderError};
+
+// 最基础的顶点：位置 + 颜色
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    // 顶点缓冲区布局：告诉管线如何从缓冲区里读取每个顶点
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            // array_stride: 每个顶点占用的字节数
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            // step_mode: 每个顶点前进一次
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// 带纹理坐标的顶点
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TexturedVertex {
+    pub position: [f32; 3],
+    // 纹理坐标：左上角为 (0, 0)，右下角为 (1, 1)
+    pub tex_coords: [f32; 2],
+}
+
+impl TexturedVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TexturedVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// WGSL 着色器源码，path 不为空时表示源码来自磁盘上的文件
+#[derive(Debug, Clone)]
+pub struct Shader {
+    pub label: String,
+    pub source: String,
+    pub path: Option<PathBuf>,
+}
+
+impl Shader {
+    // 直接使用内存里的 WGSL 源码，一般配合 include_str! 使用
+    pub fn from_wgsl(label: &str, source: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            source: source.to_string(),
+            path: None,
+        }
+    }
+
+    // 从磁盘读取 WGSL 文件
+    pub fn from_path(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        Ok(Self {
+            label: path.display().to_string(),
+            source: std::fs::read_to_string(path)?,
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    // 源码仍然使用内嵌的版本，但记录磁盘上的路径，供热重载监听
+    // 发布后源码目录不存在时热重载会被跳过
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn create_module(&self, device: &wgpu::Device) -> wgpu::ShaderModule {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&self.label),
+            source: wgpu::ShaderSource::Wgsl(self.source.as_str().into()),
+        })
+    }
+}
+
+// 渲染目标的状态：管线必须和渲染通道的附件保持一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetState {
+    pub color_format: wgpu::TextureFormat,
+    // 深度附件的格式，None 表示渲染通道没有深度附件
+    pub depth_format: Option<wgpu::TextureFormat>,
+    // 管线没有指定比较函数时使用的深度比较函数
+    pub depth_compare: wgpu::CompareFunction,
+    // 颜色和深度附件的采样数，大于 1 时开启了 MSAA
+    pub sample_count: u32,
+}
+
+// 创建渲染管线所需的全部信息
+#[derive(Debug, Clone)]
+pub struct PipelineDescriptor {
+    pub label: String,
+    pub shader: Shader,
+    // 顶点着色器和片元着色器的入口函数名
+    pub vs_entry: String,
+    pub fs_entry: String,
+    pub vertex_layouts: Vec<wgpu::VertexBufferLayout<'static>>,
+    pub bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+    // 图元拓扑，比如三角形列表、线段列表
+    pub topology: wgpu::PrimitiveTopology,
+    // 剔除模式，None 表示不剔除
+    pub cull_mode: Option<wgpu::Face>,
+    pub blend: Option<wgpu::BlendState>,
+    // 是否写入深度，半透明物体和覆盖层一般不写入
+    pub depth_write_enabled: bool,
+    // 深度比较函数，None 表示使用渲染目标的默认值
+    pub depth_compare: Option<wgpu::CompareFunction>,
+    // 着色器里 override 常量的值，按名字对应
+    pub constants: Vec<(String, f64)>,
+}
+
+impl PipelineDescriptor {
+    pub fn new(label: &str, shader: Shader) -> Self {
+        Self {
+            label: label.to_string(),
+            shader,
+            vs_entry: "vs_main".to_string(),
+            fs_entry: "fs_main".to_string(),
+            vertex_layouts: vec![Vertex::desc()],
+            bind_group_layouts: vec![],
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: Some(wgpu::Face::Back),
+            blend: Some(wgpu::BlendState::REPLACE),
+            depth_write_enabled: true,
+            depth_compare: None,
+            constants: vec![],
+        }
+    }
+}
+
+// 保存了描述信息的渲染管线，可以在渲染目标变化时重建
+pub struct Pipeline {
+    pub desc: PipelineDescriptor,
+    pub pipeline: wgpu::RenderPipeline,
+}
+
+impl Pipeline {
+    pub fn new(device: &wgpu::Device, desc: PipelineDescriptor, targets: &TargetState) -> Self {
+        let module = desc.shader.create_module(device);
+        let pipeline = Self::create(device, &desc, &module, targets);
+        Self { desc, pipeline }
+    }
+
+    // 用已经编译好的着色器模块创建管线
+    pub fn create(
+        device: &wgpu::Device,
+        desc: &PipelineDescriptor,
+        module: &wgpu::ShaderModule,
+        targets: &TargetState,
+    ) -> wgpu::RenderPipeline {
+        let bind_group_layouts: Vec<&wgpu::BindGroupLayout> =
+            desc.bind_group_layouts.iter().collect();
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&desc.label),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        let constants: Vec<(&str, f64)> = desc
+            .constants
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+            .collect();
+        let compilation_options = wgpu::PipelineCompilationOptions {
+            constants: &constants,
+            ..Default::default()
+        };
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&desc.label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module,
+                entry_point: Some(&desc.vs_entry),
+                buffers: &desc.vertex_layouts,
+                compilation_options: compilation_options.clone(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module,
+                entry_point: Some(&desc.fs_entry),
+                // 颜色目标的格式必须和渲染通道的颜色附件一致
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: targets.color_format,
+                    blend: desc.blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: compilation_options.clone(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: desc.topology,
+                strip_index_format: None,
+                // 逆时针为正面
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: desc.cull_mode,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            // 深度模板状态必须和渲染通道的深度附件格式一致
+            depth_stencil: targets.depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: desc.depth_write_enabled,
+                depth_compare: desc.depth_compare.unwrap_or(targets.depth_compare),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: targets.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    // 渲染目标变化后重新创建管线
+    pub fn rebuild(&mut self, device: &wgpu::Device, targets: &TargetState) {
+        let module = self.desc.shader.create_module(device);
+        self.pipeline = Self::create(device, &self.desc, &module, targets);
+    }
+}
+
+// 网格：顶点缓冲区 + 可选的索引缓冲区
+pub struct Mesh {
+    pub vertex_buffer: wgpu::Buffer,
+    pub vertex_count: u32,
+    pub index_buffer: Option<wgpu::Buffer>,
+    pub index_count: u32,
+}
+
+impl Mesh {
+    // indices 为空时按顶点顺序绘制
+    pub fn new<V: bytemuck::Pod>(
+        device: &wgpu::Device,
+        label: &str,
+        vertices: &[V],
+        indices: &[u32],
+    ) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{label} Vertex Buffer")),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = (!indices.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{label} Index Buffer")),
+                contents: bytemuck::cast_slice(indices),
+                usage: wgpu::BufferUsages::INDEX,
+            })
+        });
+        Self {
+            vertex_buffer,
+            vertex_count: vertices.len() as u32,
+            index_buffer,
+            index_count: indices.len() as u32,
+        }
+    }
+
+    // 在渲染通道里绘制这个网格
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>, instances: Range<u32>) {
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        match &self.index_buffer {
+            Some(index_buffer) => {
+                pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..self.index_count, 0, instances);
+            }
+            None => pass.draw(0..self.vertex_count, instances),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstanceBufferId(pub usize);
+
+// 一次绘制：用哪条管线画哪个网格，以及需要绑定的绑定组
+#[derive(Debug, Clone)]
+pub struct DrawCall {
+    pub pipeline: PipelineId,
+    pub mesh: MeshId,
+    // 按顺序绑定到 group 0、1、2...
+    pub bind_groups: Vec<wgpu::BindGroup>,
+    pub instances: Range<u32>,
+    // 实例缓冲区，绑定到顶点缓冲区槽位 1，设置后 instances 由缓冲区里的实例数量决定
+    pub instance_buffer: Option<InstanceBufferId>,
+    // 是否在阴影通道里绘制，网格的顶点必须是 ModelVertex
+    pub casts_shadows: bool,
+}
+
+impl DrawCall {
+    pub fn new(pipeline: PipelineId, mesh: MeshId) -> Self {
+        Self {
+            pipeline,
+            mesh,
+            bind_groups: vec![],
+            instances: 0..1,
+            instance_buffer: None,
+            casts_shadows: false,
+        }
+    }
+
+    // 使用实例缓冲区绘制
+    pub fn with_instances(mut self, instance_buffer: InstanceBufferId) -> Self {
+        self.instance_buffer = Some(instance_buffer);
+        self
+    }
+
+    // 在阴影通道里绘制这个网格
+    pub fn with_shadows(mut self) -> Self {
+        self.casts_shadows = true;
+        self
+    }
+}
+
+// 管线、网格和绘制列表的集合
+pub struct Renderer {
+    pub targets: TargetState,
+    pipelines: Vec<Pipeline>,
+    meshes: Vec<Mesh>,
+    instance_buffers: Vec<InstanceBuffer>,
+    draws: Vec<DrawCall>,
+    // 添加或重新创建管线时加一，热重载据此判断是否要重新注册监听的着色器文件
+    pipelines_generation: u64,
+}
+
+impl Renderer {
+    pub fn new(targets: TargetState) -> Self {
+        Self {
+            targets,
+            pipelines: vec![],
+            meshes: vec![],
+            instance_buffers: vec![],
+            draws: vec![],
+            pipelines_generation: 0,
+        }
+    }
+
+    pub fn add_pipeline(&mut self, device: &wgpu::Device, desc: PipelineDescriptor) -> PipelineId {
+        self.pipelines_generation += 1;
+        self.pipelines
+            .push(Pipeline::new(device, desc, &self.targets));
+        PipelineId(self.pipelines.len() - 1)
+    }
+
+    pub fn pipeline(&self, id: PipelineId) -> &Pipeline {
+        &self.pipelines[id.0]
+    }
+
+    // 修改描述符（比如着色器路径）之后要调用 rebuild_pipeline 才会生效
+    pub fn pipeline_mut(&mut self, id: PipelineId) -> &mut Pipeline {
+        &mut self.pipelines[id.0]
+    }
+
+    // 用修改后的描述符重新创建一条管线
+    pub fn rebuild_pipeline(&mut self, device: &wgpu::Device, id: PipelineId) {
+        self.pipelines_generation += 1;
+        self.pipelines[id.0].rebuild(device, &self.targets);
+    }
+
+    pub fn add_mesh(&mut self, mesh: Mesh) -> MeshId {
+        self.meshes.push(mesh);
+        MeshId(self.meshes.len() - 1)
+    }
+
+    pub fn mesh(&self, id: MeshId) -> &Mesh {
+        &self.meshes[id.0]
+    }
+
+    pub fn add_instance_buffer(&mut self, instance_buffer: InstanceBuffer) -> InstanceBufferId {
+        self.instance_buffers.push(instance_buffer);
+        InstanceBufferId(self.instance_buffers.len() - 1)
+    }
+
+    pub fn instance_buffer(&self, id: InstanceBufferId) -> &InstanceBuffer {
+        &self.instance_buffers[id.0]
+    }
+
+    // 在 update() 里修改实例，渲染前由 flush_instances 上传
+    pub fn instance_buffer_mut(&mut self, id: InstanceBufferId) -> &mut InstanceBuffer {
+        &mut self.instance_buffers[id.0]
+    }
+
+    // 上传所有实例缓冲区里变化的部分
+    pub fn flush_instances(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        for instance_buffer in &mut self.instance_buffers {
+            instance_buffer.flush(device, queue);
+        }
+    }
+
+    // 添加一次绘制，绘制列表会在每一帧的主渲染通道里执行
+    pub fn add_draw(&mut self, draw: DrawCall) {
+        self.draws.push(draw);
+    }
+
+    pub fn clear_draws(&mut self) {
+        self.draws.clear();
+    }
+
+    // 渲染目标变化时，用新的目标状态重建所有管线
+    pub fn set_targets(&mut self, device: &wgpu::Device, targets: TargetState) {
+        if targets == self.targets {
+            return;
+        }
+        self.targets = targets;
+        self.pipelines_generation += 1;
+        for pipeline in &mut self.pipelines {
+            pipeline.rebuild(device, &targets);
+        }
+    }
+
+    pub fn pipelines_generation(&self) -> u64 {
+        self.pipelines_generation
+    }
+
+    // 所有来自磁盘文件的着色器路径
+    pub fn shader_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = Vec::new();
+        for pipeline in &self.pipelines {
+            if let Some(path) = &pipeline.desc.shader.path
+                && !paths.contains(path)
+            {
+                paths.push(path.clone());
+            }
+        }
+        paths
+    }
+
+    // 重新读取并编译 path 对应的着色器，替换所有使用它的管线，返回替换的管线数量
+    // 失败时所有管线保持原样
+    pub fn reload_shader(
+        &mut self,
+        device: &wgpu::Device,
+        path: &Path,
+    ) -> Result<usize, ShaderError> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let uses_path = |pipeline: &Pipeline| {
+            pipeline
+                .desc
+                .shader
+                .path
+                .as_ref()
+                .is_some_and(|p| p.canonicalize().unwrap_or_else(|_| p.clone()) == canonical)
+        };
+        let source = std::fs::read_to_string(path).map_err(|e| ShaderError {
+            path: path.to_path_buf(),
+            line: 0,
+            column: 0,
+            message: e.to_string(),
+        })?;
+        let shader = Shader {
+            label: path.display().to_string(),
+            source,
+            path: Some(path.to_path_buf()),
+        };
+
+        let descs: Vec<&PipelineDescriptor> = self
+            .pipelines
+            .iter()
+            .filter(|p| uses_path(p))
+            .map(|p| &p.desc)
+            .collect();
+        let rebuilt =
+            shader_reload::rebuild_pipelines(device, path, &shader, &descs, &self.targets)?;
+        let count = rebuilt.len();
+        let targets = self.pipelines.iter_mut().filter(|p| uses_path(p));
+        for (pipeline, new_pipeline) in targets.zip(rebuilt) {
+            pipeline.pipeline = new_pipeline;
+            pipeline.desc.shader.source = shader.source.clone();
+        }
+        Ok(count)
+    }
+
+    // 在已经开始的渲染通道里执行绘制列表
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>) {
+        for draw in &self.draws {
+            pass.set_pipeline(&self.pipelines[draw.pipeline.0].pipeline);
+            for (i, bind_group) in draw.bind_groups.iter().enumerate() {
+                pass.set_bind_group(i as u32, bind_group, &[]);
+            }
+            let instances = match draw.instance_buffer {
+                Some(id) => self.instance_buffers[id.0].bind(pass, 1),
+                None => draw.instances.clone(),
+            };
+            if !instances.is_empty() {
+                self.meshes[draw.mesh.0].draw(pass, instances);
+            }
+        }
+    }
+
+    // 阴影通道：只绘制投射阴影的网格，按有没有实例缓冲区选择阴影管线，绑定组由调用者设置
+    pub fn draw_shadow_casters(
+        &self,
+        pass: &mut wgpu::RenderPass<'_>,
+        mesh_pipeline: &wgpu::RenderPipeline,
+        instanced_pipeline: &wgpu::RenderPipeline,
+    ) {
+        for draw in self.draws.iter().filter(|d| d.casts_shadows) {
+            let instances = match draw.instance_buffer {
+                Some(id) => {
+                    pass.set_pipeline(instanced_pipeline);
+                    self.instance_buffers[id.0].bind(pass, 1)
+                }
+                None => {
+                    pass.set_pipeline(mesh_pipeline);
+                    draw.instances.clone()
+                }
+            };
+            if !instances.is_empty() {
+                self.meshes[draw.mesh.0].draw(pass, instances);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
+
+    #[test]
+    fn generation_changes_only_when_pipelines_are_recreated() {
+        let Some(app) = headless::test_device_app() else {
+            return;
+        };
+        let targets = app.renderer.targets;
+        let mut renderer = Renderer::new(targets);
+        crate::demo::add_triangle(&mut renderer, &app.device);
+        let id = PipelineId(0);
+        let added = renderer.pipelines_generation();
+        // 只是借用不算修改
+        let _ = renderer.pipeline_mut(id).desc.label.clone();
+        renderer.set_targets(&app.device, targets);
+        assert_eq!(renderer.pipelines_generation(), added);
+
+        renderer.rebuild_pipeline(&app.device, id);
+        let rebuilt = renderer.pipelines_generation();
+        assert!(rebuilt > added);
+        renderer.set_targets(
+            &app.device,
+            TargetState {
+                depth_format: None,
+                ..targets
+            },
+        );
+        assert!(renderer.pipelines_generation() > rebuilt);
+    }
+}
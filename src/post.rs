@@ -0,0 +1,592 @@
+// 后处理
+// 场景渲染到 HDR 纹理之后、色调映射之前，按 PostSettings::effects 的顺序运行一串全屏效果。
+// 每个效果是渲染图里的一个通道，读取上一步的结果、写入一张新的临时纹理，第一个效果读取场景的 HDR 纹理，
+// 最后一步写入的纹理交给色调映射，没有开启的效果时直接映射场景纹理。
+// FXAA 的边缘阈值是按显示的亮度定的，不放进 HDR 的效果链：开启时色调映射先写入一张展示平面格式的 LDR 临时纹理，
+// FXAA 读取它再写入展示平面，不管它在 effects 里排在哪里都在色调映射之后运行。
+// 效果的输出、泛光的降采样链和 LDR 纹理都是渲染图的临时纹理，由 TransientPool 分配和复用：
+// 生命周期不重叠的输出共用物理纹理，效果链自然就是乒乓交替的，这里不持有任何尺寸相关的纹理。
+
+use wgpu::util::DeviceExt;
+
+use crate::hdr::{HDR_FORMAT, HdrTarget};
+use crate::pipeline::{Pipeline, PipelineDescriptor, Shader, TargetState};
+use crate::render_graph::{GraphResource, PassContext, RenderGraph, TransientTexture};
+use crate::texture::{BindingKind, BindingLayout};
+
+// 泛光降采样链的最大级数，第一级是半分辨率
+pub const MAX_BLOOM_LEVELS: usize = 6;
+
+// 可以放进效果列表的后处理效果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostEffect {
+    Bloom,
+    Fxaa,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    // 亮度超过阈值的部分才会产生泛光，HDR 下 1.0 大约是白色
+    pub threshold: f32,
+    // 阈值附近的过渡宽度，0 表示硬阈值
+    pub knee: f32,
+    // 叠加回画面时的强度
+    pub intensity: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FxaaSettings {
+    pub enabled: bool,
+    // 沿边缘方向搜索的最大距离，单位是像素
+    pub span_max: f32,
+    // 局部对比度低于 max(edge_threshold_min, 最大亮度 × edge_threshold) 时不处理
+    pub edge_threshold: f32,
+    pub edge_threshold_min: f32,
+}
+
+impl Default for FxaaSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            span_max: 8.0,
+            edge_threshold: 0.125,
+            edge_threshold_min: 1.0 / 32.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostSettings {
+    // 效果的执行顺序，同一个效果出现多次时只运行第一次
+    pub effects: Vec<PostEffect>,
+    pub bloom: BloomSettings,
+    pub fxaa: FxaaSettings,
+}
+
+impl Default for PostSettings {
+    // 默认先泛光再抗锯齿，两者都关闭
+    fn default() -> Self {
+        Self {
+            effects: vec![PostEffect::Bloom, PostEffect::Fxaa],
+            bloom: BloomSettings::default(),
+            fxaa: FxaaSettings::default(),
+        }
+    }
+}
+
+impl PostSettings {
+    // 按顺序列出这一帧要运行的效果，跳过关闭的和重复的
+    pub fn enabled_effects(&self) -> Vec<PostEffect> {
+        let mut effects = Vec::new();
+        for &effect in &self.effects {
+            let enabled = match effect {
+                PostEffect::Bloom => self.bloom.enabled,
+                PostEffect::Fxaa => self.fxaa.enabled,
+            };
+            if enabled && !effects.contains(&effect) {
+                effects.push(effect);
+            }
+        }
+        effects
+    }
+}
+
+// 和 bloom.wgsl、fxaa.wgsl 里的 PostUniform 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    span_max: f32,
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    // FXAA 的输入是 sRGB 格式时为 1：采样得到的是解码后的线性值，算亮度前要近似地重新编码
+    srgb_input: u32,
+    _padding: f32,
+}
+
+impl PostUniform {
+    // 升采样后第一级里是所有级的总和，强度除以级数，泛光的亮度就不随分辨率变化
+    fn new(
+        settings: &PostSettings,
+        bloom_levels: usize,
+        output_format: wgpu::TextureFormat,
+    ) -> Self {
+        Self {
+            threshold: settings.bloom.threshold,
+            knee: settings.bloom.knee.max(0.0),
+            intensity: settings.bloom.intensity / bloom_levels.max(1) as f32,
+            span_max: settings.fxaa.span_max,
+            edge_threshold: settings.fxaa.edge_threshold,
+            edge_threshold_min: settings.fxaa.edge_threshold_min,
+            srgb_input: output_format.is_srgb() as u32,
+            _padding: 0.0,
+        }
+    }
+}
+
+// 泛光降采样链每一级的尺寸：从半分辨率开始每级减半，最短边到 1 像素或级数用完为止
+pub fn bloom_level_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mut sizes = Vec::new();
+    let (mut w, mut h) = (width / 2, height / 2);
+    while w >= 1 && h >= 1 && sizes.len() < MAX_BLOOM_LEVELS {
+        sizes.push((w, h));
+        w /= 2;
+        h /= 2;
+    }
+    sizes
+}
+
+pub struct PostProcess {
+    settings: PostSettings,
+    output_format: wgpu::TextureFormat,
+    buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    source_layout: BindingLayout,
+    composite_layout: BindingLayout,
+    prefilter: Pipeline,
+    downsample: Pipeline,
+    upsample: Pipeline,
+    composite: Pipeline,
+    fxaa: Pipeline,
+    // 场景纹理的尺寸，决定临时纹理的尺寸和泛光的级数
+    size: (u32, u32),
+}
+
+impl PostProcess {
+    // output_format 是展示平面的格式，FXAA 在色调映射之后以它为输入和输出
+    pub fn new(
+        device: &wgpu::Device,
+        scene: &HdrTarget,
+        output_format: wgpu::TextureFormat,
+        settings: PostSettings,
+    ) -> Self {
+        let source_entries = [
+            (wgpu::ShaderStages::FRAGMENT, BindingKind::TEXTURE_2D),
+            (wgpu::ShaderStages::FRAGMENT, BindingKind::FILTERING_SAMPLER),
+            (wgpu::ShaderStages::FRAGMENT, BindingKind::Uniform),
+        ];
+        let source_layout =
+            BindingLayout::new(device, "Post Source Bind Group Layout", &source_entries);
+        let mut composite_entries = source_entries.to_vec();
+        composite_entries.push((wgpu::ShaderStages::FRAGMENT, BindingKind::TEXTURE_2D));
+        let composite_layout = BindingLayout::new(
+            device,
+            "Bloom Composite Bind Group Layout",
+            &composite_entries,
+        );
+        let bloom_shader = Shader::from_wgsl("bloom.wgsl", include_str!("shaders/bloom.wgsl"));
+        let targets = TargetState {
+            color_format: HDR_FORMAT,
+            depth_format: None,
+            depth_compare: wgpu::CompareFunction::Always,
+            sample_count: 1,
+        };
+        let create = |label: &str,
+                      shader: &Shader,
+                      fs_entry: &str,
+                      layout: &BindingLayout,
+                      blend: wgpu::BlendState,
+                      targets: &TargetState| {
+            let mut desc = PipelineDescriptor::new(label, shader.clone());
+            desc.fs_entry = fs_entry.to_string();
+            // 全屏三角形由 vertex_index 生成
+            desc.vertex_layouts.clear();
+            desc.bind_group_layouts = vec![layout.layout.clone()];
+            desc.cull_mode = None;
+            desc.blend = Some(blend);
+            Pipeline::new(device, desc, targets)
+        };
+        let replace = wgpu::BlendState::REPLACE;
+        // 升采样的结果叠加到上一级降采样的结果上
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+        let prefilter = create(
+            "Bloom Prefilter Pipeline",
+            &bloom_shader,
+            "fs_prefilter",
+            &source_layout,
+            replace,
+            &targets,
+        );
+        let downsample = create(
+            "Bloom Downsample Pipeline",
+            &bloom_shader,
+            "fs_downsample",
+            &source_layout,
+            replace,
+            &targets,
+        );
+        let upsample = create(
+            "Bloom Upsample Pipeline",
+            &bloom_shader,
+            "fs_upsample",
+            &source_layout,
+            additive,
+            &targets,
+        );
+        let composite = create(
+            "Bloom Composite Pipeline",
+            &bloom_shader,
+            "fs_composite",
+            &composite_layout,
+            replace,
+            &targets,
+        );
+        let fxaa = create(
+            "FXAA Pipeline",
+            &Shader::from_wgsl("fxaa.wgsl", include_str!("shaders/fxaa.wgsl")),
+            "fs_main",
+            &source_layout,
+            replace,
+            &TargetState {
+                color_format: output_format,
+                ..targets
+            },
+        );
+        let size = scene.texture.size();
+        let levels = bloom_level_sizes(size.width, size.height).len();
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Uniform Buffer"),
+            contents: bytemuck::bytes_of(&PostUniform::new(&settings, levels, output_format)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // 降采样和升采样都依赖线性过滤，边缘外的采样取边缘的颜色
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        Self {
+            settings,
+            output_format,
+            buffer,
+            sampler,
+            source_layout,
+            composite_layout,
+            prefilter,
+            downsample,
+            upsample,
+            composite,
+            fxaa,
+            size: (size.width, size.height),
+        }
+    }
+
+    // 场景的 HDR 纹理重新创建（比如窗口尺寸变化）后，临时纹理的尺寸跟着变化
+    pub fn resize(&mut self, queue: &wgpu::Queue, scene: &HdrTarget) {
+        let size = scene.texture.size();
+        self.size = (size.width, size.height);
+        // 泛光的级数可能变了
+        self.write_uniform(queue);
+    }
+
+    pub fn settings(&self) -> &PostSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: PostSettings) {
+        self.settings = settings;
+        self.write_uniform(queue);
+    }
+
+    fn write_uniform(&self, queue: &wgpu::Queue) {
+        let uniform = PostUniform::new(
+            &self.settings,
+            self.bloom_levels().len(),
+            self.output_format,
+        );
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    fn bloom_levels(&self) -> Vec<(u32, u32)> {
+        bloom_level_sizes(self.size.0, self.size.1)
+    }
+
+    // 这一帧在色调映射之前运行的效果，画面太小、降采样链为空时跳过泛光
+    fn active_effects(&self) -> Vec<PostEffect> {
+        let mut effects = self.settings.enabled_effects();
+        effects.retain(|&e| match e {
+            PostEffect::Bloom => !self.bloom_levels().is_empty(),
+            PostEffect::Fxaa => false,
+        });
+        effects
+    }
+
+    // 开启 FXAA 时色调映射要写入 ldr_texture() 描述的临时纹理，再由 run_fxaa 写入展示平面
+    pub fn fxaa_enabled(&self) -> bool {
+        self.settings.enabled_effects().contains(&PostEffect::Fxaa)
+    }
+
+    // FXAA 的输入：和展示平面同样尺寸、同样格式
+    pub fn ldr_texture(&self) -> TransientTexture {
+        transient(self.size, self.output_format)
+    }
+
+    // 在渲染图里按顺序声明色调映射之前的效果，scene 是场景的 HDR 纹理。
+    // 返回最后一个效果写入的临时纹理，没有运行任何效果时返回 None，色调映射直接读取场景纹理
+    pub fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        device: &'a wgpu::Device,
+        scene: GraphResource,
+        scene_view: &'a wgpu::TextureView,
+    ) -> Option<GraphResource> {
+        let mut source = None;
+        for effect in self.active_effects() {
+            match effect {
+                PostEffect::Bloom => {
+                    let output =
+                        graph.create_texture("Bloom Output", transient(self.size, HDR_FORMAT));
+                    // 每一级用单独的纹理，不需要在同一张纹理的不同 mip 之间读写
+                    let levels: Vec<GraphResource> = self
+                        .bloom_levels()
+                        .into_iter()
+                        .map(|size| {
+                            graph.create_texture("Bloom Level", transient(size, HDR_FORMAT))
+                        })
+                        .collect();
+                    let mut pass = graph.add_pass("Bloom");
+                    pass.read(source.unwrap_or(scene));
+                    for &level in &levels {
+                        pass.write(level);
+                    }
+                    let output = pass.write(output);
+                    pass.execute(move |ctx| {
+                        let levels: Vec<&wgpu::TextureView> =
+                            levels.iter().map(|&level| ctx.texture(level)).collect();
+                        let source = source_view(ctx, source, scene_view);
+                        let output = ctx.texture(output);
+                        self.run_bloom(device, ctx.encoder, source, &levels, output);
+                    });
+                    source = Some(output);
+                }
+                PostEffect::Fxaa => unreachable!("FXAA 在色调映射之后运行"),
+            }
+        }
+        source
+    }
+
+    // 读取色调映射写入的 LDR 纹理，抗锯齿后写入 output
+    pub fn run_fxaa(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+    ) {
+        let group = self.source_group(device, input);
+        fullscreen_pass(encoder, "FXAA Pass", &self.fxaa, &group, output, true);
+    }
+
+    // 临时纹理每帧可能分配到不同的物理纹理，绑定组在录制命令时创建
+    fn source_group(&self, device: &wgpu::Device, view: &wgpu::TextureView) -> wgpu::BindGroup {
+        self.source_layout.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(view),
+                wgpu::BindingResource::Sampler(&self.sampler),
+                self.buffer.as_entire_binding(),
+            ],
+        )
+    }
+
+    fn run_bloom(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        levels: &[&wgpu::TextureView],
+        output: &wgpu::TextureView,
+    ) {
+        let level_groups: Vec<wgpu::BindGroup> = levels
+            .iter()
+            .map(|level| self.source_group(device, level))
+            .collect();
+        fullscreen_pass(
+            encoder,
+            "Bloom Prefilter Pass",
+            &self.prefilter,
+            &self.source_group(device, source),
+            levels[0],
+            true,
+        );
+        for (group, level) in level_groups.iter().zip(&levels[1..]) {
+            fullscreen_pass(
+                encoder,
+                "Bloom Downsample Pass",
+                &self.downsample,
+                group,
+                level,
+                true,
+            );
+        }
+        // 从最小的一级往上，每一级放大后叠加到上一级，最后第一级里是所有级的总和
+        for (group, level) in level_groups[1..].iter().zip(levels).rev() {
+            fullscreen_pass(
+                encoder,
+                "Bloom Upsample Pass",
+                &self.upsample,
+                group,
+                level,
+                false,
+            );
+        }
+        let composite_group = self.composite_layout.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(source),
+                wgpu::BindingResource::Sampler(&self.sampler),
+                self.buffer.as_entire_binding(),
+                wgpu::BindingResource::TextureView(levels[0]),
+            ],
+        );
+        fullscreen_pass(
+            encoder,
+            "Bloom Composite Pass",
+            &self.composite,
+            &composite_group,
+            output,
+            true,
+        );
+    }
+}
+
+// 后处理用到的临时纹理都是渲染目标，之后的通道还要采样它
+fn transient((width, height): (u32, u32), format: wgpu::TextureFormat) -> TransientTexture {
+    TransientTexture {
+        width,
+        height,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        sample_count: 1,
+    }
+}
+
+// 效果的输入：上一个效果写入的临时纹理，第一个效果读取场景纹理
+fn source_view<'e>(
+    ctx: &PassContext<'e>,
+    source: Option<GraphResource>,
+    scene_view: &'e wgpu::TextureView,
+) -> &'e wgpu::TextureView {
+    match source {
+        Some(source) => ctx.texture(source),
+        None => scene_view,
+    }
+}
+
+// 画一个覆盖 output 的全屏三角形，clear 为 false 时保留 output 原来的内容用于混合
+fn fullscreen_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    pipeline: &Pipeline,
+    bind_group: &wgpu::BindGroup,
+    output: &wgpu::TextureView,
+    clear: bool,
+) {
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: output,
+            resolve_target: None,
+            depth_slice: None,
+            ops: wgpu::Operations {
+                load: if clear {
+                    wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                } else {
+                    wgpu::LoadOp::Load
+                },
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        ..Default::default()
+    });
+    pass.set_pipeline(&pipeline.pipeline);
+    pass.set_bind_group(0, bind_group, &[]);
+    pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_levels_halve_until_one_pixel_or_max() {
+        assert_eq!(
+            bloom_level_sizes(64, 48),
+            vec![(32, 24), (16, 12), (8, 6), (4, 3), (2, 1)]
+        );
+        assert_eq!(bloom_level_sizes(1920, 1080).len(), MAX_BLOOM_LEVELS);
+        assert_eq!(bloom_level_sizes(1920, 1080)[0], (960, 540));
+        assert!(bloom_level_sizes(1, 1).is_empty());
+    }
+
+    // 效果的输出、泛光的每一级和 FXAA 的输入都从渲染图的纹理池分配，第二帧全部复用第一帧的纹理
+    #[test]
+    fn post_targets_are_allocated_from_the_graph_pool() {
+        use crate::headless;
+
+        let Some(mut app) = headless::test_device_app() else {
+            return;
+        };
+        let mut settings = app.post_settings().clone();
+        settings.bloom.enabled = true;
+        settings.fxaa.enabled = true;
+        app.set_post_settings(settings);
+        app.render().unwrap();
+        // 泛光的输出和 LDR 纹理格式不同，不能共用
+        let expected = bloom_level_sizes(16, 16).len() + 2;
+        assert_eq!(app.graph_pool.len(), expected);
+        app.render().unwrap();
+        assert_eq!(app.graph_pool.len(), expected);
+        app.set_post_settings(PostSettings::default());
+        app.render().unwrap();
+        assert!(app.graph_pool.is_empty());
+    }
+
+    #[test]
+    fn enabled_effects_keep_order_and_skip_disabled() {
+        let mut settings = PostSettings::default();
+        assert!(settings.enabled_effects().is_empty());
+        settings.bloom.enabled = true;
+        settings.fxaa.enabled = true;
+        assert_eq!(
+            settings.enabled_effects(),
+            vec![PostEffect::Bloom, PostEffect::Fxaa]
+        );
+        settings.effects = vec![PostEffect::Fxaa, PostEffect::Bloom, PostEffect::Fxaa];
+        assert_eq!(
+            settings.enabled_effects(),
+            vec![PostEffect::Fxaa, PostEffect::Bloom]
+        );
+        settings.fxaa.enabled = false;
+        assert_eq!(settings.enabled_effects(), vec![PostEffect::Bloom]);
+    }
+
+    #[test]
+    fn uniform_is_16_byte_aligned() {
+        assert_eq!(std::mem::size_of::<PostUniform>() % 16, 0);
+    }
+}
@@ -0,0 +1,701 @@
+// 渲染图
+// 每个通道声明自己读写哪些资源，渲染图按数据依赖排出执行顺序，不再需要在 render() 里手动排列。
+// 资源句柄带版本号：写入一个资源会产生新的版本，读取某个版本的通道排在产生它的通道之后，
+// 写入新版本的通道排在所有读取旧版本的通道之后，所以声明通道的先后不影响执行顺序。
+// 从标记为输出的资源倒推，结果没有被使用的通道会被剔除；有副作用（比如回读）的通道总是保留。
+// 临时纹理只在一帧之内使用，由渲染图从纹理池里分配，描述相同、生命周期不重叠的临时纹理共用一张物理纹理。
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Write;
+
+// 渲染图里的资源句柄：资源的下标加上版本号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GraphResource {
+    index: usize,
+    version: u32,
+}
+
+// 临时纹理的描述，描述完全相同的临时纹理才能共用物理纹理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransientTexture {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub usage: wgpu::TextureUsages,
+    pub sample_count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphError {
+    // 通道之间的依赖形成了环，里面是环上（以及依赖环）的通道
+    Cycle(Vec<String>),
+}
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::Cycle(passes) => {
+                write!(f, "渲染图的通道之间存在循环依赖: {}", passes.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+struct Resource {
+    name: String,
+    // None 表示外部导入的资源，渲染图只跟踪依赖，不管理它的显存
+    transient: Option<TransientTexture>,
+    // 已经产生的版本数，最新的版本号是 versions - 1
+    versions: u32,
+}
+
+// 通道执行时拿到的上下文
+pub struct PassContext<'e> {
+    pub encoder: &'e mut wgpu::CommandEncoder,
+    // 按资源下标排列，只有这一帧用到的临时纹理有值
+    textures: &'e [Option<&'e wgpu::TextureView>],
+}
+
+impl<'e> PassContext<'e> {
+    // 临时纹理分配到的视图，返回的引用不借用上下文，可以和 encoder 同时使用
+    pub fn texture(&self, resource: GraphResource) -> &'e wgpu::TextureView {
+        self.textures[resource.index].expect("只有临时纹理才能通过 PassContext 获取")
+    }
+}
+
+type PassFn<'a> = Box<dyn FnOnce(&mut PassContext<'_>) + 'a>;
+
+struct Pass<'a> {
+    name: String,
+    reads: Vec<GraphResource>,
+    // 这个通道产生的新版本，被覆盖的旧版本是 version - 1
+    writes: Vec<GraphResource>,
+    side_effect: bool,
+    execute: Option<PassFn<'a>>,
+}
+
+impl Pass<'_> {
+    // 执行前需要准备好的所有版本：读取的版本和被写入覆盖的旧版本
+    fn inputs(&self) -> impl Iterator<Item = GraphResource> + '_ {
+        self.reads
+            .iter()
+            .copied()
+            .chain(self.writes.iter().map(|w| GraphResource {
+                index: w.index,
+                version: w.version - 1,
+            }))
+    }
+}
+
+// 编译结果：执行顺序和临时纹理的分配
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledGraph {
+    // 保留下来的通道，按执行顺序排列
+    pub order: Vec<usize>,
+    // 被剔除的通道，按声明顺序排列
+    pub culled: Vec<usize>,
+    // 需要分配的物理纹理
+    pub slots: Vec<TransientTexture>,
+    // 每个资源分配到的物理纹理，导入的资源和没有用到的临时纹理是 None
+    pub assignments: Vec<Option<usize>>,
+}
+
+// 声明一个通道的读写，最后用 execute 给出录制命令的闭包
+pub struct PassBuilder<'g, 'a> {
+    graph: &'g mut RenderGraph<'a>,
+    pass: usize,
+}
+
+impl<'a> PassBuilder<'_, 'a> {
+    pub fn read(&mut self, resource: GraphResource) -> &mut Self {
+        self.graph.passes[self.pass].reads.push(resource);
+        self
+    }
+
+    // 写入资源的最新版本，返回写入之后的新版本，之后的通道读取新版本才能看到这次写入
+    pub fn write(&mut self, resource: GraphResource) -> GraphResource {
+        let target = &mut self.graph.resources[resource.index];
+        assert_eq!(
+            resource.version + 1,
+            target.versions,
+            "{} 的第 {} 版已经被写入过，只能写入最新的版本",
+            target.name,
+            resource.version
+        );
+        let written = GraphResource {
+            index: resource.index,
+            version: target.versions,
+        };
+        target.versions += 1;
+        self.graph.passes[self.pass].writes.push(written);
+        written
+    }
+
+    // 标记这个通道有渲染图看不到的副作用，即使没有输出被使用也不会被剔除
+    pub fn side_effect(&mut self) -> &mut Self {
+        self.graph.passes[self.pass].side_effect = true;
+        self
+    }
+
+    pub fn execute(self, f: impl FnOnce(&mut PassContext<'_>) + 'a) {
+        self.graph.passes[self.pass].execute = Some(Box::new(f));
+    }
+}
+
+// 一帧的渲染图，闭包可以借用这一帧里的任何数据，执行后就丢弃
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    resources: Vec<Resource>,
+    passes: Vec<Pass<'a>>,
+    outputs: Vec<GraphResource>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 导入渲染图之外的资源（展示平面、持久的纹理和缓冲区），只用来跟踪依赖
+    pub fn import(&mut self, name: &str) -> GraphResource {
+        self.add_resource(name, None)
+    }
+
+    // 声明一张临时纹理，第一个写入它的通道之前内容是未定义的
+    pub fn create_texture(&mut self, name: &str, desc: TransientTexture) -> GraphResource {
+        self.add_resource(name, Some(desc))
+    }
+
+    fn add_resource(&mut self, name: &str, transient: Option<TransientTexture>) -> GraphResource {
+        self.resources.push(Resource {
+            name: name.to_string(),
+            transient,
+            versions: 1,
+        });
+        GraphResource {
+            index: self.resources.len() - 1,
+            version: 0,
+        }
+    }
+
+    // 标记某个版本是这一帧的最终结果，产生它的通道和它依赖的通道都会保留
+    pub fn mark_output(&mut self, resource: GraphResource) {
+        self.outputs.push(resource);
+    }
+
+    pub fn add_pass(&mut self, name: &str) -> PassBuilder<'_, 'a> {
+        self.passes.push(Pass {
+            name: name.to_string(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+            side_effect: false,
+            execute: None,
+        });
+        let pass = self.passes.len() - 1;
+        PassBuilder { graph: self, pass }
+    }
+
+    pub fn pass_name(&self, pass: usize) -> &str {
+        &self.passes[pass].name
+    }
+
+    // 剔除、排序并分配临时纹理
+    pub fn compile(&self) -> Result<CompiledGraph, GraphError> {
+        let mut producers = HashMap::new();
+        for (p, pass) in self.passes.iter().enumerate() {
+            for &w in &pass.writes {
+                producers.insert(w, p);
+            }
+        }
+
+        // 从输出和有副作用的通道倒推需要保留的通道
+        let mut needed = vec![false; self.passes.len()];
+        let mut stack: Vec<usize> = self
+            .outputs
+            .iter()
+            .filter_map(|r| producers.get(r).copied())
+            .chain((0..self.passes.len()).filter(|&p| self.passes[p].side_effect))
+            .collect();
+        while let Some(p) = stack.pop() {
+            if std::mem::replace(&mut needed[p], true) {
+                continue;
+            }
+            stack.extend(
+                self.passes[p]
+                    .inputs()
+                    .filter_map(|r| producers.get(&r).copied()),
+            );
+        }
+
+        // 依赖边：产生者在读取者之前（写后读），读取旧版本的在写入新版本的之前（读后写）
+        let mut readers: HashMap<GraphResource, Vec<usize>> = HashMap::new();
+        for (p, pass) in self.passes.iter().enumerate() {
+            if needed[p] {
+                for r in pass.inputs() {
+                    readers.entry(r).or_default().push(p);
+                }
+            }
+        }
+        let mut successors = vec![Vec::new(); self.passes.len()];
+        let mut in_degree = vec![0usize; self.passes.len()];
+        let mut add_edge = |from: usize, to: usize| {
+            if from != to && !successors[from].contains(&to) {
+                successors[from].push(to);
+                in_degree[to] += 1;
+            }
+        };
+        for (p, pass) in self.passes.iter().enumerate() {
+            if !needed[p] {
+                continue;
+            }
+            for r in pass.inputs() {
+                if let Some(&producer) = producers.get(&r) {
+                    add_edge(producer, p);
+                }
+            }
+            for w in &pass.writes {
+                let old = GraphResource {
+                    index: w.index,
+                    version: w.version - 1,
+                };
+                for &reader in readers.get(&old).into_iter().flatten() {
+                    add_edge(reader, p);
+                }
+            }
+        }
+
+        // Kahn 拓扑排序，可以同时执行的通道按声明顺序排列，结果是确定的
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..self.passes.len())
+            .filter(|&p| needed[p] && in_degree[p] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::new();
+        while let Some(Reverse(p)) = ready.pop() {
+            order.push(p);
+            for &next in &successors[p] {
+                in_degree[next] -= 1;
+                if in_degree[next] == 0 {
+                    ready.push(Reverse(next));
+                }
+            }
+        }
+        let kept = needed.iter().filter(|&&n| n).count();
+        if order.len() < kept {
+            let stuck = (0..self.passes.len())
+                .filter(|&p| needed[p] && !order.contains(&p))
+                .map(|p| self.passes[p].name.clone())
+                .collect();
+            return Err(GraphError::Cycle(stuck));
+        }
+
+        // 每张临时纹理从第一次到最后一次被使用的区间
+        let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; self.resources.len()];
+        for (step, &p) in order.iter().enumerate() {
+            let pass = &self.passes[p];
+            for r in pass.reads.iter().chain(&pass.writes) {
+                if self.resources[r.index].transient.is_some() {
+                    let lifetime = lifetimes[r.index].get_or_insert((step, step));
+                    lifetime.1 = step;
+                }
+            }
+        }
+        let mut transients: Vec<(usize, (usize, usize))> = lifetimes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, lifetime)| lifetime.map(|l| (i, l)))
+            .collect();
+        transients.sort_by_key(|&(i, (first, _))| (first, i));
+        // 贪心分配：找一张描述相同、上一个使用者已经结束的物理纹理，找不到就新建
+        let mut slots: Vec<TransientTexture> = Vec::new();
+        let mut slot_last_use: Vec<usize> = Vec::new();
+        let mut assignments = vec![None; self.resources.len()];
+        for (i, (first, last)) in transients {
+            let desc = self.resources[i].transient.unwrap();
+            let slot =
+                match (0..slots.len()).find(|&s| slots[s] == desc && slot_last_use[s] < first) {
+                    Some(slot) => slot,
+                    None => {
+                        slots.push(desc);
+                        slot_last_use.push(0);
+                        slots.len() - 1
+                    }
+                };
+            slot_last_use[slot] = last;
+            assignments[i] = Some(slot);
+        }
+
+        Ok(CompiledGraph {
+            culled: (0..self.passes.len()).filter(|&p| !needed[p]).collect(),
+            order,
+            slots,
+            assignments,
+        })
+    }
+
+    // 按编译出的顺序执行保留下来的通道，临时纹理从 pool 里取
+    pub fn execute(
+        self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        pool: &mut TransientPool,
+    ) -> Result<(), GraphError> {
+        let compiled = self.compile()?;
+        pool.allocate(device, &compiled.slots);
+        let textures: Vec<Option<&wgpu::TextureView>> = compiled
+            .assignments
+            .iter()
+            .map(|slot| slot.map(|s| pool.view(s)))
+            .collect();
+        let mut passes: Vec<Option<Pass>> = self.passes.into_iter().map(Some).collect();
+        let mut ctx = PassContext {
+            encoder,
+            textures: &textures,
+        };
+        for p in compiled.order {
+            if let Some(execute) = passes[p].take().and_then(|pass| pass.execute) {
+                execute(&mut ctx);
+            }
+        }
+        Ok(())
+    }
+
+    // 导出 Graphviz DOT 格式：方框是通道，椭圆是资源的各个版本，被剔除的通道画成虚线
+    pub fn to_dot(&self) -> Result<String, GraphError> {
+        let compiled = self.compile()?;
+        let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut dot = String::from("digraph RenderGraph {\n    rankdir=LR;\n");
+        for (p, pass) in self.passes.iter().enumerate() {
+            let label = match compiled.order.iter().position(|&o| o == p) {
+                Some(step) => format!("{}. {}", step + 1, quote(&pass.name)),
+                None => format!("{} (culled)", quote(&pass.name)),
+            };
+            let style = if compiled.culled.contains(&p) {
+                "style=dashed, color=gray"
+            } else {
+                "style=filled, fillcolor=lightblue"
+            };
+            let _ = writeln!(dot, "    p{p} [label=\"{label}\", shape=box, {style}];");
+        }
+        for (i, resource) in self.resources.iter().enumerate() {
+            let kind = match (resource.transient, compiled.assignments[i]) {
+                (Some(_), Some(slot)) => format!("transient #{slot}"),
+                (Some(_), None) => "transient, unused".to_string(),
+                (None, _) => "imported".to_string(),
+            };
+            let output = |version| self.outputs.contains(&GraphResource { index: i, version });
+            for version in 0..resource.versions {
+                let shape = if output(version) {
+                    "doubleoctagon"
+                } else {
+                    "ellipse"
+                };
+                let _ = writeln!(
+                    dot,
+                    "    r{i}_{version} [label=\"{} v{version}\\n({kind})\", shape={shape}];",
+                    quote(&resource.name)
+                );
+            }
+        }
+        for (p, pass) in self.passes.iter().enumerate() {
+            for r in pass.inputs() {
+                let _ = writeln!(dot, "    r{}_{} -> p{p};", r.index, r.version);
+            }
+            for w in &pass.writes {
+                let _ = writeln!(dot, "    p{p} -> r{}_{};", w.index, w.version);
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+}
+
+// 跨帧复用的临时纹理，每帧按编译结果取用，这一帧没有用到的纹理会被释放
+#[derive(Default)]
+pub struct TransientPool {
+    textures: Vec<(TransientTexture, wgpu::TextureView)>,
+}
+
+impl TransientPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 让第 i 张纹理对应 slots[i]，描述相同的旧纹理直接复用
+    fn allocate(&mut self, device: &wgpu::Device, slots: &[TransientTexture]) {
+        let mut old = std::mem::take(&mut self.textures);
+        for &desc in slots {
+            let texture = match old.iter().position(|(d, _)| *d == desc) {
+                Some(i) => old.swap_remove(i),
+                None => (desc, Self::create(device, &desc)),
+            };
+            self.textures.push(texture);
+        }
+    }
+
+    fn create(device: &wgpu::Device, desc: &TransientTexture) -> wgpu::TextureView {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Render Graph Transient"),
+                size: wgpu::Extent3d {
+                    width: desc.width,
+                    height: desc.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: desc.sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: desc.format,
+                usage: desc.usage,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn view(&self, slot: usize) -> &wgpu::TextureView {
+        &self.textures[slot].1
+    }
+
+    // 当前持有的物理纹理数量
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc(width: u32) -> TransientTexture {
+        TransientTexture {
+            width,
+            height: width,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            sample_count: 1,
+        }
+    }
+
+    fn names(graph: &RenderGraph, passes: &[usize]) -> Vec<String> {
+        passes
+            .iter()
+            .map(|&p| graph.pass_name(p).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn passes_are_ordered_by_data_flow_not_declaration() {
+        let mut graph = RenderGraph::new();
+        let surface = graph.import("Surface");
+        let color = graph.create_texture("Color", desc(8));
+        // 先声明读取者、后声明产生者，顺序应当由依赖决定
+        let lit = GraphResource {
+            index: color.index,
+            version: 1,
+        };
+        let mut present = graph.add_pass("Present");
+        present.read(lit);
+        let presented = present.write(surface);
+        let mut scene = graph.add_pass("Scene");
+        assert_eq!(scene.write(color), lit);
+        graph.mark_output(presented);
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(names(&graph, &compiled.order), ["Scene", "Present"]);
+        assert!(compiled.culled.is_empty());
+    }
+
+    #[test]
+    fn readers_of_old_version_run_before_the_next_write() {
+        let mut graph = RenderGraph::new();
+        let hdr = graph.import("HDR");
+        let stats = graph.import("Stats");
+        let surface = graph.import("Surface");
+        let mut scene = graph.add_pass("Scene");
+        let lit = scene.write(hdr);
+        // Overlay 覆盖 lit 之后的版本，统计必须先读到 lit
+        let mut overlay = graph.add_pass("Overlay");
+        let overlaid = overlay.write(lit);
+        let mut measure = graph.add_pass("Measure");
+        measure.read(lit);
+        let measured = measure.write(stats);
+        let mut present = graph.add_pass("Present");
+        present.read(overlaid).read(measured);
+        let out = present.write(surface);
+        graph.mark_output(out);
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(
+            names(&graph, &compiled.order),
+            ["Scene", "Measure", "Overlay", "Present"]
+        );
+    }
+
+    #[test]
+    fn passes_without_used_outputs_are_culled() {
+        let mut graph = RenderGraph::new();
+        let surface = graph.import("Surface");
+        let unused = graph.import("Luminance");
+        let log = graph.import("Readback");
+        let mut exposure = graph.add_pass("Auto Exposure");
+        exposure.write(unused);
+        let mut readback = graph.add_pass("Readback");
+        readback.write(log);
+        readback.side_effect();
+        let mut tonemap = graph.add_pass("Tonemap");
+        let out = tonemap.write(surface);
+        graph.mark_output(out);
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(names(&graph, &compiled.order), ["Readback", "Tonemap"]);
+        assert_eq!(names(&graph, &compiled.culled), ["Auto Exposure"]);
+        let dot = graph.to_dot().unwrap();
+        assert!(dot.starts_with("digraph RenderGraph {"));
+        assert!(dot.contains("Auto Exposure (culled)\", shape=box, style=dashed"));
+        assert!(dot.contains("p2 -> r0_1;"));
+        assert!(dot.contains("shape=doubleoctagon"));
+    }
+
+    #[test]
+    fn transients_with_disjoint_lifetimes_share_a_slot() {
+        let mut graph = RenderGraph::new();
+        let surface = graph.import("Surface");
+        let a = graph.create_texture("A", desc(8));
+        let b = graph.create_texture("B", desc(8));
+        let c = graph.create_texture("C", desc(8));
+        let small = graph.create_texture("Small", desc(4));
+        // A -> B -> C -> Surface：A 在 B 写完之后就不再使用，C 可以复用 A 的纹理
+        let mut pass = graph.add_pass("A");
+        let a1 = pass.write(a);
+        let mut pass = graph.add_pass("B");
+        pass.read(a1);
+        let b1 = pass.write(b);
+        let mut pass = graph.add_pass("Small");
+        pass.read(b1);
+        let s1 = pass.write(small);
+        let mut pass = graph.add_pass("C");
+        pass.read(s1);
+        let c1 = pass.write(c);
+        let mut pass = graph.add_pass("Present");
+        pass.read(c1);
+        let out = pass.write(surface);
+        graph.mark_output(out);
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(compiled.slots.len(), 3);
+        assert_eq!(compiled.assignments[a.index], compiled.assignments[c.index]);
+        assert_ne!(compiled.assignments[a.index], compiled.assignments[b.index]);
+        assert_eq!(compiled.assignments[surface.index], None);
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let mut graph = RenderGraph::new();
+        let a = graph.import("A");
+        let b = graph.import("B");
+        let c = graph.import("C");
+        // Q 覆盖 A 的第 0 版并产生 C，P 读 A 的第 0 版和 Q 产生的 C：
+        // P 必须在 Q 覆盖 A 之前，又必须在 Q 产生 C 之后
+        let mut q = graph.add_pass("Q");
+        q.write(a);
+        let c1 = q.write(c);
+        let mut p = graph.add_pass("P");
+        p.read(a).read(c1);
+        let out = p.write(b);
+        graph.mark_output(out);
+
+        match graph.compile() {
+            Err(GraphError::Cycle(passes)) => assert_eq!(passes, ["Q", "P"]),
+            other => panic!("应当检测到循环依赖: {other:?}"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "只能写入最新的版本")]
+    fn writing_a_stale_version_panics() {
+        let mut graph = RenderGraph::new();
+        let hdr = graph.import("HDR");
+        graph.add_pass("First").write(hdr);
+        graph.add_pass("Second").write(hdr);
+    }
+
+    // 临时纹理在 GPU 上真正分配：清成红色后复制到导入的纹理里读回，第二帧复用池里的纹理
+    #[test]
+    fn transient_textures_are_pooled_across_frames() {
+        use crate::headless;
+
+        let Some(app) = headless::test_device_app() else {
+            return;
+        };
+        let (device, queue) = (app.device(), app.queue());
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Graph Target"),
+            size: wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let mut pool = TransientPool::new();
+        for _ in 0..2 {
+            let mut graph = RenderGraph::new();
+            let output = graph.import("Target");
+            let color = graph.create_texture(
+                "Color",
+                TransientTexture {
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                    ..desc(4)
+                },
+            );
+            let mut copy = graph.add_pass("Copy");
+            let red = GraphResource {
+                index: color.index,
+                version: 1,
+            };
+            copy.read(red);
+            let copied = copy.write(output);
+            copy.execute(|ctx| {
+                ctx.encoder.copy_texture_to_texture(
+                    ctx.texture(red).texture().as_image_copy(),
+                    target.as_image_copy(),
+                    target.size(),
+                );
+            });
+            let mut clear = graph.add_pass("Clear");
+            clear.write(color);
+            clear.execute(move |ctx| {
+                ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Clear"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: ctx.texture(color),
+                        resolve_target: None,
+                        depth_slice: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    ..Default::default()
+                });
+            });
+            graph.mark_output(copied);
+            let mut encoder = device.create_command_encoder(&Default::default());
+            graph.execute(device, &mut encoder, &mut pool).unwrap();
+            queue.submit(Some(encoder.finish()));
+            assert_eq!(pool.len(), 1);
+        }
+        let pixels = headless::read_texture_rgba8(device, queue, &target).unwrap();
+        assert!(pixels.chunks(4).all(|p| p == [255, 0, 0, 255]));
+    }
+}
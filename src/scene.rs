@@ -0,0 +1,534 @@
+// glTF 2.0 场景导入
+// 支持 .gltf（外部或 data URI 内嵌的缓冲区和图片）和 .glb（二进制块内嵌）。
+// 导入默认场景的节点层级并计算每个节点的世界变换，每个网格可以有多个图元，
+// 每个图元使用一个 PBR 金属度-粗糙度材质；相机节点转换成 Camera。
+// 渲染时同一个网格被多个节点引用的情况用实例缓冲区一次画完，节点的世界矩阵就是实例的模型矩阵。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::camera::Camera;
+use crate::instance::{Instance, InstanceBuffer};
+use crate::material::{MaterialId, Materials, PbrMaterial, PbrParams, PbrTextures};
+use crate::model::{self, ModelVertex};
+use crate::pipeline::{DrawCall, Mesh, PipelineId, Renderer};
+use crate::texture::{BindingLayout, ColorSpace, Texture};
+
+// 导入场景时可能出现的错误
+#[derive(Debug)]
+pub enum SceneError {
+    // 文件打不开、JSON 格式错误、缓冲区或图片加载失败
+    Gltf { path: PathBuf, error: gltf::Error },
+    // 能解析但数据不受支持或不一致
+    Invalid { path: PathBuf, message: String },
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Gltf { path, error } => {
+                write!(f, "导入 glTF 场景 {} 失败: {error}", path.display())
+            }
+            SceneError::Invalid { path, message } => {
+                write!(f, "glTF 场景 {} 的数据有误: {message}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// 场景节点，local 是相对父节点的变换，world 是世界变换
+pub struct SceneNode {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub local: Mat4,
+    pub world: Mat4,
+    // Scene::meshes 里的下标
+    pub mesh: Option<usize>,
+    // Scene::cameras 里的下标
+    pub camera: Option<usize>,
+}
+
+// 网格里的一个图元，material 是 Scene::materials 里的下标
+pub struct ScenePrimitive {
+    pub mesh: Mesh,
+    pub material: usize,
+}
+
+pub struct SceneMesh {
+    pub name: String,
+    pub primitives: Vec<ScenePrimitive>,
+}
+
+// 透视相机，aspect_ratio 为 None 时使用窗口的宽高比
+pub struct SceneCamera {
+    pub name: String,
+    pub node: usize,
+    pub yfov: f32,
+    pub znear: f32,
+    pub zfar: Option<f32>,
+    pub aspect_ratio: Option<f32>,
+}
+
+pub struct Scene {
+    pub nodes: Vec<SceneNode>,
+    // 没有父节点的节点
+    pub roots: Vec<usize>,
+    pub meshes: Vec<SceneMesh>,
+    pub materials: Vec<PbrMaterial>,
+    pub cameras: Vec<SceneCamera>,
+}
+
+impl Scene {
+    // 导入 .gltf 或 .glb 文件的默认场景
+    pub fn load_gltf(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &BindingLayout,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let (document, buffers, images) = gltf::import(path).map_err(|error| SceneError::Gltf {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let invalid = |message: String| SceneError::Invalid {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        // 多个材质共用的纹理只创建一次；同一张图片可能既被当作颜色贴图又被当作数据贴图，
+        // 所以按颜色空间分别缓存
+        let mut cache: HashMap<(usize, ColorSpace), Texture> = HashMap::new();
+        let mut load_texture = |texture: gltf::Texture, color_space: ColorSpace| {
+            if let Some(loaded) = cache.get(&(texture.index(), color_space)) {
+                return Ok(loaded.clone());
+            }
+            let image = &images[texture.source().index()];
+            let pixels = to_rgba8(image).map_err(&invalid)?;
+            let label = texture.name().unwrap_or("glTF Texture");
+            let mut loaded = Texture::from_pixels(
+                device,
+                queue,
+                &pixels,
+                image.width,
+                image.height,
+                match color_space {
+                    ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+                    ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+                },
+                label,
+            );
+            loaded.sampler = create_sampler(device, &texture.sampler(), label);
+            cache.insert((texture.index(), color_space), loaded.clone());
+            Ok::<_, SceneError>(loaded)
+        };
+
+        let mut materials = Vec::new();
+        for m in document.materials() {
+            let pbr = m.pbr_metallic_roughness();
+            let params = PbrParams {
+                base_color: pbr.base_color_factor(),
+                emissive: m.emissive_factor(),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                normal_scale: m.normal_texture().map_or(1.0, |t| t.scale()),
+                occlusion_strength: m.occlusion_texture().map_or(1.0, |t| t.strength()),
+                ..Default::default()
+            };
+            let textures = PbrTextures {
+                base_color: pbr
+                    .base_color_texture()
+                    .map(|t| load_texture(t.texture(), ColorSpace::Srgb))
+                    .transpose()?,
+                metallic_roughness: pbr
+                    .metallic_roughness_texture()
+                    .map(|t| load_texture(t.texture(), ColorSpace::Linear))
+                    .transpose()?,
+                normal: m
+                    .normal_texture()
+                    .map(|t| load_texture(t.texture(), ColorSpace::Linear))
+                    .transpose()?,
+                occlusion: m
+                    .occlusion_texture()
+                    .map(|t| load_texture(t.texture(), ColorSpace::Linear))
+                    .transpose()?,
+                emissive: m
+                    .emissive_texture()
+                    .map(|t| load_texture(t.texture(), ColorSpace::Srgb))
+                    .transpose()?,
+            };
+            let name = m.name().unwrap_or("glTF Material");
+            materials.push(PbrMaterial::new(
+                device, queue, layout, name, params, textures,
+            ));
+        }
+
+        let mut default_material = None;
+        let mut meshes = Vec::new();
+        for m in document.meshes() {
+            let name = m.name().unwrap_or("glTF Mesh").to_string();
+            let mut primitives = Vec::new();
+            for p in m.primitives() {
+                if p.mode() != gltf::mesh::Mode::Triangles {
+                    log::warn!("跳过网格 {name} 里不是三角形列表的图元: {:?}", p.mode());
+                    continue;
+                }
+                let (vertices, indices) =
+                    read_primitive(&p, &buffers).map_err(|e| invalid(format!("{name}: {e}")))?;
+                let material = match p.material().index() {
+                    Some(index) => index,
+                    // 没有材质的图元使用 glTF 规定的默认材质
+                    None => *default_material.get_or_insert_with(|| {
+                        materials.push(PbrMaterial::new(
+                            device,
+                            queue,
+                            layout,
+                            "Default",
+                            PbrParams::default(),
+                            PbrTextures::default(),
+                        ));
+                        materials.len() - 1
+                    }),
+                };
+                primitives.push(ScenePrimitive {
+                    mesh: Mesh::new(device, &name, &vertices, &indices),
+                    material,
+                });
+            }
+            meshes.push(SceneMesh { name, primitives });
+        }
+
+        let mut cameras = Vec::new();
+        let mut nodes = Vec::new();
+        let mut roots = Vec::new();
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or_else(|| invalid("没有任何场景".to_string()))?;
+        for node in scene.nodes() {
+            let root = add_node(&node, None, Mat4::IDENTITY, &mut nodes, &mut cameras);
+            roots.push(root);
+        }
+        Ok(Self {
+            nodes,
+            roots,
+            meshes,
+            materials,
+            cameras,
+        })
+    }
+
+    // 用场景里的第 index 个相机创建 Camera，aspect 是窗口的宽高比
+    pub fn camera(&self, index: usize, aspect: f32) -> Option<Camera> {
+        let c = self.cameras.get(index)?;
+        let world = self.nodes[c.node].world;
+        // glTF 的相机看向本地坐标的 -Z，上方是 +Y
+        let eye = world.transform_point3(Vec3::ZERO);
+        let forward = world
+            .transform_vector3(Vec3::NEG_Z)
+            .normalize_or(Vec3::NEG_Z);
+        Some(Camera {
+            eye,
+            target: eye + forward,
+            up: world.transform_vector3(Vec3::Y).normalize_or(Vec3::Y),
+            aspect: c.aspect_ratio.unwrap_or(aspect),
+            fovy: c.yfov,
+            znear: c.znear,
+            // 没有远平面表示无限远，这里取一个足够大的值
+            zfar: c.zfar.unwrap_or(1000.0),
+            reverse_z: false,
+        })
+    }
+
+    // 把场景交给 renderer 绘制：每个图元一次绘制调用，引用同一个网格的节点作为实例
+    // group(0) 是 scene_bind_group（一般是相机），group(1) 是材质，
+    // shared_bind_groups 依次绑定到 group(2) 之后（比如光源和阴影、环境光照）
+    // 场景的材质移交给 materials，返回的 id 和 Scene::materials 的顺序一致
+    pub fn add_to_renderer(
+        self,
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        pipeline: PipelineId,
+        scene_bind_group: &wgpu::BindGroup,
+        shared_bind_groups: &[&wgpu::BindGroup],
+        materials: &mut Materials,
+    ) -> Vec<MaterialId> {
+        let Self { nodes, meshes, .. } = self;
+        let material_ids: Vec<MaterialId> = self
+            .materials
+            .into_iter()
+            .map(|m| materials.add(m))
+            .collect();
+        for (mesh_index, mesh) in meshes.into_iter().enumerate() {
+            let instances: Vec<Instance> = nodes
+                .iter()
+                .filter(|n| n.mesh == Some(mesh_index))
+                .map(|n| {
+                    let (scale, rotation, position) = n.world.to_scale_rotation_translation();
+                    Instance {
+                        position,
+                        rotation,
+                        scale,
+                        ..Default::default()
+                    }
+                })
+                .collect();
+            if instances.is_empty() {
+                continue;
+            }
+            let instance_buffer =
+                renderer.add_instance_buffer(InstanceBuffer::new(device, &mesh.name, &instances));
+            for primitive in mesh.primitives {
+                let mesh_id = renderer.add_mesh(primitive.mesh);
+                let mut draw = DrawCall::new(pipeline, mesh_id)
+                    .with_instances(instance_buffer)
+                    .with_shadows();
+                // 同一个材质的图元克隆的是同一个绑定组，修改参数对它们同时生效
+                let material = materials.get(material_ids[primitive.material]).unwrap();
+                draw.bind_groups = vec![scene_bind_group.clone(), material.bind_group.clone()];
+                draw.bind_groups
+                    .extend(shared_bind_groups.iter().map(|&b| b.clone()));
+                renderer.add_draw(draw);
+            }
+        }
+        material_ids
+    }
+}
+
+// 深度优先地添加节点和它的子节点，返回节点在 nodes 里的下标
+fn add_node(
+    node: &gltf::Node,
+    parent: Option<usize>,
+    parent_world: Mat4,
+    nodes: &mut Vec<SceneNode>,
+    cameras: &mut Vec<SceneCamera>,
+) -> usize {
+    let local = Mat4::from_cols_array_2d(&node.transform().matrix());
+    let world = parent_world * local;
+    let index = nodes.len();
+    let camera = node.camera().and_then(|c| match c.projection() {
+        gltf::camera::Projection::Perspective(p) => {
+            cameras.push(SceneCamera {
+                name: c.name().unwrap_or("glTF Camera").to_string(),
+                node: index,
+                yfov: p.yfov(),
+                znear: p.znear(),
+                zfar: p.zfar(),
+                aspect_ratio: p.aspect_ratio(),
+            });
+            Some(cameras.len() - 1)
+        }
+        gltf::camera::Projection::Orthographic(_) => {
+            log::warn!(
+                "暂不支持正交相机，忽略 {}",
+                c.name().unwrap_or("glTF Camera")
+            );
+            None
+        }
+    });
+    nodes.push(SceneNode {
+        name: node.name().unwrap_or("").to_string(),
+        parent,
+        children: vec![],
+        local,
+        world,
+        mesh: node.mesh().map(|m| m.index()),
+        camera,
+    });
+    for child in node.children() {
+        let child_index = add_node(&child, Some(index), world, nodes, cameras);
+        nodes[index].children.push(child_index);
+    }
+    index
+}
+
+// 读取图元的顶点和索引，缺少的法线和切线会被生成
+fn read_primitive(
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+) -> Result<(Vec<ModelVertex>, Vec<u32>), String> {
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    let positions: Vec<Vec3> = reader
+        .read_positions()
+        .ok_or("图元没有 POSITION 属性")?
+        .map(Vec3::from)
+        .collect();
+    let count = positions.len();
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..count as u32).collect(),
+    };
+    if let Some(&index) = indices.iter().find(|&&i| i as usize >= count) {
+        return Err(format!("索引 {index} 超出顶点数量 {count}"));
+    }
+    let check_len = |name: &str, len: usize| {
+        if len == count {
+            Ok(())
+        } else {
+            Err(format!("{name} 数量 {len} 和顶点数量 {count} 不匹配"))
+        }
+    };
+    let normals: Vec<Vec3> = match reader.read_normals() {
+        Some(normals) => normals.map(Vec3::from).collect(),
+        None => model::generate_normals(&positions, &indices),
+    };
+    check_len("NORMAL", normals.len())?;
+    // glTF 的纹理坐标原点在左上角，和 wgpu 一致，不需要翻转
+    let tex_coords: Vec<Vec2> = match reader.read_tex_coords(0) {
+        Some(tex_coords) => tex_coords.into_f32().map(Vec2::from).collect(),
+        None => vec![Vec2::ZERO; count],
+    };
+    check_len("TEXCOORD_0", tex_coords.len())?;
+    let tangents: Vec<[f32; 4]> = match reader.read_tangents() {
+        Some(tangents) => tangents.collect(),
+        None => model::compute_tangents(&positions, &normals, &tex_coords, &indices),
+    };
+    check_len("TANGENT", tangents.len())?;
+    let vertices = (0..count)
+        .map(|i| ModelVertex {
+            position: positions[i].to_array(),
+            tex_coords: tex_coords[i].to_array(),
+            normal: normals[i].to_array(),
+            tangent: tangents[i],
+        })
+        .collect();
+    Ok((vertices, indices))
+}
+
+// 把 glTF 解码出来的各种像素格式转换成 RGBA8
+fn to_rgba8(image: &gltf::image::Data) -> Result<Vec<u8>, String> {
+    use gltf::image::Format;
+    let (channels, bytes_per_channel) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let texel_size = channels * bytes_per_channel;
+    let expected = (image.width * image.height) as usize * texel_size;
+    if image.pixels.len() != expected {
+        return Err(format!(
+            "图片数据长度 {} 和尺寸 {}x{} 不匹配",
+            image.pixels.len(),
+            image.width,
+            image.height
+        ));
+    }
+    let channel = |bytes: &[u8]| -> u8 {
+        match bytes_per_channel {
+            1 => bytes[0],
+            // 16 位通道是小端序，取高 8 位
+            2 => bytes[1],
+            _ => {
+                let v = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (v.clamp(0.0, 1.0) * 255.0).round() as u8
+            }
+        }
+    };
+    let mut rgba = Vec::with_capacity((image.width * image.height) as usize * 4);
+    for texel in image.pixels.chunks_exact(texel_size) {
+        let c = |i: usize| channel(&texel[i * bytes_per_channel..]);
+        rgba.extend_from_slice(&match channels {
+            // 单通道的数据贴图（比如遮蔽）放在 R 通道，灰度图复制到 RGB
+            1 => [c(0), c(0), c(0), 255],
+            2 => [c(0), c(1), 0, 255],
+            3 => [c(0), c(1), c(2), 255],
+            _ => [c(0), c(1), c(2), c(3)],
+        });
+    }
+    Ok(rgba)
+}
+
+// 按 glTF 的采样器设置创建 wgpu 采样器
+fn create_sampler(
+    device: &wgpu::Device,
+    sampler: &gltf::texture::Sampler,
+    label: &str,
+) -> wgpu::Sampler {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    };
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        _ => wgpu::FilterMode::Linear,
+    };
+    // 纹理没有生成 mipmap，只取 min_filter 里的缩小过滤方式
+    let min_filter = match sampler.min_filter() {
+        Some(
+            MinFilter::Nearest | MinFilter::NearestMipmapNearest | MinFilter::NearestMipmapLinear,
+        ) => wgpu::FilterMode::Nearest,
+        _ => wgpu::FilterMode::Linear,
+    };
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some(label),
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter,
+        min_filter,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{self, HeadlessOptions};
+
+    #[test]
+    fn imports_hierarchy_primitives_materials_and_cameras() {
+        let Some(app) = headless::test_app(HeadlessOptions::default()) else {
+            return;
+        };
+        let layout = PbrMaterial::layout(&app.device);
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/scene.gltf");
+        let scene = Scene::load_gltf(&app.device, &app.queue, &layout, path).unwrap();
+
+        let names: Vec<&str> = scene.nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["root", "box", "floor", "camera"]);
+        assert_eq!(scene.roots, [0, 3]);
+        assert_eq!(scene.nodes[0].children, [1, 2]);
+        assert_eq!(scene.nodes[1].parent, Some(0));
+        // 子节点的世界变换包含父节点的平移
+        let box_origin = scene.nodes[1].world.transform_point3(Vec3::ZERO);
+        assert!((box_origin - Vec3::new(0.0, 0.1, 0.0)).length() < 1e-5);
+
+        assert_eq!(scene.meshes[0].primitives.len(), 2);
+        let materials: Vec<&str> = scene.materials.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(materials, ["checker", "red metal", "floor"]);
+        assert_eq!(scene.materials[1].params.metallic, 1.0);
+
+        let camera = scene.camera(0, 2.0).unwrap();
+        assert!((camera.eye - Vec3::new(0.0, 1.5, 3.0)).length() < 1e-5);
+        assert!(camera.forward().dot((Vec3::ZERO - camera.eye).normalize()) > 0.999);
+        assert_eq!(camera.aspect, 2.0);
+    }
+
+    #[test]
+    fn malformed_file_is_an_error() {
+        let Some(app) = headless::test_app(HeadlessOptions::default()) else {
+            return;
+        };
+        let layout = PbrMaterial::layout(&app.device);
+        let path = std::env::temp_dir().join(format!("my-wgpu-{}.gltf", std::process::id()));
+        std::fs::write(&path, "{ \"asset\": ").unwrap();
+        let result = Scene::load_gltf(&app.device, &app.queue, &layout, &path);
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(result, Err(SceneError::Gltf { .. })));
+    }
+}
@@ -0,0 +1,239 @@
+// 着色器热重载
+// 监听磁盘上的 WGSL 文件，文件变化后先用 naga 解析和校验，
+// 通过后再原地替换使用它的管线，不需要重新创建 device。
+// 编译失败时保留之前的管线继续渲染，并把错误（文件、行、列、信息）
+// 记录到日志、显示在窗口的错误覆盖层上。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use notify::{RecursiveMode, Watcher};
+
+use crate::pipeline::{Pipeline, PipelineDescriptor, Shader, TargetState};
+
+// 着色器编译错误，line 和 column 从 1 开始，为 0 表示没有位置信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderError {
+    pub path: PathBuf,
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+impl ShaderError {
+    pub(crate) fn new(
+        path: &Path,
+        location: Option<naga::SourceLocation>,
+        message: String,
+    ) -> Self {
+        let (line, column) = location
+            .map(|l| (l.line_number, l.line_position))
+            .unwrap_or((0, 0));
+        Self {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+// 用 naga 解析 WGSL 源码，计算管线也用它读取入口函数的工作组大小
+pub(crate) fn parse_wgsl(path: &Path, source: &str) -> Result<naga::Module, ShaderError> {
+    naga::front::wgsl::parse_str(source)
+        .map_err(|e| ShaderError::new(path, e.location(source), e.message().to_string()))
+}
+
+// 用 naga 解析并校验 WGSL 源码，错误里带上行列信息
+pub fn validate_wgsl(path: &Path, source: &str) -> Result<(), ShaderError> {
+    let module = parse_wgsl(path, source)?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .map_err(|e| ShaderError::new(path, e.location(source), e.as_inner().to_string()))?;
+    Ok(())
+}
+
+// 在错误作用域里重新编译着色器并创建管线，
+// naga 校验没有覆盖到的错误（比如和管线布局不匹配）也会被捕获，而不是让程序崩溃
+pub fn rebuild_pipelines(
+    device: &wgpu::Device,
+    path: &Path,
+    shader: &Shader,
+    descs: &[&PipelineDescriptor],
+    targets: &TargetState,
+) -> Result<Vec<wgpu::RenderPipeline>, ShaderError> {
+    validate_wgsl(path, &shader.source)?;
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = shader.create_module(device);
+    let pipelines = descs
+        .iter()
+        .map(|desc| Pipeline::create(device, desc, &module, targets))
+        .collect();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(e) => Err(ShaderError::new(path, None, e.to_string())),
+        None => Ok(pipelines),
+    }
+}
+
+// 监听着色器文件的变化
+// 监听的是文件所在的目录，因为很多编辑器保存时会先写临时文件再重命名
+pub struct ShaderWatcher {
+    watcher: notify::RecommendedWatcher,
+    rx: mpsc::Receiver<notify::Result<notify::Event>>,
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl ShaderWatcher {
+    pub fn new() -> notify::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        Ok(Self {
+            watcher: notify::recommended_watcher(tx)?,
+            rx,
+            files: HashSet::new(),
+            dirs: HashSet::new(),
+        })
+    }
+
+    // 开始监听一个文件，重复调用没有影响
+    pub fn watch(&mut self, path: &Path) -> notify::Result<()> {
+        let path = path.canonicalize()?;
+        if self.files.contains(&path) {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent()
+            && !self.dirs.contains(dir)
+        {
+            self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            self.dirs.insert(dir.to_path_buf());
+        }
+        self.files.insert(path);
+        Ok(())
+    }
+
+    // 取出自上次调用以来发生变化的文件，不会阻塞
+    pub fn changed_files(&self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for event in self.rx.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    log::warn!("监听着色器文件出错: {e}");
+                    continue;
+                }
+            };
+            if !(event.kind.is_modify() || event.kind.is_create()) {
+                continue;
+            }
+            for path in event.paths {
+                let path = path.canonicalize().unwrap_or(path);
+                if self.files.contains(&path) && !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+        changed
+    }
+}
+
+// 着色器错误覆盖层：在窗口顶部画一条半透明的红色横条，提示当前有着色器编译错误
+// 错误信息的第一行由文字渲染画在横条上，完整的信息同时写到日志和窗口标题里。
+// 横条和文字一样在色调映射之后直接画在展示平面上，不受曝光、泛光和色调映射的影响
+pub struct ErrorOverlay {
+    pipeline: Pipeline,
+}
+
+impl ErrorOverlay {
+    pub fn new(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Self {
+        let mut desc = PipelineDescriptor::new(
+            "Shader Error Overlay",
+            Shader::from_wgsl(
+                "error_overlay.wgsl",
+                include_str!("shaders/error_overlay.wgsl"),
+            ),
+        );
+        // 顶点在着色器里根据 vertex_index 生成，不需要顶点缓冲区
+        desc.vertex_layouts.clear();
+        desc.cull_mode = None;
+        desc.blend = Some(wgpu::BlendState::ALPHA_BLENDING);
+        // 展示平面没有深度缓冲区，也不需要多重采样
+        let targets = TargetState {
+            color_format: output_format,
+            depth_format: None,
+            depth_compare: wgpu::CompareFunction::Always,
+            sample_count: 1,
+        };
+        Self {
+            pipeline: Pipeline::new(device, desc, &targets),
+        }
+    }
+
+    // 在已经色调映射过的输出上叠加横条，保留原来的内容
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shader Error Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.draw(0..6, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_shader_passes() {
+        let source = include_str!("shaders/vertex_color.wgsl");
+        assert_eq!(
+            validate_wgsl(Path::new("vertex_color.wgsl"), source),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn parse_error_reports_line_and_column() {
+        let source = "@vertex\nfn vs_main() -> @builtin(position) vec4f {\n    return vec4f(1.0, 2.0, 3.0, 4.0)\n}\n";
+        let err = validate_wgsl(Path::new("broken.wgsl"), source).unwrap_err();
+        assert_eq!(err.path, Path::new("broken.wgsl"));
+        assert_eq!(err.line, 4);
+        assert_eq!(err.column, 1);
+        assert!(err.to_string().starts_with("broken.wgsl:4:1: "));
+    }
+
+    #[test]
+    fn validation_error_reports_location() {
+        let source = "@fragment\nfn fs_main() -> @location(0) vec4f {\n    return 1.0;\n}\n";
+        let err = validate_wgsl(Path::new("broken.wgsl"), source).unwrap_err();
+        assert_ne!(err.line, 0);
+    }
+}
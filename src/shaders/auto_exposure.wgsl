@@ -0,0 +1,86 @@
+// 自动曝光：对数亮度直方图和带时间适应的平均亮度
+// build_histogram 每个线程处理一个像素，先在工作组共享内存里累加，再合并到全局直方图，减少全局原子操作
+// average_luminance 只用一个工作组，每个线程负责一个桶，归约求出加权平均后向目标亮度靠近
+
+struct ExposureParams {
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    blend_up: f32,
+    blend_down: f32,
+};
+
+@group(0) @binding(0)
+var t_hdr: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> params: ExposureParams;
+@group(0) @binding(2)
+var<storage, read_write> histogram: array<atomic<u32>, 256>;
+@group(0) @binding(3)
+var<storage, read_write> luminance: f32;
+
+// 比这更暗的像素算作纯黑，放进第 0 个桶
+const BLACK_THRESHOLD: f32 = 0.00001;
+
+var<workgroup> local_bins: array<atomic<u32>, 256>;
+
+// 亮度映射到 1..255 号桶
+fn bin_of(color: vec3f) -> u32 {
+    let lum = dot(color, vec3f(0.2126, 0.7152, 0.0722));
+    if lum < BLACK_THRESHOLD {
+        return 0u;
+    }
+    let t = clamp((log2(lum) - params.min_log_luminance) / params.log_luminance_range, 0.0, 1.0);
+    return u32(t * 254.0 + 1.0);
+}
+
+@compute @workgroup_size(16, 16)
+fn build_histogram(
+    @builtin(global_invocation_id) id: vec3u,
+    @builtin(local_invocation_index) index: u32,
+) {
+    atomicStore(&local_bins[index], 0u);
+    workgroupBarrier();
+    let size = textureDimensions(t_hdr);
+    if all(id.xy < size) {
+        let color = textureLoad(t_hdr, vec2i(id.xy), 0).rgb;
+        atomicAdd(&local_bins[bin_of(color)], 1u);
+    }
+    workgroupBarrier();
+    // 工作组正好 256 个线程，每个线程合并一个桶
+    let count = atomicLoad(&local_bins[index]);
+    if count > 0u {
+        atomicAdd(&histogram[index], count);
+    }
+}
+
+var<workgroup> weighted: array<f32, 256>;
+
+@compute @workgroup_size(256)
+fn average_luminance(@builtin(local_invocation_index) index: u32) {
+    let count = atomicLoad(&histogram[index]);
+    weighted[index] = f32(count) * f32(index);
+    // 清空直方图，下一帧重新统计
+    atomicStore(&histogram[index], 0u);
+    workgroupBarrier();
+    for (var stride = 128u; stride > 0u; stride >>= 1u) {
+        if index < stride {
+            weighted[index] += weighted[index + stride];
+        }
+        workgroupBarrier();
+    }
+    if index == 0u {
+        let size = textureDimensions(t_hdr);
+        // 第 0 个线程读到的 count 正好是纯黑像素的数量
+        let lit = f32(size.x * size.y) - f32(count);
+        // 整个画面都是黑色时保持上一帧的亮度
+        if lit < 1.0 {
+            return;
+        }
+        let mean_bin = weighted[0] / lit;
+        let log_lum = (mean_bin - 1.0) / 254.0 * params.log_luminance_range + params.min_log_luminance;
+        let target_lum = exp2(log_lum);
+        let previous = luminance;
+        let blend = select(params.blend_down, params.blend_up, target_lum > previous);
+        luminance = mix(previous, target_lum, blend);
+    }
+}
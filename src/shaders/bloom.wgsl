@@ -0,0 +1,93 @@
+// 泛光：双重过滤（dual filter）的降采样和升采样
+// fs_prefilter 从输入纹理提取超过阈值的亮部，同时降采样到半分辨率；
+// fs_downsample 每次把上一级缩小一半，中心 4 倍权重加上四个对角，一共 5 次采样；
+// fs_upsample 从最小的一级开始，用 8 次采样的帐篷形核放大，加色混合叠加到上一级；
+// fs_composite 把累加到第一级的泛光乘以强度加回输入纹理，写入后处理的输出。
+
+struct PostUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    span_max: f32,
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    srgb_input: u32,
+};
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> params: PostUniform;
+// 只有 fs_composite 使用，其他入口的绑定组布局里没有这一项
+@group(0) @binding(3)
+var t_bloom: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) uv: vec2f,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+    // 纹理坐标的 y 轴向下
+    out.uv = vec2f(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn downsample(uv: vec2f) -> vec3f {
+    // 偏移一个源纹素，也就是输出纹素的一半，每次线性采样正好是 2×2 个源纹素的平均
+    let texel = 1.0 / vec2f(textureDimensions(t_source));
+    var sum = textureSample(t_source, s_source, uv).rgb * 4.0;
+    sum += textureSample(t_source, s_source, uv - texel).rgb;
+    sum += textureSample(t_source, s_source, uv + texel).rgb;
+    sum += textureSample(t_source, s_source, uv + vec2f(texel.x, -texel.y)).rgb;
+    sum += textureSample(t_source, s_source, uv - vec2f(texel.x, -texel.y)).rgb;
+    return sum / 8.0;
+}
+
+// 软阈值：亮度在 threshold ± knee 之间按二次曲线过渡，避免泛光边缘出现硬边
+fn threshold(color: vec3f) -> vec3f {
+    let brightness = max(color.r, max(color.g, color.b));
+    var soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    soft = soft * soft / (4.0 * params.knee + 1e-4);
+    let contribution = max(soft, brightness - params.threshold) / max(brightness, 1e-4);
+    return color * contribution;
+}
+
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(threshold(downsample(in.uv)), 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(downsample(in.uv), 1.0);
+}
+
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4f {
+    // 源纹理是更小的一级，偏移半个源纹素
+    let h = 0.5 / vec2f(textureDimensions(t_source));
+    var sum = textureSample(t_source, s_source, in.uv + vec2f(-2.0 * h.x, 0.0)).rgb;
+    sum += textureSample(t_source, s_source, in.uv + vec2f(2.0 * h.x, 0.0)).rgb;
+    sum += textureSample(t_source, s_source, in.uv + vec2f(0.0, -2.0 * h.y)).rgb;
+    sum += textureSample(t_source, s_source, in.uv + vec2f(0.0, 2.0 * h.y)).rgb;
+    sum += textureSample(t_source, s_source, in.uv + vec2f(-h.x, h.y)).rgb * 2.0;
+    sum += textureSample(t_source, s_source, in.uv + vec2f(h.x, h.y)).rgb * 2.0;
+    sum += textureSample(t_source, s_source, in.uv + vec2f(h.x, -h.y)).rgb * 2.0;
+    sum += textureSample(t_source, s_source, in.uv + vec2f(-h.x, -h.y)).rgb * 2.0;
+    return vec4f(sum / 12.0, 1.0);
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4f {
+    let color = textureLoad(t_source, vec2i(in.clip_position.xy), 0);
+    let bloom = textureSample(t_bloom, s_source, in.uv).rgb;
+    return vec4f(color.rgb + bloom * params.intensity, color.a);
+}
@@ -0,0 +1,59 @@
+// 镜面反射 BRDF 的积分查找表（split-sum 近似的第二部分）
+// 横轴是 n·v，纵轴是粗糙度，R 通道是 F0 的缩放，G 通道是偏移：∫BRDF·cosθ = F0 · R + G
+// 和环境贴图无关，所以只需要计算一次
+
+@group(0) @binding(0)
+var output: texture_storage_2d<rgba16float, write>;
+
+const PI: f32 = 3.14159265359;
+const SAMPLE_COUNT: u32 = 256u;
+
+fn hammersley(i: u32, n: u32) -> vec2f {
+    return vec2f(f32(i) / f32(n), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+// IBL 的 Smith 几何遮蔽使用 k = α / 2，和直接光照的 k 不同
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    let g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3u) {
+    let size = textureDimensions(output);
+    if any(id.xy >= size) {
+        return;
+    }
+    let coords = (vec2f(id.xy) + 0.5) / vec2f(size);
+    let n_dot_v = coords.x;
+    let roughness = coords.y;
+    // 法线是 +Z，视线在 xz 平面里
+    let v = vec3f(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    let alpha = roughness * roughness;
+    let a2 = alpha * alpha;
+    var scale = 0.0;
+    var bias = 0.0;
+    for (var i = 0u; i < SAMPLE_COUNT; i++) {
+        let xi = hammersley(i, SAMPLE_COUNT);
+        let phi = 2.0 * PI * xi.x;
+        let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a2 - 1.0) * xi.y));
+        let sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+        let h = vec3f(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+        let l = reflect(-v, h);
+        let n_dot_l = max(l.z, 0.0);
+        let n_dot_h = max(h.z, 0.0);
+        let v_dot_h = max(dot(v, h), 0.0);
+        if n_dot_l > 0.0 {
+            // 按 GGX 重要性采样时，BRDF·cosθ / pdf 化简成 G · v·h / (n·h · n·v)
+            let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+            let g_vis = g * v_dot_h / (n_dot_h * n_dot_v);
+            let fc = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fc) * g_vis;
+            bias += fc * g_vis;
+        }
+    }
+    let n = f32(SAMPLE_COUNT);
+    textureStore(output, id.xy, vec4f(scale / n, bias / n, 0.0, 1.0));
+}
@@ -0,0 +1,42 @@
+// 生成环境立方体贴图的 mip 链：每次渲染一个面的一级 mip，从上一级线性采样
+// 输出纹素的中心正好落在上一级 2×2 个纹素中间，线性过滤就是取平均。
+// 用渲染通道而不是计算着色器，是因为 GL 后端采样一级 mip 时会限制整个纹理的 mip 范围，
+// 同一张纹理其他级别的存储纹理写入会被忽略，作为颜色附件写入则不受影响
+
+struct DownsampleParams {
+    face: u32,
+};
+
+@group(0) @binding(0)
+var t_source: texture_cube<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> params: DownsampleParams;
+
+fn cube_direction(face: u32, uv: vec2f) -> vec3f {
+    switch face {
+        case 0u: { return vec3f(1.0, -uv.y, -uv.x); }
+        case 1u: { return vec3f(-1.0, -uv.y, uv.x); }
+        case 2u: { return vec3f(uv.x, 1.0, uv.y); }
+        case 3u: { return vec3f(uv.x, -1.0, -uv.y); }
+        case 4u: { return vec3f(uv.x, -uv.y, 1.0); }
+        default: { return vec3f(-uv.x, -uv.y, -1.0); }
+    }
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    // t_source 只绑定了上一级 mip，输出的边长是它的一半
+    let size = vec2f(textureDimensions(t_source)) / 2.0;
+    let uv = position.xy / size * 2.0 - 1.0;
+    let dir = normalize(cube_direction(params.face, uv));
+    return vec4f(textureSampleLevel(t_source, s_source, dir, 0.0).rgb, 1.0);
+}
@@ -0,0 +1,34 @@
+// 调试线条：每两个顶点一条线段，颜色是线性 HDR 值，和场景一起经过色调映射
+
+struct CameraUniform {
+    view_proj: mat4x4f,
+    view: mat4x4f,
+    proj: mat4x4f,
+    position: vec4f,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) color: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) color: vec4f,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4f(in.position, 1.0);
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return in.color;
+}
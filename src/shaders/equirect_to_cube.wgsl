@@ -0,0 +1,38 @@
+// 把等距柱状投影（经纬度）的全景图转换成立方体贴图
+// 每个线程写立方体一个面上的一个纹素：先算出纹素对应的方向，再换算成全景图的经纬度坐标采样
+
+@group(0) @binding(0)
+var t_equirect: texture_2d<f32>;
+@group(0) @binding(1)
+var s_equirect: sampler;
+@group(0) @binding(2)
+var output: texture_storage_2d_array<rgba16float, write>;
+
+const PI: f32 = 3.14159265359;
+
+// 立方体面上的坐标 uv（[-1, 1]，v 向下）对应的方向，面的顺序是 +X、-X、+Y、-Y、+Z、-Z
+fn cube_direction(face: u32, uv: vec2f) -> vec3f {
+    switch face {
+        case 0u: { return vec3f(1.0, -uv.y, -uv.x); }
+        case 1u: { return vec3f(-1.0, -uv.y, uv.x); }
+        case 2u: { return vec3f(uv.x, 1.0, uv.y); }
+        case 3u: { return vec3f(uv.x, -1.0, -uv.y); }
+        case 4u: { return vec3f(uv.x, -uv.y, 1.0); }
+        default: { return vec3f(-uv.x, -uv.y, -1.0); }
+    }
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3u) {
+    let size = textureDimensions(output);
+    if any(id.xy >= size) {
+        return;
+    }
+    let uv = (vec2f(id.xy) + 0.5) / vec2f(size) * 2.0 - 1.0;
+    let dir = normalize(cube_direction(id.z, uv));
+    // 经度从 -X 开始绕 +Y 一圈，纬度从 +Y（顶部）到 -Y（底部）
+    let equirect_uv = vec2f(atan2(dir.z, dir.x) / (2.0 * PI) + 0.5, acos(clamp(dir.y, -1.0, 1.0)) / PI);
+    // 计算着色器没有屏幕空间导数，必须指定 mip 级别
+    let color = textureSampleLevel(t_equirect, s_equirect, equirect_uv, 0.0);
+    textureStore(output, id.xy, id.z, vec4f(color.rgb, 1.0));
+}
@@ -0,0 +1,20 @@
+// 着色器错误覆盖层：窗口顶部的半透明红色横条
+// 两个三角形组成一个矩形，顶点位置由 vertex_index 生成
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+    var corners = array<vec2f, 6>(
+        vec2f(-1.0, 1.0),
+        vec2f(-1.0, 0.85),
+        vec2f(1.0, 0.85),
+        vec2f(-1.0, 1.0),
+        vec2f(1.0, 0.85),
+        vec2f(1.0, 1.0),
+    );
+    return vec4f(corners[index], 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4f {
+    return vec4f(0.8, 0.05, 0.05, 0.75);
+}
@@ -0,0 +1,93 @@
+// FXAA：按亮度找出边缘，沿边缘方向多采样几次，用模糊掉的颜色代替锯齿
+// 在色调映射之后运行，输入已经是显示用的 LDR 颜色，边缘阈值和通常的经验值一致。
+// 输入是 sRGB 格式时采样得到线性值，开方近似伽马编码；否则色调映射已经手动编码过，直接使用。
+
+struct PostUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    span_max: f32,
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    srgb_input: u32,
+};
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> params: PostUniform;
+
+// 方向向量的最小衰减和随亮度的衰减比例
+const REDUCE_MIN: f32 = 1.0 / 128.0;
+const REDUCE_MUL: f32 = 1.0 / 8.0;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) uv: vec2f,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2f(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn luma(color: vec3f) -> f32 {
+    let l = dot(color, vec3f(0.299, 0.587, 0.114));
+    if params.srgb_input != 0u {
+        return sqrt(l);
+    }
+    return l;
+}
+
+fn sample_rgb(uv: vec2f) -> vec3f {
+    return textureSampleLevel(t_source, s_source, uv, 0.0).rgb;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let texel = 1.0 / vec2f(textureDimensions(t_source));
+    let center = textureSampleLevel(t_source, s_source, in.uv, 0.0);
+    let luma_m = luma(center.rgb);
+    let luma_nw = luma(sample_rgb(in.uv + vec2f(-1.0, -1.0) * texel));
+    let luma_ne = luma(sample_rgb(in.uv + vec2f(1.0, -1.0) * texel));
+    let luma_sw = luma(sample_rgb(in.uv + vec2f(-1.0, 1.0) * texel));
+    let luma_se = luma(sample_rgb(in.uv + vec2f(1.0, 1.0) * texel));
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+    // 对比度不够的地方不是边缘，保持原样
+    if luma_max - luma_min < max(params.edge_threshold_min, luma_max * params.edge_threshold) {
+        return center;
+    }
+
+    // 亮度梯度的垂直方向就是边缘的方向
+    var dir = vec2f(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2f(-params.span_max), vec2f(params.span_max)) * texel;
+
+    // 沿边缘方向取两组样本：内侧两个和外侧两个
+    let inner = 0.5 * (
+        sample_rgb(in.uv + dir * (1.0 / 3.0 - 0.5)) +
+        sample_rgb(in.uv + dir * (2.0 / 3.0 - 0.5))
+    );
+    let outer = inner * 0.5 + 0.25 * (
+        sample_rgb(in.uv - dir * 0.5) +
+        sample_rgb(in.uv + dir * 0.5)
+    );
+    // 外侧的样本跨过了别的边缘时亮度会超出范围，只用内侧的
+    let luma_outer = luma(outer);
+    if luma_outer < luma_min || luma_outer > luma_max {
+        return vec4f(inner, center.a);
+    }
+    return vec4f(outer, center.a);
+}
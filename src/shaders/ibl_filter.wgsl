@@ -0,0 +1,119 @@
+// 预计算 IBL 需要的立方体贴图，两个入口共用同一个绑定组布局：
+// irradiance 对法线所在的半球做余弦加权的积分，得到漫反射用的辐照度（已经除以 π）；
+// prefilter 按 GGX 分布做重要性采样，每一级 mip 对应一个粗糙度，得到镜面反射用的预过滤贴图。
+// 样本按概率密度选择环境贴图的 mip 级别（滤波重要性采样），太阳这样的小亮点不会变成一堆亮斑，
+// 所以环境贴图的 mip 链要先由 cubemap_downsample.wgsl 生成好。
+
+struct FilterParams {
+    roughness: f32,
+    sample_count: u32,
+    // 环境贴图第 0 级的边长
+    source_size: f32,
+};
+
+@group(0) @binding(0)
+var t_source: texture_cube<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var output: texture_storage_2d_array<rgba16float, write>;
+@group(0) @binding(3)
+var<uniform> params: FilterParams;
+
+const PI: f32 = 3.14159265359;
+
+fn cube_direction(face: u32, uv: vec2f) -> vec3f {
+    switch face {
+        case 0u: { return vec3f(1.0, -uv.y, -uv.x); }
+        case 1u: { return vec3f(-1.0, -uv.y, uv.x); }
+        case 2u: { return vec3f(uv.x, 1.0, uv.y); }
+        case 3u: { return vec3f(uv.x, -1.0, -uv.y); }
+        case 4u: { return vec3f(uv.x, -uv.y, 1.0); }
+        default: { return vec3f(-uv.x, -uv.y, -1.0); }
+    }
+}
+
+// 输出纹素对应的方向，超出纹理范围时返回 false
+fn texel_direction(id: vec3u, dir: ptr<function, vec3f>) -> bool {
+    let size = textureDimensions(output);
+    if any(id.xy >= size) {
+        return false;
+    }
+    let uv = (vec2f(id.xy) + 0.5) / vec2f(size) * 2.0 - 1.0;
+    *dir = normalize(cube_direction(id.z, uv));
+    return true;
+}
+
+// Hammersley 低差异序列，比随机数收敛得快
+fn hammersley(i: u32, n: u32) -> vec2f {
+    return vec2f(f32(i) / f32(n), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+// 把以 +Z 为法线的切线空间向量转换到以 n 为法线的世界空间
+fn tangent_to_world(v: vec3f, n: vec3f) -> vec3f {
+    let up = select(vec3f(1.0, 0.0, 0.0), vec3f(0.0, 0.0, 1.0), abs(n.z) < 0.999);
+    let t = normalize(cross(up, n));
+    let b = cross(n, t);
+    return t * v.x + b * v.y + n * v.z;
+}
+
+// 一个样本覆盖的立体角和环境贴图一个纹素的立体角之比，决定采样哪一级 mip
+fn source_lod(pdf: f32) -> f32 {
+    let texel = 4.0 * PI / (6.0 * params.source_size * params.source_size);
+    let sample = 1.0 / (f32(params.sample_count) * pdf + 0.0001);
+    return max(0.5 * log2(sample / texel), 0.0);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn irradiance(@builtin(global_invocation_id) id: vec3u) {
+    var n: vec3f;
+    if !texel_direction(id, &n) {
+        return;
+    }
+    var sum = vec3f(0.0);
+    for (var i = 0u; i < params.sample_count; i++) {
+        // 余弦加权的半球采样，pdf = cosθ / π 正好抵消积分里的 cosθ 和 1/π
+        let xi = hammersley(i, params.sample_count);
+        let phi = 2.0 * PI * xi.x;
+        let cos_theta = sqrt(1.0 - xi.y);
+        let sin_theta = sqrt(xi.y);
+        let l = tangent_to_world(vec3f(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta), n);
+        let lod = source_lod(cos_theta / PI);
+        sum += textureSampleLevel(t_source, s_source, l, lod).rgb;
+    }
+    textureStore(output, id.xy, id.z, vec4f(sum / f32(params.sample_count), 1.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn prefilter(@builtin(global_invocation_id) id: vec3u) {
+    var n: vec3f;
+    if !texel_direction(id, &n) {
+        return;
+    }
+    // 假设视线方向等于法线和反射方向，这样预过滤的结果只和反射方向有关
+    let v = n;
+    let alpha = params.roughness * params.roughness;
+    let a2 = alpha * alpha;
+    var sum = vec3f(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < params.sample_count; i++) {
+        // 按 GGX 分布采样半程向量
+        let xi = hammersley(i, params.sample_count);
+        let phi = 2.0 * PI * xi.x;
+        let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a2 - 1.0) * xi.y));
+        let sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+        let h = tangent_to_world(vec3f(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta), n);
+        let l = reflect(-v, h);
+        let n_dot_l = dot(n, l);
+        if n_dot_l > 0.0 {
+            // N = V 时 pdf = D(h) · (n·h) / (4 · v·h) = D(h) / 4
+            let d = cos_theta * cos_theta * (a2 - 1.0) + 1.0;
+            let pdf = a2 / (PI * d * d) / 4.0;
+            // 粗糙度为 0 时 pdf 无穷大，直接采样第 0 级
+            let lod = select(source_lod(pdf), 0.0, params.roughness == 0.0);
+            sum += textureSampleLevel(t_source, s_source, l, lod).rgb * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+    textureStore(output, id.xy, id.z, vec4f(sum / max(weight, 0.0001), 1.0));
+}
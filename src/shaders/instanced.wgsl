@@ -0,0 +1,46 @@
+// 实例化着色器：每个实例带一个模型矩阵、颜色和自定义属性
+// 这里把自定义属性的 x 分量当作绕实例中心的额外缩放
+
+struct CameraUniform {
+    view_proj: mat4x4f,
+    view: mat4x4f,
+    proj: mat4x4f,
+    position: vec4f,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) color: vec3f,
+};
+
+struct InstanceInput {
+    @location(5) model_0: vec4f,
+    @location(6) model_1: vec4f,
+    @location(7) model_2: vec4f,
+    @location(8) model_3: vec4f,
+    @location(9) color: vec4f,
+    @location(10) custom: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) color: vec4f,
+};
+
+@vertex
+fn vs_main(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4f(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let scale = 1.0 + instance.custom.x;
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * model * vec4f(in.position * scale, 1.0);
+    out.color = vec4f(in.color, 1.0) * instance.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return in.color;
+}
@@ -0,0 +1,225 @@
+// 模型着色器：漫反射贴图 + 法线贴图，用 group(2) 里的平行光、点光源和聚光灯做 Blinn-Phong 着色
+// 投射阴影的光源从阴影贴图数组里采样，平行光按视图空间深度选择级联，用 PCF 柔化边缘
+
+struct CameraUniform {
+    view_proj: mat4x4f,
+    view: mat4x4f,
+    proj: mat4x4f,
+    position: vec4f,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct MaterialUniform {
+    diffuse: vec4f,
+    specular: vec3f,
+    shininess: f32,
+};
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+@group(1) @binding(2)
+var t_normal: texture_2d<f32>;
+@group(1) @binding(3)
+var s_normal: sampler;
+@group(1) @binding(4)
+var<uniform> material: MaterialUniform;
+
+const MAX_LIGHTS: u32 = 128u;
+const LIGHT_DIRECTIONAL: u32 = 0u;
+const LIGHT_POINT: u32 = 1u;
+const LIGHT_SPOT: u32 = 2u;
+
+struct Light {
+    position: vec3f,
+    kind: u32,
+    direction: vec3f,
+    range: f32,
+    color: vec3f,
+    intensity: f32,
+    cos_inner: f32,
+    cos_outer: f32,
+    shadow_layer: i32,
+};
+
+struct Lights {
+    ambient: vec4f,
+    count: u32,
+    lights: array<Light, MAX_LIGHTS>,
+};
+
+@group(2) @binding(0)
+var<uniform> lights: Lights;
+
+const MAX_CASCADES: u32 = 4u;
+
+struct ShadowUniform {
+    cascades: array<mat4x4f, 4>,
+    spots: array<mat4x4f, 4>,
+    splits: vec4f,
+    texel_sizes: vec4f,
+    cascade_count: u32,
+    pcf_radius: u32,
+    normal_offset: f32,
+    debug_cascades: u32,
+    inv_resolution: f32,
+};
+
+@group(2) @binding(1)
+var t_shadow: texture_depth_2d_array;
+@group(2) @binding(2)
+var s_shadow: sampler_comparison;
+@group(2) @binding(3)
+var<uniform> shadow: ShadowUniform;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) tex_coords: vec2f,
+    @location(2) normal: vec3f,
+    @location(3) tangent: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) tex_coords: vec2f,
+    @location(1) normal: vec3f,
+    @location(2) tangent: vec4f,
+    @location(3) world_position: vec3f,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4f(in.position, 1.0);
+    out.tex_coords = in.tex_coords;
+    out.normal = in.normal;
+    out.tangent = in.tangent;
+    out.world_position = in.position;
+    return out;
+}
+
+// 光源在 range 处平滑地衰减到 0，range 以内近似平方反比
+fn distance_attenuation(distance: f32, range: f32) -> f32 {
+    let ratio = distance / max(range, 0.0001);
+    let window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (1.0 + distance * distance);
+}
+
+// 片元所在的级联，超出最后一个级联时返回 cascade_count
+fn cascade_index(position: vec3f) -> u32 {
+    let view_depth = -(camera.view * vec4f(position, 1.0)).z;
+    for (var i = 0u; i < shadow.cascade_count; i++) {
+        if view_depth < shadow.splits[i] {
+            return i;
+        }
+    }
+    return shadow.cascade_count;
+}
+
+// 在一层阴影贴图里做 (2r+1)×(2r+1) 的 PCF，返回被照亮的比例
+fn sample_shadow(light_view_proj: mat4x4f, layer: u32, position: vec3f) -> f32 {
+    let clip = light_view_proj * vec4f(position, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2f(0.5, -0.5) + 0.5;
+    // 阴影贴图范围外的片元当作被照亮
+    if any(uv < vec2f(0.0)) || any(uv > vec2f(1.0)) || ndc.z > 1.0 {
+        return 1.0;
+    }
+    let r = i32(shadow.pcf_radius);
+    var lit = 0.0;
+    for (var y = -r; y <= r; y++) {
+        for (var x = -r; x <= r; x++) {
+            let offset = vec2f(f32(x), f32(y)) * shadow.inv_resolution;
+            lit += textureSampleCompareLevel(t_shadow, s_shadow, uv + offset, layer, ndc.z);
+        }
+    }
+    let size = f32(2 * r + 1);
+    return lit / (size * size);
+}
+
+// 沿法线把采样位置推出去大约 normal_offset 个纹素，避免表面自己遮挡自己
+fn shadow_factor(light: Light, position: vec3f, normal: vec3f) -> f32 {
+    if light.shadow_layer < 0 {
+        return 1.0;
+    }
+    let layer = u32(light.shadow_layer);
+    if light.kind == LIGHT_DIRECTIONAL {
+        let cascade = cascade_index(position);
+        if cascade >= shadow.cascade_count {
+            return 1.0;
+        }
+        let offset = normal * shadow.normal_offset * shadow.texel_sizes[cascade];
+        return sample_shadow(shadow.cascades[cascade], layer + cascade, position + offset);
+    }
+    // 聚光灯是透视投影，纹素的世界尺寸随距离增大
+    let distance = length(light.position - position);
+    let tan_half = sqrt(max(1.0 - light.cos_outer * light.cos_outer, 0.0)) / max(light.cos_outer, 0.0001);
+    let texel = 2.0 * tan_half * distance * shadow.inv_resolution;
+    let offset = normal * shadow.normal_offset * texel;
+    return sample_shadow(shadow.spots[layer - MAX_CASCADES], layer, position + offset);
+}
+
+// 调试视图：按级联给画面着色，红、绿、蓝、黄依次是由近到远的级联
+fn cascade_tint(position: vec3f) -> vec3f {
+    var colors = array<vec3f, 4>(
+        vec3f(1.0, 0.3, 0.3),
+        vec3f(0.3, 1.0, 0.3),
+        vec3f(0.3, 0.3, 1.0),
+        vec3f(1.0, 1.0, 0.3),
+    );
+    let cascade = cascade_index(position);
+    if cascade >= shadow.cascade_count {
+        return vec3f(1.0);
+    }
+    return colors[cascade];
+}
+
+// 单个光源的 Blinn-Phong 漫反射 + 高光
+fn blinn_phong(light: Light, position: vec3f, normal: vec3f, view_dir: vec3f, albedo: vec3f) -> vec3f {
+    var light_dir: vec3f;
+    var attenuation = 1.0;
+    if light.kind == LIGHT_DIRECTIONAL {
+        light_dir = -light.direction;
+    } else {
+        let to_light = light.position - position;
+        let distance = length(to_light);
+        light_dir = to_light / max(distance, 0.0001);
+        attenuation = distance_attenuation(distance, light.range);
+        if light.kind == LIGHT_SPOT {
+            // 内锥角以内全亮，外锥角以外全暗，中间平滑过渡
+            let cos_angle = dot(-light_dir, light.direction);
+            attenuation *= smoothstep(light.cos_outer, light.cos_inner, cos_angle);
+        }
+    }
+    let n_dot_l = max(dot(normal, light_dir), 0.0);
+    // Blinn-Phong 用半程向量代替反射向量，背光面没有高光
+    let half_dir = normalize(light_dir + view_dir);
+    let specular = pow(max(dot(normal, half_dir), 0.0), material.shininess) * step(0.0001, n_dot_l);
+    let radiance = light.color * light.intensity * attenuation;
+    return (albedo * n_dot_l + material.specular * specular) * radiance;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let albedo = textureSample(t_diffuse, s_diffuse, in.tex_coords) * material.diffuse;
+    // 把切线空间的法线贴图转换到世界空间
+    let n = normalize(in.normal);
+    let t = normalize(in.tangent.xyz);
+    let b = cross(n, t) * in.tangent.w;
+    let tangent_normal = textureSample(t_normal, s_normal, in.tex_coords).xyz * 2.0 - 1.0;
+    let normal = normalize(mat3x3f(t, b, n) * tangent_normal);
+    let view_dir = normalize(camera.position.xyz - in.world_position);
+    var color = albedo.rgb * lights.ambient.rgb;
+    for (var i = 0u; i < min(lights.count, MAX_LIGHTS); i++) {
+        let light = lights.lights[i];
+        color += blinn_phong(light, in.world_position, normal, view_dir, albedo.rgb)
+            * shadow_factor(light, in.world_position, n);
+    }
+    if shadow.debug_cascades != 0u && shadow.cascade_count > 0u {
+        color *= cascade_tint(in.world_position);
+    }
+    return vec4f(color, albedo.a);
+}
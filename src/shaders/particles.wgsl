@@ -0,0 +1,116 @@
+// 粒子的公告板渲染：每个存活粒子一个实例、6 个顶点组成始终朝向相机的正方形，
+// 实例数量由模拟阶段写入的间接绘制参数决定，顶点着色器通过存活列表找到粒子。
+// 颜色和大小按粒子的归一化年龄在发射器的曲线上取值，片元着色器把正方形裁成柔和的圆点。
+
+struct CameraUniform {
+    view_proj: mat4x4f,
+    view: mat4x4f,
+    proj: mat4x4f,
+    position: vec4f,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct Particle {
+    position: vec3f,
+    age: f32,
+    velocity: vec3f,
+    lifetime: f32,
+};
+
+// 和 particles_sim.wgsl 里的 EmitterUniform 相同
+struct EmitterUniform {
+    position_radius: vec4f,
+    direction_spread: vec4f,
+    gravity_drag: vec4f,
+    speed_lifetime: vec4f,
+    color_keys: array<vec4f, 4>,
+    color_times: vec4f,
+    size_keys: vec4f,
+    size_times: vec4f,
+    speed_keys: vec4f,
+    speed_times: vec4f,
+    attractors: array<vec4f, 4>,
+    counts: vec4u,
+    spawn_count: u32,
+    seed: u32,
+    capacity: u32,
+    dt: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> emitter: EmitterUniform;
+@group(1) @binding(1)
+var<storage, read> particles: array<Particle>;
+@group(1) @binding(2)
+var<storage, read> alive_list: array<u32>;
+
+fn sample_curve(values: vec4f, times: vec4f, count: u32, t: f32) -> f32 {
+    if count <= 1u || t <= times[0] {
+        return values[0];
+    }
+    for (var i = 1u; i < count; i++) {
+        if t <= times[i] {
+            let f = (t - times[i - 1u]) / max(times[i] - times[i - 1u], 1e-5);
+            return mix(values[i - 1u], values[i], f);
+        }
+    }
+    return values[count - 1u];
+}
+
+fn sample_color(t: f32) -> vec4f {
+    let count = emitter.counts.x;
+    if count <= 1u || t <= emitter.color_times[0] {
+        return emitter.color_keys[0];
+    }
+    for (var i = 1u; i < count; i++) {
+        if t <= emitter.color_times[i] {
+            let span = max(emitter.color_times[i] - emitter.color_times[i - 1u], 1e-5);
+            let f = (t - emitter.color_times[i - 1u]) / span;
+            return mix(emitter.color_keys[i - 1u], emitter.color_keys[i], f);
+        }
+    }
+    return emitter.color_keys[count - 1u];
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) uv: vec2f,
+    @location(1) color: vec4f,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    var corners = array<vec2f, 6>(
+        vec2f(-1.0, -1.0), vec2f(1.0, -1.0), vec2f(1.0, 1.0),
+        vec2f(-1.0, -1.0), vec2f(1.0, 1.0), vec2f(-1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    let particle = particles[alive_list[instance_index]];
+    let t = clamp(particle.age / particle.lifetime, 0.0, 1.0);
+    let size = sample_curve(emitter.size_keys, emitter.size_times, emitter.counts.y, t);
+    // 视图矩阵的前两行就是相机在世界空间里的右方向和上方向
+    let right = vec3f(camera.view[0].x, camera.view[1].x, camera.view[2].x);
+    let up = vec3f(camera.view[0].y, camera.view[1].y, camera.view[2].y);
+    let world = particle.position + (right * corner.x + up * corner.y) * size * 0.5;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4f(world, 1.0);
+    out.uv = corner;
+    out.color = sample_color(t);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let falloff = 1.0 - smoothstep(0.3, 1.0, length(in.uv));
+    let alpha = in.color.a * falloff;
+    if alpha <= 0.0 {
+        discard;
+    }
+    return vec4f(in.color.rgb, alpha);
+}
@@ -0,0 +1,174 @@
+// GPU 粒子模拟，每个发射器每帧两次调度：
+// emit 为这一帧要发射的每个粒子从死亡列表弹出一个空闲槽位，按发射器参数随机初始化；
+// simulate 推进所有槽位里存活的粒子，寿命结束的压回死亡列表，存活的追加到存活列表，
+// 同时累加间接绘制参数里的实例数量，渲染时只画存活的粒子。
+
+struct Particle {
+    position: vec3f,
+    age: f32,
+    velocity: vec3f,
+    // 小于等于 0 表示这个槽位是空闲的
+    lifetime: f32,
+};
+
+struct EmitterUniform {
+    // xyz 是发射位置，w 是发射球的半径
+    position_radius: vec4f,
+    // xyz 是发射方向，w 是圆锥的半角
+    direction_spread: vec4f,
+    // xyz 是重力加速度，w 是阻力系数
+    gravity_drag: vec4f,
+    // 初速度和寿命的随机范围：speed_min, speed_max, lifetime_min, lifetime_max
+    speed_lifetime: vec4f,
+    color_keys: array<vec4f, 4>,
+    color_times: vec4f,
+    size_keys: vec4f,
+    size_times: vec4f,
+    speed_keys: vec4f,
+    speed_times: vec4f,
+    // xyz 是位置，w 是强度，负数表示排斥
+    attractors: array<vec4f, 4>,
+    // 颜色、大小、速度曲线的关键帧数量和吸引子数量
+    counts: vec4u,
+    spawn_count: u32,
+    seed: u32,
+    capacity: u32,
+    dt: f32,
+};
+
+struct Counters {
+    // 死亡列表里的空闲槽位数量，弹出时可能短暂变成负数
+    dead_count: atomic<i32>,
+};
+
+// 和 wgpu::util::DrawIndirectArgs 的布局一致
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    first_vertex: u32,
+    first_instance: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> emitter: EmitterUniform;
+@group(0) @binding(1)
+var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2)
+var<storage, read_write> dead_list: array<u32>;
+@group(0) @binding(3)
+var<storage, read_write> counters: Counters;
+@group(0) @binding(4)
+var<storage, read_write> alive_list: array<u32>;
+@group(0) @binding(5)
+var<storage, read_write> draw_args: DrawArgs;
+
+const PI: f32 = 3.14159265359;
+
+// PCG 哈希，用发射序号和每帧的种子生成互不相关的随机数
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random(state: ptr<function, u32>) -> f32 {
+    *state = pcg(*state);
+    return f32(*state) / 4294967295.0;
+}
+
+// 以 n 为轴、半角为 spread 的圆锥里均匀分布的方向
+fn random_in_cone(n: vec3f, spread: f32, state: ptr<function, u32>) -> vec3f {
+    let cos_theta = mix(1.0, cos(spread), random(state));
+    let sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+    let phi = 2.0 * PI * random(state);
+    let up = select(vec3f(1.0, 0.0, 0.0), vec3f(0.0, 0.0, 1.0), abs(n.z) < 0.999);
+    let t = normalize(cross(up, n));
+    let b = cross(n, t);
+    return t * cos(phi) * sin_theta + b * sin(phi) * sin_theta + n * cos_theta;
+}
+
+// 在 times 给出的关键帧之间线性插值，超出范围时取两端的值
+fn sample_curve(values: vec4f, times: vec4f, count: u32, t: f32) -> f32 {
+    if count <= 1u || t <= times[0] {
+        return values[0];
+    }
+    for (var i = 1u; i < count; i++) {
+        if t <= times[i] {
+            let f = (t - times[i - 1u]) / max(times[i] - times[i - 1u], 1e-5);
+            return mix(values[i - 1u], values[i], f);
+        }
+    }
+    return values[count - 1u];
+}
+
+@compute @workgroup_size(64)
+fn emit(@builtin(global_invocation_id) id: vec3u) {
+    if id.x >= emitter.spawn_count {
+        return;
+    }
+    // 弹出一个空闲槽位，死亡列表已经空了就放弃这次发射
+    let previous = atomicSub(&counters.dead_count, 1);
+    if previous <= 0 {
+        atomicAdd(&counters.dead_count, 1);
+        return;
+    }
+    let index = dead_list[previous - 1];
+
+    var state = pcg(id.x ^ pcg(emitter.seed));
+    // 球内均匀分布：半径按立方根分布
+    let offset = random_in_cone(vec3f(0.0, 1.0, 0.0), PI, &state)
+        * pow(random(&state), 1.0 / 3.0) * emitter.position_radius.w;
+    let direction = random_in_cone(
+        normalize(emitter.direction_spread.xyz),
+        emitter.direction_spread.w,
+        &state,
+    );
+    let speed = mix(emitter.speed_lifetime.x, emitter.speed_lifetime.y, random(&state));
+    var particle: Particle;
+    particle.position = emitter.position_radius.xyz + offset;
+    particle.age = 0.0;
+    particle.velocity = direction * speed;
+    particle.lifetime = max(mix(emitter.speed_lifetime.z, emitter.speed_lifetime.w, random(&state)), 1e-3);
+    particles[index] = particle;
+}
+
+@compute @workgroup_size(256)
+fn simulate(@builtin(global_invocation_id) id: vec3u) {
+    if id.x >= emitter.capacity {
+        return;
+    }
+    var particle = particles[id.x];
+    if particle.lifetime <= 0.0 {
+        return;
+    }
+    let dt = emitter.dt;
+    particle.age += dt;
+    if particle.age >= particle.lifetime {
+        // 寿命结束：标记为空闲并压回死亡列表
+        particle.lifetime = 0.0;
+        particles[id.x] = particle;
+        let slot = atomicAdd(&counters.dead_count, 1);
+        dead_list[slot] = id.x;
+        return;
+    }
+
+    var acceleration = emitter.gravity_drag.xyz;
+    for (var i = 0u; i < emitter.counts.w; i++) {
+        let attractor = emitter.attractors[i];
+        let to_attractor = attractor.xyz - particle.position;
+        // 加一个小常数，粒子穿过吸引子中心时加速度不会无穷大
+        let distance2 = dot(to_attractor, to_attractor) + 0.05;
+        acceleration += attractor.w * to_attractor / (distance2 * sqrt(distance2));
+    }
+    particle.velocity += acceleration * dt;
+    // 指数衰减的阻力，和帧率无关
+    particle.velocity *= exp(-emitter.gravity_drag.w * dt);
+    // 速度曲线按归一化的年龄缩放位移
+    let t = particle.age / particle.lifetime;
+    let speed_scale = sample_curve(emitter.speed_keys, emitter.speed_times, emitter.counts.z, t);
+    particle.position += particle.velocity * speed_scale * dt;
+    particles[id.x] = particle;
+
+    let slot = atomicAdd(&draw_args.instance_count, 1u);
+    alive_list[slot] = id.x;
+}
@@ -0,0 +1,364 @@
+// glTF 场景着色器：金属度-粗糙度 PBR 材质 + 实例的模型矩阵
+// 直接光照用 Cook-Torrance 微表面模型：GGX 法线分布、Smith 几何遮蔽（Schlick-GGX 近似）和 Fresnel-Schlick，
+// 漫反射是 Lambert。光源和阴影在 group(2)，和 model.wgsl 共用同一个绑定组。
+// 加载了环境贴图时，环境光来自 group(3) 的 IBL：辐照度贴图做漫反射，预过滤贴图和 BRDF 查找表做镜面反射
+
+struct CameraUniform {
+    view_proj: mat4x4f,
+    view: mat4x4f,
+    proj: mat4x4f,
+    position: vec4f,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct PbrParams {
+    base_color: vec4f,
+    emissive: vec3f,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+};
+
+@group(1) @binding(0)
+var t_base_color: texture_2d<f32>;
+@group(1) @binding(1)
+var s_base_color: sampler;
+@group(1) @binding(2)
+var t_metallic_roughness: texture_2d<f32>;
+@group(1) @binding(3)
+var s_metallic_roughness: sampler;
+@group(1) @binding(4)
+var t_normal: texture_2d<f32>;
+@group(1) @binding(5)
+var s_normal: sampler;
+@group(1) @binding(6)
+var t_occlusion: texture_2d<f32>;
+@group(1) @binding(7)
+var s_occlusion: sampler;
+@group(1) @binding(8)
+var t_emissive: texture_2d<f32>;
+@group(1) @binding(9)
+var s_emissive: sampler;
+@group(1) @binding(10)
+var<uniform> material: PbrParams;
+
+const MAX_LIGHTS: u32 = 128u;
+const LIGHT_DIRECTIONAL: u32 = 0u;
+const LIGHT_POINT: u32 = 1u;
+const LIGHT_SPOT: u32 = 2u;
+
+struct Light {
+    position: vec3f,
+    kind: u32,
+    direction: vec3f,
+    range: f32,
+    color: vec3f,
+    intensity: f32,
+    cos_inner: f32,
+    cos_outer: f32,
+    shadow_layer: i32,
+};
+
+struct Lights {
+    ambient: vec4f,
+    count: u32,
+    lights: array<Light, MAX_LIGHTS>,
+};
+
+@group(2) @binding(0)
+var<uniform> lights: Lights;
+
+const MAX_CASCADES: u32 = 4u;
+
+struct ShadowUniform {
+    cascades: array<mat4x4f, 4>,
+    spots: array<mat4x4f, 4>,
+    splits: vec4f,
+    texel_sizes: vec4f,
+    cascade_count: u32,
+    pcf_radius: u32,
+    normal_offset: f32,
+    debug_cascades: u32,
+    inv_resolution: f32,
+};
+
+@group(2) @binding(1)
+var t_shadow: texture_depth_2d_array;
+@group(2) @binding(2)
+var s_shadow: sampler_comparison;
+@group(2) @binding(3)
+var<uniform> shadow: ShadowUniform;
+
+struct EnvironmentUniform {
+    intensity: f32,
+    max_lod: f32,
+    enabled: u32,
+};
+
+@group(3) @binding(1)
+var t_irradiance: texture_cube<f32>;
+@group(3) @binding(2)
+var t_prefiltered: texture_cube<f32>;
+@group(3) @binding(3)
+var t_brdf_lut: texture_2d<f32>;
+@group(3) @binding(4)
+var s_environment: sampler;
+@group(3) @binding(5)
+var<uniform> environment: EnvironmentUniform;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) tex_coords: vec2f,
+    @location(2) normal: vec3f,
+    @location(3) tangent: vec4f,
+};
+
+struct InstanceInput {
+    @location(5) model_0: vec4f,
+    @location(6) model_1: vec4f,
+    @location(7) model_2: vec4f,
+    @location(8) model_3: vec4f,
+    @location(9) color: vec4f,
+    @location(10) custom: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) tex_coords: vec2f,
+    @location(1) normal: vec3f,
+    @location(2) tangent: vec4f,
+    @location(3) world_position: vec3f,
+};
+
+@vertex
+fn vs_main(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4f(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    // 只支持等比缩放，非等比缩放时法线需要用逆转置矩阵
+    let model3 = mat3x3f(model[0].xyz, model[1].xyz, model[2].xyz);
+    let world_position = model * vec4f(in.position, 1.0);
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * world_position;
+    out.tex_coords = in.tex_coords;
+    out.normal = model3 * in.normal;
+    out.tangent = vec4f(model3 * in.tangent.xyz, in.tangent.w);
+    out.world_position = world_position.xyz;
+    return out;
+}
+
+const PI: f32 = 3.14159265359;
+
+// 光源在 range 处平滑地衰减到 0，range 以内近似平方反比
+fn distance_attenuation(distance: f32, range: f32) -> f32 {
+    let ratio = distance / max(range, 0.0001);
+    let window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (1.0 + distance * distance);
+}
+
+// 片元所在的级联，超出最后一个级联时返回 cascade_count
+fn cascade_index(position: vec3f) -> u32 {
+    let view_depth = -(camera.view * vec4f(position, 1.0)).z;
+    for (var i = 0u; i < shadow.cascade_count; i++) {
+        if view_depth < shadow.splits[i] {
+            return i;
+        }
+    }
+    return shadow.cascade_count;
+}
+
+// 在一层阴影贴图里做 (2r+1)×(2r+1) 的 PCF，返回被照亮的比例
+fn sample_shadow(light_view_proj: mat4x4f, layer: u32, position: vec3f) -> f32 {
+    let clip = light_view_proj * vec4f(position, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2f(0.5, -0.5) + 0.5;
+    if any(uv < vec2f(0.0)) || any(uv > vec2f(1.0)) || ndc.z > 1.0 {
+        return 1.0;
+    }
+    let r = i32(shadow.pcf_radius);
+    var lit = 0.0;
+    for (var y = -r; y <= r; y++) {
+        for (var x = -r; x <= r; x++) {
+            let offset = vec2f(f32(x), f32(y)) * shadow.inv_resolution;
+            lit += textureSampleCompareLevel(t_shadow, s_shadow, uv + offset, layer, ndc.z);
+        }
+    }
+    let size = f32(2 * r + 1);
+    return lit / (size * size);
+}
+
+// 沿法线把采样位置推出去大约 normal_offset 个纹素，避免表面自己遮挡自己
+fn shadow_factor(light: Light, position: vec3f, normal: vec3f) -> f32 {
+    if light.shadow_layer < 0 {
+        return 1.0;
+    }
+    let layer = u32(light.shadow_layer);
+    if light.kind == LIGHT_DIRECTIONAL {
+        let cascade = cascade_index(position);
+        if cascade >= shadow.cascade_count {
+            return 1.0;
+        }
+        let offset = normal * shadow.normal_offset * shadow.texel_sizes[cascade];
+        return sample_shadow(shadow.cascades[cascade], layer + cascade, position + offset);
+    }
+    let distance = length(light.position - position);
+    let tan_half = sqrt(max(1.0 - light.cos_outer * light.cos_outer, 0.0)) / max(light.cos_outer, 0.0001);
+    let texel = 2.0 * tan_half * distance * shadow.inv_resolution;
+    let offset = normal * shadow.normal_offset * texel;
+    return sample_shadow(shadow.spots[layer - MAX_CASCADES], layer, position + offset);
+}
+
+// 调试视图：按级联给画面着色，红、绿、蓝、黄依次是由近到远的级联
+fn cascade_tint(position: vec3f) -> vec3f {
+    var colors = array<vec3f, 4>(
+        vec3f(1.0, 0.3, 0.3),
+        vec3f(0.3, 1.0, 0.3),
+        vec3f(0.3, 0.3, 1.0),
+        vec3f(1.0, 1.0, 0.3),
+    );
+    let cascade = cascade_index(position);
+    if cascade >= shadow.cascade_count {
+        return vec3f(1.0);
+    }
+    return colors[cascade];
+}
+
+// GGX（Trowbridge-Reitz）法线分布：朝向半程向量的微表面所占的比例
+fn distribution_ggx(n_dot_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Smith 几何遮蔽：入射和出射方向各自被微表面挡住的比例相乘
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    // 直接光照使用的 k = (roughness + 1)² / 8
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+// Fresnel-Schlick：掠射角时反射率趋向 1
+fn fresnel_schlick(cos_theta: f32, f0: vec3f) -> vec3f {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// 环境光的菲涅尔项考虑粗糙度：粗糙表面在掠射角的反射不会那么强
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3f, roughness: f32) -> vec3f {
+    return f0 + (max(vec3f(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// 基于图像的环境光：漫反射和镜面反射都按 split-sum 近似从预计算的贴图里查出来
+fn ambient_ibl(n: vec3f, v: vec3f, albedo: vec3f, metallic: f32, roughness: f32, f0: vec3f) -> vec3f {
+    let n_dot_v = max(dot(n, v), 0.0001);
+    let f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    let k_d = (1.0 - f) * (1.0 - metallic);
+    let irradiance = textureSampleLevel(t_irradiance, s_environment, n, 0.0).rgb;
+    let r = reflect(-v, n);
+    let prefiltered = textureSampleLevel(t_prefiltered, s_environment, r, roughness * environment.max_lod).rgb;
+    let brdf = textureSampleLevel(t_brdf_lut, s_environment, vec2f(n_dot_v, roughness), 0.0).rg;
+    let specular = prefiltered * (f * brdf.x + brdf.y);
+    return (k_d * irradiance * albedo + specular) * environment.intensity;
+}
+
+// 光源到达片元的方向和辐照度
+struct Incoming {
+    direction: vec3f,
+    radiance: vec3f,
+};
+
+fn incoming_light(light: Light, position: vec3f) -> Incoming {
+    var out: Incoming;
+    var attenuation = 1.0;
+    if light.kind == LIGHT_DIRECTIONAL {
+        out.direction = -light.direction;
+    } else {
+        let to_light = light.position - position;
+        let distance = length(to_light);
+        out.direction = to_light / max(distance, 0.0001);
+        attenuation = distance_attenuation(distance, light.range);
+        if light.kind == LIGHT_SPOT {
+            let cos_angle = dot(-out.direction, light.direction);
+            attenuation *= smoothstep(light.cos_outer, light.cos_inner, cos_angle);
+        }
+    }
+    out.radiance = light.color * light.intensity * attenuation;
+    return out;
+}
+
+// 单个光源的 Cook-Torrance 镜面反射 + Lambert 漫反射
+fn cook_torrance(
+    l: vec3f,
+    radiance: vec3f,
+    n: vec3f,
+    v: vec3f,
+    albedo: vec3f,
+    metallic: f32,
+    roughness: f32,
+    f0: vec3f,
+) -> vec3f {
+    let n_dot_l = max(dot(n, l), 0.0);
+    if n_dot_l <= 0.0 {
+        return vec3f(0.0);
+    }
+    let h = normalize(l + v);
+    let n_dot_v = max(dot(n, v), 0.0001);
+    let n_dot_h = max(dot(n, h), 0.0);
+    // 感知上线性的粗糙度平方后才是 GGX 的 alpha
+    let alpha = roughness * roughness;
+    let d = distribution_ggx(n_dot_h, alpha);
+    let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+    let f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+    let specular = d * g * f / (4.0 * n_dot_v * n_dot_l);
+    // 被反射掉的能量不再参与漫反射，金属没有漫反射
+    let k_d = (1.0 - f) * (1.0 - metallic);
+    return (k_d * albedo / PI + specular) * radiance * n_dot_l;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let base_color = textureSample(t_base_color, s_base_color, in.tex_coords) * material.base_color;
+    // glTF 规定金属度在 B 通道，粗糙度在 G 通道
+    let metallic_roughness = textureSample(t_metallic_roughness, s_metallic_roughness, in.tex_coords);
+    let metallic = clamp(material.metallic * metallic_roughness.b, 0.0, 1.0);
+    // 粗糙度太小时高光会变成一个点，在低精度下闪烁
+    let roughness = clamp(material.roughness * metallic_roughness.g, 0.045, 1.0);
+    let n = normalize(in.normal);
+    let t = normalize(in.tangent.xyz - n * dot(n, in.tangent.xyz));
+    let b = cross(n, t) * in.tangent.w;
+    var tangent_normal = textureSample(t_normal, s_normal, in.tex_coords).xyz * 2.0 - 1.0;
+    tangent_normal = vec3f(tangent_normal.xy * material.normal_scale, tangent_normal.z);
+    var normal = normalize(mat3x3f(t, b, n) * tangent_normal);
+    let v = normalize(camera.position.xyz - in.world_position);
+    // 不剔除背面，双面材质的背面要翻转法线
+    if dot(n, v) < 0.0 {
+        normal = -normal;
+    }
+    let occlusion = mix(1.0, textureSample(t_occlusion, s_occlusion, in.tex_coords).r, material.occlusion_strength);
+    let emissive = textureSample(t_emissive, s_emissive, in.tex_coords).rgb * material.emissive;
+
+    // 非金属的垂直反射率统一取 0.04，金属用基础颜色
+    let f0 = mix(vec3f(0.04), base_color.rgb, metallic);
+    var color = vec3f(0.0);
+    for (var i = 0u; i < min(lights.count, MAX_LIGHTS); i++) {
+        let light = lights.lights[i];
+        let incoming = incoming_light(light, in.world_position);
+        color += cook_torrance(incoming.direction, incoming.radiance, normal, v, base_color.rgb, metallic, roughness, f0)
+            * shadow_factor(light, in.world_position, normal);
+    }
+    // 环境光：有环境贴图时用 IBL，否则漫反射部分用基础颜色、金属用 F0，只有环境光受遮蔽贴图影响
+    var ambient: vec3f;
+    if environment.enabled != 0u {
+        ambient = ambient_ibl(normal, v, base_color.rgb, metallic, roughness, f0);
+    } else {
+        ambient = lights.ambient.rgb * (base_color.rgb * (1.0 - metallic) + f0 * metallic);
+    }
+    color += ambient * occlusion + emissive;
+    if shadow.debug_cascades != 0u && shadow.cascade_count > 0u {
+        color *= cascade_tint(in.world_position);
+    }
+    return vec4f(color, base_color.a);
+}
@@ -0,0 +1,22 @@
+// 阴影通道：只把顶点变换到光源的裁剪空间，没有片元着色器，只写入深度
+
+@group(0) @binding(0)
+var<uniform> light_view_proj: mat4x4f;
+
+@vertex
+fn vs_main(@location(0) position: vec3f) -> @builtin(position) vec4f {
+    return light_view_proj * vec4f(position, 1.0);
+}
+
+// 实例化的网格，模型矩阵来自实例缓冲区
+@vertex
+fn vs_instanced(
+    @location(0) position: vec3f,
+    @location(5) model_0: vec4f,
+    @location(6) model_1: vec4f,
+    @location(7) model_2: vec4f,
+    @location(8) model_3: vec4f,
+) -> @builtin(position) vec4f {
+    let model = mat4x4f(model_0, model_1, model_2, model_3);
+    return light_view_proj * model * vec4f(position, 1.0);
+}
@@ -0,0 +1,144 @@
+// 程序化大气天空：对每个像素的视线做单次散射的光线步进（Nishita 模型）
+// 视线上每一点被太阳照亮的光，经过 Rayleigh（空气分子，短波长散射得多，天空是蓝的）
+// 和 Mie（气溶胶，几乎不分波长，集中在太阳周围）散射进视线，再沿视线衰减到相机。
+// 天空画在远平面上，在场景之后绘制，只有深度还是清除值的像素才会通过深度测试。
+
+// 远平面的深度，普通深度是 1.0，反向 Z 是 0.0，由 depth::use_far_plane 设置
+override FAR_DEPTH: f32 = 1.0;
+
+struct CameraUniform {
+    view_proj: mat4x4f,
+    view: mat4x4f,
+    proj: mat4x4f,
+    position: vec4f,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct SkyUniform {
+    // 指向太阳的方向
+    sun_direction: vec3f,
+    sun_intensity: f32,
+    // 海平面的散射系数（每米）
+    rayleigh: vec3f,
+    mie_g: f32,
+    mie: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> sky: SkyUniform;
+
+const PI: f32 = 3.14159265359;
+// 长度单位是米，和 sky.rs 里的常量一致
+const PLANET_RADIUS: f32 = 6360e3;
+const ATMOSPHERE_RADIUS: f32 = 6420e3;
+// 密度按高度指数衰减的标高
+const RAYLEIGH_HEIGHT: f32 = 8000.0;
+const MIE_HEIGHT: f32 = 1200.0;
+// Mie 的消光系数比散射系数大一些（气溶胶也会吸收）
+const MIE_EXTINCTION: f32 = 1.1;
+const VIEW_STEPS: u32 = 16u;
+const LIGHT_STEPS: u32 = 8u;
+// 太阳的视半径（弧度）
+const SUN_ANGULAR_RADIUS: f32 = 0.0093;
+// 太阳落山后天空不是全黑
+const NIGHT_SKY: vec3f = vec3f(0.0004, 0.0007, 0.0015);
+
+// 射线和以原点为球心的球的两个交点距离，没有交点时返回 (-1, -1)
+// c 写成 (|o| - r)(|o| + r)，避免两个很大的平方相减丢失精度
+fn ray_sphere(origin: vec3f, dir: vec3f, radius: f32) -> vec2f {
+    let b = dot(origin, dir);
+    let len = length(origin);
+    let c = (len - radius) * (len + radius);
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return vec2f(-1.0);
+    }
+    let s = sqrt(discriminant);
+    return vec2f(-b - s, -b + s);
+}
+
+fn rayleigh_phase(mu: f32) -> f32 {
+    return 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+}
+
+// Cornette-Shanks 相位函数
+fn mie_phase(mu: f32, g: f32) -> f32 {
+    let g2 = g * g;
+    let denom = (2.0 + g2) * pow(1.0 + g2 - 2.0 * g * mu, 1.5);
+    return 3.0 / (8.0 * PI) * (1.0 - g2) * (1.0 + mu * mu) / denom;
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) ndc: vec2f,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.ndc = uv * 2.0 - 1.0;
+    out.clip_position = vec4f(out.ndc, FAR_DEPTH, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let view_dir = vec3f(in.ndc.x / camera.proj[0][0], in.ndc.y / camera.proj[1][1], -1.0);
+    let rotation = mat3x3f(camera.view[0].xyz, camera.view[1].xyz, camera.view[2].xyz);
+    let dir = normalize(transpose(rotation) * view_dir);
+    let sun = normalize(sky.sun_direction);
+
+    // 相机放在地面上方 1 米，场景本身的尺度比大气小得多，忽略相机的位置
+    let origin = vec3f(0.0, PLANET_RADIUS + 1.0, 0.0);
+    let ground = ray_sphere(origin, dir, PLANET_RADIUS);
+    let hits_ground = ground.x > 0.0;
+    let t_max = select(ray_sphere(origin, dir, ATMOSPHERE_RADIUS).y, ground.x, hits_ground);
+    let step = t_max / f32(VIEW_STEPS);
+
+    var depth_rayleigh = 0.0;
+    var depth_mie = 0.0;
+    var sum_rayleigh = vec3f(0.0);
+    var sum_mie = vec3f(0.0);
+    for (var i = 0u; i < VIEW_STEPS; i++) {
+        let position = origin + dir * (f32(i) + 0.5) * step;
+        let height = length(position) - PLANET_RADIUS;
+        let density_rayleigh = exp(-height / RAYLEIGH_HEIGHT) * step;
+        let density_mie = exp(-height / MIE_HEIGHT) * step;
+        depth_rayleigh += density_rayleigh;
+        depth_mie += density_mie;
+        // 这一点被地球挡住时照不到阳光
+        if ray_sphere(position, sun, PLANET_RADIUS).x > 0.0 {
+            continue;
+        }
+        // 从这一点到大气层顶部沿太阳方向的光学深度
+        let light_step = ray_sphere(position, sun, ATMOSPHERE_RADIUS).y / f32(LIGHT_STEPS);
+        var light_rayleigh = 0.0;
+        var light_mie = 0.0;
+        for (var j = 0u; j < LIGHT_STEPS; j++) {
+            let p = position + sun * (f32(j) + 0.5) * light_step;
+            let h = length(p) - PLANET_RADIUS;
+            light_rayleigh += exp(-h / RAYLEIGH_HEIGHT) * light_step;
+            light_mie += exp(-h / MIE_HEIGHT) * light_step;
+        }
+        let tau = sky.rayleigh * (depth_rayleigh + light_rayleigh)
+            + sky.mie * MIE_EXTINCTION * (depth_mie + light_mie);
+        let attenuation = exp(-tau);
+        sum_rayleigh += attenuation * density_rayleigh;
+        sum_mie += attenuation * density_mie;
+    }
+
+    let mu = dot(dir, sun);
+    var color = sky.sun_intensity * (sum_rayleigh * sky.rayleigh * rayleigh_phase(mu)
+        + sum_mie * sky.mie * mie_phase(mu, sky.mie_g));
+    // 太阳圆盘：沿视线衰减后的太阳光，边缘稍微柔化
+    if !hits_ground {
+        let transmittance = exp(-(sky.rayleigh * depth_rayleigh + sky.mie * MIE_EXTINCTION * depth_mie));
+        let disk = smoothstep(cos(SUN_ANGULAR_RADIUS * 1.2), cos(SUN_ANGULAR_RADIUS), mu);
+        color += disk * sky.sun_intensity * transmittance;
+    }
+    return vec4f(color + NIGHT_SKY, 1.0);
+}
@@ -0,0 +1,53 @@
+// 天空盒：全屏三角形，每个像素按视线方向采样环境立方体贴图
+// 画在远平面上，在场景之后绘制，只有深度还是清除值的像素才会通过深度测试
+
+// 远平面的深度，普通深度是 1.0，反向 Z 是 0.0，由 depth::use_far_plane 设置
+override FAR_DEPTH: f32 = 1.0;
+
+struct CameraUniform {
+    view_proj: mat4x4f,
+    view: mat4x4f,
+    proj: mat4x4f,
+    position: vec4f,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct EnvironmentUniform {
+    intensity: f32,
+    max_lod: f32,
+    enabled: u32,
+};
+
+@group(1) @binding(0)
+var t_environment: texture_cube<f32>;
+@group(1) @binding(4)
+var s_environment: sampler;
+@group(1) @binding(5)
+var<uniform> environment: EnvironmentUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) ndc: vec2f,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.ndc = uv * 2.0 - 1.0;
+    out.clip_position = vec4f(out.ndc, FAR_DEPTH, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    // 用投影矩阵的缩放把 NDC 还原成视图空间的方向，再用视图矩阵的转置（旋转部分的逆）转到世界空间
+    let view_dir = vec3f(in.ndc.x / camera.proj[0][0], in.ndc.y / camera.proj[1][1], -1.0);
+    let rotation = mat3x3f(camera.view[0].xyz, camera.view[1].xyz, camera.view[2].xyz);
+    let dir = normalize(transpose(rotation) * view_dir);
+    let color = textureSampleLevel(t_environment, s_environment, dir, 0.0).rgb;
+    return vec4f(color * environment.intensity, 1.0);
+}
@@ -0,0 +1,69 @@
+// 精灵：每个实例是一个带纹理的矩形，四个角由 vertex_index 生成
+// 世界坐标是像素，y 轴向下，正交相机把它映射到裁剪空间
+
+struct SpriteUniform {
+    view_projection: mat4x4f,
+    // 输出格式是 sRGB 时为 1：着色颜色是 sRGB 值，先转回线性，写入时硬件会再编码
+    decode_srgb: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: SpriteUniform;
+@group(1) @binding(0)
+var t_sprite: texture_2d<f32>;
+@group(1) @binding(1)
+var s_sprite: sampler;
+
+struct InstanceInput {
+    // 中心的位置
+    @location(0) position: vec2f,
+    // 缩放之后的像素尺寸
+    @location(1) size: vec2f,
+    // (u0, v0, u1, v1)，左上角和右下角的纹理坐标
+    @location(2) uv_rect: vec4f,
+    @location(3) tint: vec4f,
+    @location(4) rotation: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) uv: vec2f,
+    @location(1) tint: vec4f,
+};
+
+// 两个三角形：左上、左下、右下，左上、右下、右上
+const CORNERS = array<vec2f, 6>(
+    vec2f(0.0, 0.0), vec2f(0.0, 1.0), vec2f(1.0, 1.0),
+    vec2f(0.0, 0.0), vec2f(1.0, 1.0), vec2f(1.0, 0.0),
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, in: InstanceInput) -> VertexOutput {
+    let corner = CORNERS[index];
+    let local = (corner - 0.5) * in.size;
+    // y 轴向下，正的角度在屏幕上是顺时针旋转
+    let c = cos(in.rotation);
+    let s = sin(in.rotation);
+    let rotated = vec2f(local.x * c - local.y * s, local.x * s + local.y * c);
+    var out: VertexOutput;
+    out.clip_position = params.view_projection * vec4f(in.position + rotated, 0.0, 1.0);
+    out.uv = mix(in.uv_rect.xy, in.uv_rect.zw, corner);
+    out.tint = in.tint;
+    return out;
+}
+
+fn srgb_to_linear(x: vec3f) -> vec3f {
+    let low = x / 12.92;
+    let high = pow((x + 0.055) / 1.055, vec3f(2.4));
+    return select(high, low, x <= vec3f(0.04045));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    var tint = in.tint.rgb;
+    if params.decode_srgb != 0u {
+        tint = srgb_to_linear(tint);
+    }
+    let color = textureSample(t_sprite, s_sprite, in.uv);
+    return vec4f(color.rgb * tint, color.a * in.tint.a);
+}
@@ -0,0 +1,53 @@
+// 文字：每个字形一个矩形，顶点坐标是以左上角为原点的像素坐标
+// 图集里只存覆盖率（R8），颜色来自顶点，覆盖率作为 alpha 和展示平面混合
+
+struct TextUniform {
+    screen_size: vec2f,
+    // 输出格式是 sRGB 时为 1：顶点颜色是 sRGB 值，先转回线性，写入时硬件会再编码
+    decode_srgb: u32,
+};
+
+@group(0) @binding(0)
+var t_atlas: texture_2d<f32>;
+@group(0) @binding(1)
+var s_atlas: sampler;
+@group(0) @binding(2)
+var<uniform> params: TextUniform;
+
+struct VertexInput {
+    @location(0) position: vec2f,
+    @location(1) uv: vec2f,
+    @location(2) color: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) uv: vec2f,
+    @location(1) color: vec4f,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let ndc = in.position / params.screen_size * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.clip_position = vec4f(ndc.x, -ndc.y, 0.0, 1.0);
+    out.uv = in.uv;
+    out.color = in.color;
+    return out;
+}
+
+fn srgb_to_linear(x: vec3f) -> vec3f {
+    let low = x / 12.92;
+    let high = pow((x + 0.055) / 1.055, vec3f(2.4));
+    return select(high, low, x <= vec3f(0.04045));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let coverage = textureSample(t_atlas, s_atlas, in.uv).r;
+    var rgb = in.color.rgb;
+    if params.decode_srgb != 0u {
+        rgb = srgb_to_linear(rgb);
+    }
+    return vec4f(rgb, in.color.a * coverage);
+}
@@ -0,0 +1,111 @@
+// 色调映射：把 HDR 纹理乘以曝光后压缩到 [0, 1]
+// 用一个覆盖整个屏幕的大三角形，顶点位置由 vertex_index 生成
+
+struct TonemapUniform {
+    exposure: f32,
+    tonemapper: u32,
+    encode_srgb: u32,
+    auto_exposure: u32,
+};
+
+@group(0) @binding(0)
+var t_hdr: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> params: TonemapUniform;
+// 自动曝光在计算着色器里算出的平均亮度
+@group(0) @binding(2)
+var<storage, read> luminance: f32;
+
+// 自动曝光把平均亮度映射到中灰
+const MIDDLE_GRAY: f32 = 0.18;
+
+const TONEMAP_NONE: u32 = 0u;
+const TONEMAP_REINHARD: u32 = 1u;
+const TONEMAP_ACES: u32 = 2u;
+const TONEMAP_AGX: u32 = 3u;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn reinhard(x: vec3f) -> vec3f {
+    return x / (1.0 + x);
+}
+
+// Krzysztof Narkowicz 对 ACES 电影曲线的拟合
+fn aces(x: vec3f) -> vec3f {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3f(0.0), vec3f(1.0));
+}
+
+// AgX 的对比度曲线的多项式拟合，输入是归一化的对数空间
+fn agx_contrast(x: vec3f) -> vec3f {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn agx(color: vec3f) -> vec3f {
+    let inset = mat3x3f(
+        vec3f(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3f(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3f(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+    );
+    let outset = mat3x3f(
+        vec3f(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3f(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3f(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+    var x = inset * color;
+    x = clamp(log2(max(x, vec3f(1e-10))), vec3f(min_ev), vec3f(max_ev));
+    x = (x - min_ev) / (max_ev - min_ev);
+    x = agx_contrast(x);
+    x = outset * x;
+    // 曲线的输出带有 2.2 的伽马，转回线性，最后统一做 sRGB 编码
+    return pow(max(x, vec3f(0.0)), vec3f(2.2));
+}
+
+fn linear_to_srgb(x: vec3f) -> vec3f {
+    let low = x * 12.92;
+    let high = 1.055 * pow(x, vec3f(1.0 / 2.4)) - 0.055;
+    return select(high, low, x <= vec3f(0.0031308));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    let hdr = textureLoad(t_hdr, vec2i(position.xy), 0);
+    var exposure = params.exposure;
+    if params.auto_exposure != 0u {
+        exposure *= MIDDLE_GRAY / max(luminance, 0.0001);
+    }
+    let color = max(hdr.rgb * exposure, vec3f(0.0));
+    var mapped: vec3f;
+    switch params.tonemapper {
+        case TONEMAP_REINHARD: {
+            mapped = reinhard(color);
+        }
+        case TONEMAP_ACES: {
+            mapped = aces(color);
+        }
+        case TONEMAP_AGX: {
+            mapped = agx(color);
+        }
+        default: {
+            mapped = color;
+        }
+    }
+    mapped = clamp(mapped, vec3f(0.0), vec3f(1.0));
+    if params.encode_srgb != 0u {
+        mapped = linear_to_srgb(mapped);
+    }
+    return vec4f(mapped, 1.0);
+}
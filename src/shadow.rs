@@ -0,0 +1,420 @@
+// 阴影贴图
+// 每个投射阴影的光源先从光源的视角渲染一遍只有深度的通道，着色时比较片元在光源空间的深度判断是否被遮挡。
+// 平行光使用级联阴影：把相机视锥按距离切成几段，每段用一张正交投影的阴影贴图，近处的阴影更清晰；
+// 聚光灯用一张透视投影的阴影贴图。所有阴影贴图是同一个深度纹理数组的不同层：
+// 前 MAX_CASCADES 层给平行光的级联，后面 MAX_SPOT_SHADOWS 层给聚光灯。
+// 采样时用比较采样器做 PCF（百分比渐近过滤），让阴影边缘柔和一些；
+// 深度偏移（常量 + 按斜率）和沿法线的偏移用来消除阴影痤疮（shadow acne）。
+// 点光源需要立方体阴影贴图，这里不支持。
+
+use glam::{Mat4, Vec3, Vec3Swizzles};
+
+use crate::camera::Camera;
+use crate::instance::InstanceRaw;
+use crate::model::ModelVertex;
+use crate::pipeline::{Renderer, Shader};
+use crate::texture::{BindingKind, BindingLayout};
+
+pub const MAX_CASCADES: usize = 4;
+pub const MAX_SPOT_SHADOWS: usize = 4;
+pub const SHADOW_LAYERS: usize = MAX_CASCADES + MAX_SPOT_SHADOWS;
+// 每层阴影贴图的边长，纹理数组在创建时固定，光源的绑定组才能一直有效
+pub const SHADOW_MAP_SIZE: u32 = 1024;
+pub const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    // 平行光的级联数量，1 到 MAX_CASCADES
+    pub cascade_count: u32,
+    // 级联覆盖的最远距离，超出的部分没有阴影
+    pub max_distance: f32,
+    // 切分位置在均匀切分（0）和对数切分（1）之间插值
+    pub split_lambda: f32,
+    // 渲染阴影贴图时的常量深度偏移（深度缓冲区的最小单位）和按斜率的偏移
+    pub depth_bias: i32,
+    pub slope_bias: f32,
+    // 采样前沿法线把片元推出去的距离，单位是阴影贴图的纹素
+    pub normal_offset: f32,
+    // PCF 的半径，0 只采样一次，1 是 3×3，2 是 5×5
+    pub pcf_radius: u32,
+    // 按级联给画面着色，方便调整切分位置
+    pub debug_cascades: bool,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            cascade_count: 4,
+            max_distance: 30.0,
+            split_lambda: 0.75,
+            depth_bias: 2,
+            slope_bias: 2.0,
+            normal_offset: 1.5,
+            pcf_radius: 1,
+            debug_cascades: false,
+        }
+    }
+}
+
+// 和着色器里的 ShadowUniform 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowUniform {
+    cascades: [[[f32; 4]; 4]; MAX_CASCADES],
+    spots: [[[f32; 4]; 4]; MAX_SPOT_SHADOWS],
+    // 每个级联在相机视图空间里的最远距离
+    splits: [f32; 4],
+    // 每个级联一个纹素对应的世界空间尺寸，用来换算法线偏移
+    texel_sizes: [f32; 4],
+    cascade_count: u32,
+    pcf_radius: u32,
+    normal_offset: f32,
+    debug_cascades: u32,
+    inv_resolution: f32,
+    _padding: [f32; 3],
+}
+
+// 投射阴影的光源，由 Lights 按顺序分配纹理数组的层
+pub struct ShadowCasters {
+    // 平行光的方向
+    pub directional: Option<Vec3>,
+    // 聚光灯的视图投影矩阵，第 i 个使用第 MAX_CASCADES + i 层
+    pub spots: Vec<Mat4>,
+}
+
+// 把 [near, far] 切成 count 段，返回每段的最远距离
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let p = i as f32 / count as f32;
+            let log = near * (far / near).powf(p);
+            let uniform = near + (far - near) * p;
+            lambda * log + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+// 相机视锥在 [near, far] 之间的 8 个角，世界坐标
+pub fn frustum_corners(camera: &Camera, near: f32, far: f32) -> [Vec3; 8] {
+    let inv_view = camera.view().inverse();
+    let tan = (camera.fovy * 0.5).tan();
+    let mut corners = [Vec3::ZERO; 8];
+    for (i, z) in [near, far].into_iter().enumerate() {
+        let h = z * tan;
+        let w = h * camera.aspect;
+        for (j, (x, y)) in [(-w, -h), (w, -h), (w, h), (-w, h)].into_iter().enumerate() {
+            corners[i * 4 + j] = inv_view.transform_point3(Vec3::new(x, y, -z));
+        }
+    }
+    corners
+}
+
+fn light_up(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    }
+}
+
+// 用包围球包住一段视锥，球的半径不随相机旋转变化，阴影边缘就不会闪烁；
+// 再把投影的平移对齐到纹素网格，相机平移时阴影也不会闪烁。返回视图投影矩阵和纹素的世界尺寸
+pub fn fit_cascade(
+    camera: &Camera,
+    near: f32,
+    far: f32,
+    direction: Vec3,
+    resolution: u32,
+) -> (Mat4, f32) {
+    let corners = frustum_corners(camera, near, far);
+    let center = corners.iter().copied().sum::<Vec3>() / 8.0;
+    let radius = corners
+        .iter()
+        .map(|c| c.distance(center))
+        .fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+    // 沿光的反方向多退一段，把视锥外面、光源一侧的遮挡物也包含进来
+    let back = radius * 3.0;
+    let direction = direction.normalize_or(Vec3::NEG_Y);
+    let view = Mat4::look_at_rh(center - direction * back, center, light_up(direction));
+    let mut projection =
+        Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, back + radius);
+    let origin = (projection * view).project_point3(Vec3::ZERO).xy() * (resolution as f32 * 0.5);
+    let offset = (origin.round() - origin) * (2.0 / resolution as f32);
+    projection.w_axis.x += offset.x;
+    projection.w_axis.y += offset.y;
+    (projection * view, radius * 2.0 / resolution as f32)
+}
+
+// 聚光灯的视图投影矩阵，视野等于外锥角的两倍
+pub fn spot_view_projection(position: Vec3, direction: Vec3, outer_cone: f32, range: f32) -> Mat4 {
+    let direction = direction.normalize_or(Vec3::NEG_Y);
+    let fov = (outer_cone * 2.0).clamp(0.01, std::f32::consts::PI - 0.01);
+    let near = (range * 0.001).max(0.02);
+    Mat4::perspective_rh(fov, 1.0, near, range.max(near * 2.0))
+        * Mat4::look_to_rh(position, direction, light_up(direction))
+}
+
+// 阴影贴图纹理数组、比较采样器和渲染阴影用的管线
+pub struct ShadowMaps {
+    pub settings: ShadowSettings,
+    pub texture: wgpu::Texture,
+    // 着色时采样的整个数组
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    // 着色器里的 ShadowUniform
+    pub buffer: wgpu::Buffer,
+    // 渲染每一层时的深度附件，以及保存这一层视图投影矩阵的绑定组
+    layer_views: Vec<wgpu::TextureView>,
+    layer_buffers: Vec<wgpu::Buffer>,
+    layer_bind_groups: Vec<wgpu::BindGroup>,
+    layer_layout: BindingLayout,
+    mesh_pipeline: wgpu::RenderPipeline,
+    instanced_pipeline: wgpu::RenderPipeline,
+    // 这一帧需要渲染的层
+    active_layers: Vec<usize>,
+}
+
+impl ShadowMaps {
+    pub fn new(device: &wgpu::Device, settings: ShadowSettings) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Maps"),
+            size: wgpu::Extent3d {
+                width: SHADOW_MAP_SIZE,
+                height: SHADOW_MAP_SIZE,
+                depth_or_array_layers: SHADOW_LAYERS as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let layer_views = (0..SHADOW_LAYERS as u32)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Shadow Map Layer"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        // 比较采样器：采样结果是 “片元深度 <= 阴影贴图深度” 的比例，线性过滤相当于额外的 2×2 PCF
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Uniform Buffer"),
+            size: std::mem::size_of::<ShadowUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layer_layout = BindingLayout::new(
+            device,
+            "Shadow Layer Bind Group Layout",
+            &[(wgpu::ShaderStages::VERTEX, BindingKind::Uniform)],
+        );
+        let layer_buffers: Vec<wgpu::Buffer> = (0..SHADOW_LAYERS)
+            .map(|_| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Shadow Layer Buffer"),
+                    size: std::mem::size_of::<Mat4>() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+        let layer_bind_groups = layer_buffers
+            .iter()
+            .map(|b| layer_layout.create_bind_group(device, &[b.as_entire_binding()]))
+            .collect();
+        let (mesh_pipeline, instanced_pipeline) =
+            Self::create_pipelines(device, &layer_layout, &settings);
+        Self {
+            settings,
+            texture,
+            view,
+            sampler,
+            buffer,
+            layer_views,
+            layer_buffers,
+            layer_bind_groups,
+            layer_layout,
+            mesh_pipeline,
+            instanced_pipeline,
+            active_layers: Vec::new(),
+        }
+    }
+
+    // 只有深度的管线，没有片元着色器；深度偏移是管线状态，修改后要重建
+    fn create_pipelines(
+        device: &wgpu::Device,
+        layout: &BindingLayout,
+        settings: &ShadowSettings,
+    ) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+        let module = Shader::from_wgsl("shadow.wgsl", include_str!("shaders/shadow.wgsl"))
+            .create_module(device);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&layout.layout],
+            push_constant_ranges: &[],
+        });
+        let create = |label, entry_point, buffers: &[wgpu::VertexBufferLayout<'_>]| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &module,
+                    entry_point: Some(entry_point),
+                    buffers,
+                    compilation_options: Default::default(),
+                },
+                fragment: None,
+                primitive: wgpu::PrimitiveState {
+                    // 模型不一定是封闭的，两面都要投射阴影
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: SHADOW_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState {
+                        constant: settings.depth_bias,
+                        slope_scale: settings.slope_bias,
+                        clamp: 0.0,
+                    },
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        (
+            create("Shadow Pipeline", "vs_main", &[ModelVertex::desc()]),
+            create(
+                "Instanced Shadow Pipeline",
+                "vs_instanced",
+                &[ModelVertex::desc(), InstanceRaw::desc()],
+            ),
+        )
+    }
+
+    pub fn set_settings(&mut self, device: &wgpu::Device, settings: ShadowSettings) {
+        let rebuild = settings.depth_bias != self.settings.depth_bias
+            || settings.slope_bias != self.settings.slope_bias;
+        self.settings = settings;
+        if rebuild {
+            (self.mesh_pipeline, self.instanced_pipeline) =
+                Self::create_pipelines(device, &self.layer_layout, &settings);
+        }
+    }
+
+    // 每帧根据相机和投射阴影的光源计算各层的矩阵并上传
+    pub fn update(&mut self, queue: &wgpu::Queue, camera: &Camera, casters: &ShadowCasters) {
+        let settings = self.settings;
+        let count = (settings.cascade_count as usize).clamp(1, MAX_CASCADES);
+        let mut uniform = ShadowUniform {
+            cascades: [Mat4::IDENTITY.to_cols_array_2d(); MAX_CASCADES],
+            spots: [Mat4::IDENTITY.to_cols_array_2d(); MAX_SPOT_SHADOWS],
+            splits: [0.0; 4],
+            texel_sizes: [0.0; 4],
+            cascade_count: 0,
+            pcf_radius: settings.pcf_radius,
+            normal_offset: settings.normal_offset,
+            debug_cascades: settings.debug_cascades as u32,
+            inv_resolution: 1.0 / SHADOW_MAP_SIZE as f32,
+            _padding: [0.0; 3],
+        };
+        self.active_layers.clear();
+        if let Some(direction) = casters.directional {
+            let far = settings
+                .max_distance
+                .min(camera.zfar)
+                .max(camera.znear * 2.0);
+            let splits = cascade_splits(camera.znear, far, count, settings.split_lambda);
+            let mut near = camera.znear;
+            for (i, &split) in splits.iter().enumerate() {
+                let (matrix, texel) = fit_cascade(camera, near, split, direction, SHADOW_MAP_SIZE);
+                uniform.cascades[i] = matrix.to_cols_array_2d();
+                uniform.splits[i] = split;
+                uniform.texel_sizes[i] = texel;
+                self.write_layer(queue, i, matrix);
+                near = split;
+            }
+            uniform.cascade_count = count as u32;
+        }
+        for (i, &matrix) in casters.spots.iter().take(MAX_SPOT_SHADOWS).enumerate() {
+            uniform.spots[i] = matrix.to_cols_array_2d();
+            self.write_layer(queue, MAX_CASCADES + i, matrix);
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    fn write_layer(&mut self, queue: &wgpu::Queue, layer: usize, matrix: Mat4) {
+        queue.write_buffer(&self.layer_buffers[layer], 0, bytemuck::bytes_of(&matrix));
+        self.active_layers.push(layer);
+    }
+
+    // 在主渲染通道之前，为每个用到的层渲染一遍投射阴影的绘制
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, renderer: &Renderer) {
+        for &layer in &self.active_layers {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.layer_views[layer],
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                ..Default::default()
+            });
+            pass.set_bind_group(0, &self.layer_bind_groups[layer], &[]);
+            renderer.draw_shadow_casters(&mut pass, &self.mesh_pipeline, &self.instanced_pipeline);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_interpolate_between_uniform_and_log() {
+        let uniform = cascade_splits(1.0, 100.0, 2, 0.0);
+        assert_eq!(uniform, [50.5, 100.0]);
+        let log = cascade_splits(1.0, 100.0, 2, 1.0);
+        assert!((log[0] - 10.0).abs() < 1e-4);
+        assert!((log[1] - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cascade_contains_its_frustum_slice() {
+        let camera = Camera::new(1.5);
+        let direction = Vec3::new(-0.3, -1.0, -0.2);
+        let (matrix, texel) = fit_cascade(&camera, 0.5, 5.0, direction, 1024);
+        assert!(texel > 0.0);
+        for corner in frustum_corners(&camera, 0.5, 5.0) {
+            let p = matrix.project_point3(corner);
+            assert!(p.x.abs() <= 1.0 && p.y.abs() <= 1.0, "{p}");
+            assert!((0.0..=1.0).contains(&p.z), "{p}");
+        }
+    }
+}
@@ -0,0 +1,299 @@
+// 程序化天空
+// 除了 .hdr 环境贴图的天空盒，也可以用基于物理的大气散射（Rayleigh + Mie）实时画出天空。
+// 太阳的位置由一天中的时间决定，同一个时间也驱动场景里平行光的方向和颜色：
+// 在 CPU 上沿太阳方向积分大气的透射率，得到穿过大气后的太阳光颜色，日落时自然变成橙红色；
+// 太阳落到地平线以下后，平行光换成从反方向照来的、很暗的偏蓝月光。
+// 天空在场景之后画在远平面上，只填充深度缓冲区里还没有被物体覆盖的像素。
+
+use glam::Vec3;
+use wgpu::util::DeviceExt;
+
+use crate::depth;
+use crate::light::{Light, LightId, LightKind, Lights};
+use crate::pipeline::{Pipeline, PipelineDescriptor, Shader, TargetState};
+use crate::texture::{BindingKind, BindingLayout};
+
+// 大气模型的常量，和 sky.wgsl 一致，长度单位是米
+const PLANET_RADIUS: f32 = 6360e3;
+const ATMOSPHERE_RADIUS: f32 = 6420e3;
+const RAYLEIGH_HEIGHT: f32 = 8000.0;
+const MIE_HEIGHT: f32 = 1200.0;
+const MIE_EXTINCTION: f32 = 1.1;
+// 海平面的散射系数，对应红、绿、蓝三个波长（680、550、440 nm）
+const RAYLEIGH_SCATTERING: Vec3 = Vec3::new(5.8e-6, 13.5e-6, 33.1e-6);
+const MIE_SCATTERING: f32 = 21e-6;
+// 计算太阳光透射率时的积分步数
+const TRANSMITTANCE_STEPS: u32 = 32;
+// 月光的颜色和相对太阳光的强度
+const MOONLIGHT_COLOR: Vec3 = Vec3::new(0.6, 0.7, 1.0);
+const MOONLIGHT_SCALE: f32 = 0.03;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkySettings {
+    // 一天中的时间（小时，0-24），6 点日出、12 点正午、18 点日落
+    pub time_of_day: f32,
+    // 日出方向的方位角（弧度），0 表示太阳从 +X 方向升起
+    pub sun_azimuth: f32,
+    // 太阳轨道相对天顶的倾斜（弧度），0 表示正午时太阳在头顶
+    pub sun_tilt: f32,
+    // 大气散射使用的太阳辐照度，决定天空的亮度
+    pub sun_intensity: f32,
+    // 驱动平行光时，太阳在头顶时的光照强度
+    pub light_intensity: f32,
+    // Mie 散射的各向异性，越接近 1 太阳周围的光晕越集中
+    pub mie_g: f32,
+    // 气溶胶浓度的倍数，越大天空越朦胧、日落越红
+    pub turbidity: f32,
+}
+
+impl Default for SkySettings {
+    fn default() -> Self {
+        Self {
+            time_of_day: 10.0,
+            sun_azimuth: 0.0,
+            sun_tilt: 0.5,
+            sun_intensity: 20.0,
+            light_intensity: 3.0,
+            mie_g: 0.76,
+            turbidity: 1.0,
+        }
+    }
+}
+
+impl SkySettings {
+    // 指向太阳的单位向量：太阳在日出方向和正午方向张成的平面上转动，一天转一圈
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time_of_day - 6.0) / 24.0 * std::f32::consts::TAU;
+        let (sin_az, cos_az) = self.sun_azimuth.sin_cos();
+        let east = Vec3::new(cos_az, 0.0, sin_az);
+        let south = east.cross(Vec3::Y);
+        let noon = Vec3::Y * self.sun_tilt.cos() + south * self.sun_tilt.sin();
+        (east * angle.cos() + noon * angle.sin()).normalize()
+    }
+
+    fn mie_scattering(&self) -> f32 {
+        MIE_SCATTERING * self.turbidity
+    }
+
+    // 太阳光从大气层顶部沿 direction 的反方向到达地面后剩下的比例，被地球挡住时为 0
+    pub fn transmittance(&self, direction: Vec3) -> Vec3 {
+        let origin = Vec3::new(0.0, PLANET_RADIUS + 1.0, 0.0);
+        if ray_sphere(origin, direction, PLANET_RADIUS).is_some_and(|(near, _)| near > 0.0) {
+            return Vec3::ZERO;
+        }
+        let Some((_, far)) = ray_sphere(origin, direction, ATMOSPHERE_RADIUS) else {
+            return Vec3::ONE;
+        };
+        let step = far / TRANSMITTANCE_STEPS as f32;
+        let (mut rayleigh, mut mie) = (0.0, 0.0);
+        for i in 0..TRANSMITTANCE_STEPS {
+            let height = (origin + direction * (i as f32 + 0.5) * step).length() - PLANET_RADIUS;
+            rayleigh += (-height / RAYLEIGH_HEIGHT).exp() * step;
+            mie += (-height / MIE_HEIGHT).exp() * step;
+        }
+        let tau = RAYLEIGH_SCATTERING * rayleigh
+            + Vec3::splat(self.mie_scattering() * MIE_EXTINCTION * mie);
+        Vec3::new((-tau.x).exp(), (-tau.y).exp(), (-tau.z).exp())
+    }
+
+    // 用当前时间设置平行光：白天是穿过大气后的阳光，夜晚是反方向的月光
+    // 光源的种类和是否投射阴影保持不变
+    pub fn drive_light(&self, light: &mut Light) {
+        let sun = self.sun_direction();
+        if sun.y >= 0.0 {
+            light.direction = -sun;
+            light.color = self.transmittance(sun);
+            // 贴近地平线时透射率还不够小，再淡出一点避免日落瞬间跳变
+            light.intensity = self.light_intensity * smoothstep(0.0, 0.05, sun.y);
+        } else {
+            let moon = -sun;
+            light.direction = -moon;
+            light.color = MOONLIGHT_COLOR;
+            light.intensity =
+                self.light_intensity * MOONLIGHT_SCALE * smoothstep(0.0, 0.05, moon.y);
+        }
+    }
+
+    fn to_uniform(self) -> SkyUniform {
+        SkyUniform {
+            sun_direction: self.sun_direction().to_array(),
+            sun_intensity: self.sun_intensity,
+            rayleigh: RAYLEIGH_SCATTERING.to_array(),
+            mie_g: self.mie_g,
+            mie: self.mie_scattering(),
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// 射线和以原点为球心的球的两个交点距离，和着色器里的 ray_sphere 相同
+fn ray_sphere(origin: Vec3, direction: Vec3, radius: f32) -> Option<(f32, f32)> {
+    let b = origin.dot(direction);
+    let len = origin.length();
+    let c = (len - radius) * (len + radius);
+    let discriminant = b * b - c;
+    (discriminant >= 0.0).then(|| {
+        let s = discriminant.sqrt();
+        (-b - s, -b + s)
+    })
+}
+
+// 和着色器里的 SkyUniform 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyUniform {
+    sun_direction: [f32; 3],
+    sun_intensity: f32,
+    rayleigh: [f32; 3],
+    mie_g: f32,
+    mie: f32,
+    _padding: [f32; 3],
+}
+
+pub struct ProceduralSky {
+    settings: SkySettings,
+    // 关闭时不绘制，也不驱动平行光
+    enabled: bool,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: Pipeline,
+}
+
+impl ProceduralSky {
+    pub fn new(
+        device: &wgpu::Device,
+        targets: &TargetState,
+        camera_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let settings = SkySettings::default();
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sky Uniform Buffer"),
+            contents: bytemuck::bytes_of(&settings.to_uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let layout = BindingLayout::new(
+            device,
+            "Sky Bind Group Layout",
+            &[(wgpu::ShaderStages::FRAGMENT, BindingKind::Uniform)],
+        );
+        let bind_group = layout.create_bind_group(device, &[buffer.as_entire_binding()]);
+        let mut desc = PipelineDescriptor::new(
+            "Sky Pipeline",
+            Shader::from_wgsl("sky.wgsl", include_str!("shaders/sky.wgsl")),
+        );
+        desc.vertex_layouts.clear();
+        desc.bind_group_layouts = vec![camera_layout.clone(), layout.layout.clone()];
+        desc.cull_mode = None;
+        desc.depth_write_enabled = false;
+        depth::use_far_plane(&mut desc, targets);
+        Self {
+            settings,
+            enabled: false,
+            buffer,
+            bind_group,
+            pipeline: Pipeline::new(device, desc, targets),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn settings(&self) -> SkySettings {
+        self.settings
+    }
+
+    // None 关闭程序化天空，Some 开启并更新参数
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: Option<SkySettings>) {
+        self.enabled = settings.is_some();
+        if let Some(settings) = settings {
+            self.settings = settings;
+            queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&settings.to_uniform()));
+        }
+    }
+
+    pub fn set_targets(&mut self, device: &wgpu::Device, targets: &TargetState) {
+        depth::use_far_plane(&mut self.pipeline.desc, targets);
+        self.pipeline.rebuild(device, targets);
+    }
+
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>, camera_bind_group: &wgpu::BindGroup) {
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        pass.set_bind_group(1, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+// 找到场景里的第一个平行光，给程序化天空驱动
+pub fn first_directional(lights: &Lights) -> Option<LightId> {
+    lights
+        .iter()
+        .find(|(_, light)| light.kind == LightKind::Directional)
+        .map(|(id, _)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_layout_matches_wgsl() {
+        assert_eq!(std::mem::size_of::<SkyUniform>(), 48);
+    }
+
+    #[test]
+    fn sun_rises_east_and_peaks_at_noon() {
+        let settings = |time_of_day| SkySettings {
+            time_of_day,
+            sun_tilt: 0.0,
+            ..Default::default()
+        };
+        assert!(settings(6.0).sun_direction().abs_diff_eq(Vec3::X, 1e-5));
+        assert!(settings(12.0).sun_direction().abs_diff_eq(Vec3::Y, 1e-5));
+        assert!(
+            settings(18.0)
+                .sun_direction()
+                .abs_diff_eq(Vec3::NEG_X, 1e-5)
+        );
+        assert!(settings(0.0).sun_direction().y < -0.99);
+    }
+
+    #[test]
+    fn sunset_light_is_redder_than_noon() {
+        let sky = SkySettings::default();
+        let noon = sky.transmittance(Vec3::Y);
+        // 蓝光被散射得最多
+        assert!(noon.x > noon.y && noon.y > noon.z && noon.z > 0.5);
+        let sunset = sky.transmittance(Vec3::new(1.0, 0.03, 0.0).normalize());
+        assert!(sunset.x > sunset.z * 4.0);
+        assert!(sunset.x < noon.x);
+        assert_eq!(sky.transmittance(Vec3::NEG_Y), Vec3::ZERO);
+    }
+
+    #[test]
+    fn night_switches_to_dim_moonlight() {
+        let mut light = Light::directional(Vec3::NEG_Y, Vec3::ONE, 1.0);
+        let sky = SkySettings {
+            time_of_day: 12.0,
+            ..Default::default()
+        };
+        sky.drive_light(&mut light);
+        assert!((light.intensity - sky.light_intensity).abs() < 1e-5);
+        assert!(light.direction.y < 0.0);
+
+        SkySettings {
+            time_of_day: 0.0,
+            ..sky
+        }
+        .drive_light(&mut light);
+        assert_eq!(light.color, MOONLIGHT_COLOR);
+        assert!(light.direction.y < 0.0);
+        assert!(light.intensity < sky.light_intensity * 0.1);
+    }
+}
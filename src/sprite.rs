@@ -0,0 +1,400 @@
+// 2D 精灵批处理
+// 给工具界面和叠加层使用：每帧 queue() 要画的精灵，每个精灵有位置、旋转、缩放、着色、纹理坐标矩形和层级。
+// prepare() 按层级排序，同一层里再按纹理排序，相邻的、纹理相同的精灵合并成一次实例化绘制，
+// 所以同一层里不同纹理的精灵之间没有先后顺序，需要固定遮挡关系时放到不同的层。
+// 正交相机把以左上角为原点、y 轴向下的像素坐标映射到窗口的物理尺寸，精灵画在色调映射之后的展示平面上。
+
+use std::ops::Range;
+use std::path::Path;
+
+use glam::{Mat4, Vec2};
+
+use crate::pipeline::{Pipeline, PipelineDescriptor, Shader, TargetState};
+use crate::texture::{BindingKind, BindingLayout, ColorSpace, Texture, TextureError};
+
+// 添加到精灵批处理里的纹理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SpriteTextureId(pub usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    pub texture: SpriteTextureId,
+    // 中心的像素坐标
+    pub position: Vec2,
+    // 弧度，屏幕上顺时针为正
+    pub rotation: f32,
+    // 相对纹理坐标矩形对应的像素尺寸的缩放
+    pub scale: Vec2,
+    // sRGB 颜色，和纹理颜色相乘，alpha 是不透明度
+    pub tint: [f32; 4],
+    // (u0, v0, u1, v1)：左上角和右下角的纹理坐标，交换可以翻转
+    pub uv_rect: [f32; 4],
+    // 层级大的画在上面
+    pub layer: i32,
+}
+
+impl Sprite {
+    pub fn new(texture: SpriteTextureId, position: Vec2) -> Self {
+        Self {
+            texture,
+            position,
+            rotation: 0.0,
+            scale: Vec2::ONE,
+            tint: [1.0; 4],
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+            layer: 0,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_uv_rect(mut self, uv_rect: [f32; 4]) -> Self {
+        self.uv_rect = uv_rect;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+// 2D 正交相机，position 是视野左上角的像素坐标，zoom 大于 1 时放大
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteCamera {
+    pub position: Vec2,
+    pub zoom: f32,
+}
+
+impl Default for SpriteCamera {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl SpriteCamera {
+    // size 是窗口的物理尺寸，默认设置下一个单位就是一个物理像素
+    pub fn view_projection(&self, size: Vec2) -> Mat4 {
+        let extent = size / self.zoom;
+        Mat4::orthographic_rh(
+            self.position.x,
+            self.position.x + extent.x,
+            self.position.y + extent.y,
+            self.position.y,
+            -1.0,
+            1.0,
+        )
+    }
+}
+
+// 和 sprite.wgsl 里的 InstanceInput 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpriteInstance {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub uv_rect: [f32; 4],
+    pub tint: [f32; 4],
+    pub rotation: f32,
+}
+
+impl SpriteInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x4,
+        3 => Float32x4,
+        4 => Float32,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// 一次绘制：同一张纹理的一段连续实例
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteDraw {
+    pub texture: SpriteTextureId,
+    pub instances: Range<u32>,
+}
+
+// 按层级和纹理排序，把相邻的同纹理精灵合并成一次绘制；排序是稳定的，同一层同一纹理保持提交顺序
+pub fn build_draws(sprites: &mut [Sprite]) -> Vec<SpriteDraw> {
+    sprites.sort_by_key(|sprite| (sprite.layer, sprite.texture));
+    let mut draws: Vec<SpriteDraw> = Vec::new();
+    for (i, sprite) in sprites.iter().enumerate() {
+        let i = i as u32;
+        match draws.last_mut() {
+            Some(draw) if draw.texture == sprite.texture => draw.instances.end = i + 1,
+            _ => draws.push(SpriteDraw {
+                texture: sprite.texture,
+                instances: i..i + 1,
+            }),
+        }
+    }
+    draws
+}
+
+// 和 sprite.wgsl 里的 SpriteUniform 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpriteUniform {
+    view_projection: [[f32; 4]; 4],
+    decode_srgb: u32,
+    _padding: [u32; 3],
+}
+
+struct SpriteTexture {
+    size: Vec2,
+    bind_group: wgpu::BindGroup,
+}
+
+pub struct SpriteBatch {
+    pub camera: SpriteCamera,
+    // 这一帧排队等待绘制的精灵
+    sprites: Vec<Sprite>,
+    textures: Vec<SpriteTexture>,
+    texture_layout: BindingLayout,
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    output_format: wgpu::TextureFormat,
+    pipeline: Pipeline,
+    instance_buffer: wgpu::Buffer,
+    draws: Vec<SpriteDraw>,
+}
+
+impl SpriteBatch {
+    // output_format 是展示平面（或离屏目标）的格式，精灵直接画在上面
+    pub fn new(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Self {
+        let layout = BindingLayout::new(
+            device,
+            "Sprite Bind Group Layout",
+            &[(wgpu::ShaderStages::VERTEX_FRAGMENT, BindingKind::Uniform)],
+        );
+        let texture_layout = BindingLayout::texture_sampler(device);
+        let mut desc = PipelineDescriptor::new(
+            "Sprite Pipeline",
+            Shader::from_wgsl("sprite.wgsl", include_str!("shaders/sprite.wgsl")),
+        );
+        desc.vertex_layouts = vec![SpriteInstance::desc()];
+        desc.bind_group_layouts = vec![layout.layout.clone(), texture_layout.layout.clone()];
+        desc.cull_mode = None;
+        desc.blend = Some(wgpu::BlendState::ALPHA_BLENDING);
+        let targets = TargetState {
+            color_format: output_format,
+            depth_format: None,
+            depth_compare: wgpu::CompareFunction::Always,
+            sample_count: 1,
+        };
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Uniform Buffer"),
+            size: std::mem::size_of::<SpriteUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = layout.create_bind_group(device, &[uniform.as_entire_binding()]);
+        Self {
+            camera: SpriteCamera::default(),
+            sprites: Vec::new(),
+            textures: Vec::new(),
+            texture_layout,
+            uniform,
+            bind_group,
+            output_format,
+            pipeline: Pipeline::new(device, desc, &targets),
+            instance_buffer: Self::create_instance_buffer(device, 0),
+            draws: Vec::new(),
+        }
+    }
+
+    // 容量至少一个精灵，避免创建大小为 0 的缓冲区
+    fn create_instance_buffer(device: &wgpu::Device, instances: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Instance Buffer"),
+            size: (instances.max(1) * std::mem::size_of::<SpriteInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // 添加一张纹理，精灵的像素尺寸按纹理尺寸计算；
+    // 颜色贴图应该用 ColorSpace::matching(output_format) 创建，显示的颜色才和原图一致
+    pub fn add_texture(&mut self, device: &wgpu::Device, texture: &Texture) -> SpriteTextureId {
+        let size = texture.texture.size();
+        self.textures.push(SpriteTexture {
+            size: Vec2::new(size.width as f32, size.height as f32),
+            bind_group: texture.bind_group(device, &self.texture_layout),
+        });
+        SpriteTextureId(self.textures.len() - 1)
+    }
+
+    pub fn load_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+    ) -> Result<SpriteTextureId, TextureError> {
+        let color_space = ColorSpace::matching(self.output_format);
+        let texture = Texture::from_path(device, queue, path, color_space)?;
+        Ok(self.add_texture(device, &texture))
+    }
+
+    // 排队一个精灵，下一次 render() 画出来之后清空
+    pub fn queue(&mut self, sprite: Sprite) {
+        self.sprites.push(sprite);
+    }
+
+    // 最近一次 prepare() 合并出的绘制次数
+    pub fn draw_count(&self) -> usize {
+        self.draws.len()
+    }
+
+    // 排序、合并这一帧的精灵并上传实例数据，width 和 height 是窗口的物理尺寸
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, width: u32, height: u32) {
+        let mut sprites = std::mem::take(&mut self.sprites);
+        self.draws = build_draws(&mut sprites);
+        let instances: Vec<SpriteInstance> = sprites
+            .iter()
+            .map(|sprite| {
+                let [u0, v0, u1, v1] = sprite.uv_rect;
+                let uv_size = Vec2::new(u1 - u0, v1 - v0).abs();
+                let size = self.textures[sprite.texture.0].size * uv_size * sprite.scale;
+                SpriteInstance {
+                    position: sprite.position.to_array(),
+                    size: size.to_array(),
+                    uv_rect: sprite.uv_rect,
+                    tint: sprite.tint,
+                    rotation: sprite.rotation,
+                }
+            })
+            .collect();
+
+        let uniform = SpriteUniform {
+            view_projection: self
+                .camera
+                .view_projection(Vec2::new(width as f32, height as f32))
+                .to_cols_array_2d(),
+            decode_srgb: self.output_format.is_srgb() as u32,
+            _padding: [0; 3],
+        };
+        queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(&uniform));
+        let capacity = self.instance_buffer.size() as usize / std::mem::size_of::<SpriteInstance>();
+        if instances.len() > capacity {
+            self.instance_buffer =
+                Self::create_instance_buffer(device, instances.len().next_power_of_two());
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        // 保留容量，下一帧排队时不用重新分配
+        sprites.clear();
+        self.sprites = sprites;
+    }
+
+    // 在 output 上画出 prepare() 准备好的精灵，没有精灵时不开启渲染通道
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        if self.draws.is_empty() {
+            return;
+        }
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Sprite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        for draw in &self.draws {
+            pass.set_bind_group(1, &self.textures[draw.texture.0].bind_group, &[]);
+            pass.draw(0..6, draw.instances.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec4;
+
+    #[test]
+    fn draws_are_sorted_by_layer_and_merged_by_texture() {
+        let a = SpriteTextureId(0);
+        let b = SpriteTextureId(1);
+        let mut sprites = vec![
+            Sprite::new(a, Vec2::ZERO).with_layer(1),
+            Sprite::new(b, Vec2::ZERO),
+            Sprite::new(a, Vec2::ZERO),
+            Sprite::new(b, Vec2::ONE),
+            Sprite::new(b, Vec2::ZERO).with_layer(1),
+            Sprite::new(a, Vec2::ONE).with_layer(-1),
+        ];
+        let draws = build_draws(&mut sprites);
+        let textures: Vec<usize> = draws.iter().map(|d| d.texture.0).collect();
+        // 第 -1 层的 a 和第 0 层的 a 相邻，合并成一次绘制；然后是第 0 层的 b，第 1 层的 a、b
+        assert_eq!(textures, [0, 1, 0, 1]);
+        assert_eq!(draws[0].instances, 0..2);
+        assert_eq!(draws[1].instances, 2..4);
+        assert_eq!(sprites[0].layer, -1);
+        // 同一层同一纹理保持提交顺序
+        assert_eq!(sprites[2].position, Vec2::ZERO);
+        assert_eq!(sprites[3].position, Vec2::ONE);
+    }
+
+    #[test]
+    fn camera_maps_pixels_to_clip_space() {
+        let size = Vec2::new(200.0, 100.0);
+        let camera = SpriteCamera::default();
+        let top_left = camera.view_projection(size) * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        let bottom_right = camera.view_projection(size) * Vec4::new(200.0, 100.0, 0.0, 1.0);
+        assert!(
+            top_left
+                .truncate()
+                .abs_diff_eq(glam::Vec3::new(-1.0, 1.0, 0.5), 1e-5)
+        );
+        assert!(
+            bottom_right
+                .truncate()
+                .abs_diff_eq(glam::Vec3::new(1.0, -1.0, 0.5), 1e-5)
+        );
+
+        // 放大两倍后视野只有一半，从 position 开始
+        let zoomed = SpriteCamera {
+            position: Vec2::new(50.0, 25.0),
+            zoom: 2.0,
+        };
+        let corner = zoomed.view_projection(size) * Vec4::new(150.0, 75.0, 0.0, 1.0);
+        assert!(
+            corner
+                .truncate()
+                .truncate()
+                .abs_diff_eq(Vec2::new(1.0, -1.0), 1e-5)
+        );
+    }
+}
@@ -0,0 +1,747 @@
+// 文字渲染
+// 用 ab_glyph 解析 TrueType/OpenType 字体，字形按需光栅化到一张 R8 的图集纹理里，
+// 图集用货架（shelf）算法动态装箱：一行一行地放，放不下时先清空旧字形，仍然放不下再把图集扩大一倍。
+// 文字是立即模式的：每帧 queue() 要显示的文字，render() 里把所有文字排版成一个顶点缓冲区，一次绘制画完。
+// 字体可以有多个，按添加顺序查找，默认字体里没有的字符（比如中文）会用后面的字体显示。
+// 默认的字体链是：内嵌的等宽字体、系统里找到的中文字体（可选）、内嵌的中文子集字体。
+// 文字画在色调映射之后的展示平面上，不受曝光和后处理影响，坐标是以左上角为原点的像素。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{Font, FontArc, GlyphId, ScaleFont};
+use glam::Vec2;
+
+use crate::pipeline::{Pipeline, PipelineDescriptor, Shader, TargetState};
+use crate::texture::{BindingKind, BindingLayout};
+
+// 内嵌的默认字体，等宽字体适合显示调试信息和着色器错误
+pub const DEFAULT_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono.ttf");
+// 内嵌的中文后备字体，只有窗口标题和演示里用到的几个汉字，找不到系统中文字体时保证它们能显示
+pub const FALLBACK_CJK_FONT: &[u8] = include_bytes!("../assets/fonts/CjkSubset.ttf");
+// 常见系统上的中文字体，按顺序找第一个存在的；.ttc 字体集合用第一个字体
+const SYSTEM_CJK_FONTS: &[&str] = &[
+    // Linux
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-zenhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    // macOS
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    // Windows
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\simsun.ttc",
+];
+// 图集的初始边长和最大边长
+const INITIAL_ATLAS_SIZE: u32 = 512;
+const MAX_ATLAS_SIZE: u32 = 4096;
+// 字形之间留 1 像素的空隙，线性过滤时不会采样到相邻的字形
+const GLYPH_PADDING: u32 = 1;
+
+#[derive(Debug)]
+pub enum TextError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    InvalidFont {
+        path: PathBuf,
+    },
+}
+
+impl std::fmt::Display for TextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextError::Io { path, error } => {
+                write!(f, "读取字体文件 {} 失败: {error}", path.display())
+            }
+            TextError::InvalidFont { path } => {
+                write!(f, "{} 不是有效的 TrueType/OpenType 字体", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+// 水平对齐方式，决定 TextSection::position.x 是每一行的左端、中点还是右端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+// 一段要显示的文字，可以用 \n 换行
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSection {
+    pub text: String,
+    // 第一行顶部的像素坐标，x 的含义由 align 决定
+    pub position: Vec2,
+    // 字号，单位是像素
+    pub size: f32,
+    // sRGB 颜色，alpha 是不透明度
+    pub color: [f32; 4],
+    pub align: TextAlign,
+}
+
+impl TextSection {
+    pub fn new(text: impl Into<String>, position: Vec2) -> Self {
+        Self {
+            text: text.into(),
+            position,
+            size: 16.0,
+            color: [1.0; 4],
+            align: TextAlign::Left,
+        }
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+}
+
+// 排版结果里的一个字形：用哪个字体、哪个字形，基线原点在哪里
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub font: usize,
+    pub glyph: GlyphId,
+    // 基线上的原点，已经取整到像素，字形不会因为小数坐标变模糊
+    pub origin: Vec2,
+}
+
+// 默认的字体链：等宽字体在前决定行高和基线，中文子集字体在最后兜底
+pub fn default_fonts() -> Vec<FontArc> {
+    vec![
+        FontArc::try_from_slice(DEFAULT_FONT).expect("内嵌的默认字体无效"),
+        FontArc::try_from_slice(FALLBACK_CJK_FONT).expect("内嵌的中文字体无效"),
+    ]
+}
+
+// 在常见位置查找系统的中文字体，返回第一个能解析的字体
+pub fn find_system_cjk_font() -> Option<(PathBuf, FontArc)> {
+    SYSTEM_CJK_FONTS.iter().map(PathBuf::from).find_map(|path| {
+        let data = std::fs::read(&path).ok()?;
+        let font = FontArc::try_from_vec(data).ok()?;
+        Some((path, font))
+    })
+}
+
+// 按字体顺序找第一个包含这个字符的字体，都没有时用第一个字体的缺字符号
+fn find_glyph(fonts: &[FontArc], c: char) -> (usize, GlyphId) {
+    fonts
+        .iter()
+        .enumerate()
+        .map(|(i, font)| (i, font.glyph_id(c)))
+        .find(|(_, id)| id.0 != 0)
+        .unwrap_or((0, GlyphId(0)))
+}
+
+// 排版一段文字，返回字形和整段文字的宽高；行高和基线由第一个字体决定
+pub fn layout(fonts: &[FontArc], section: &TextSection) -> (Vec<PositionedGlyph>, Vec2) {
+    let primary = fonts[0].as_scaled(section.size);
+    let line_height = primary.height() + primary.line_gap();
+    let mut glyphs = Vec::new();
+    let mut width: f32 = 0.0;
+    let mut line_count = 0;
+    for (row, line) in section.text.split('\n').enumerate() {
+        line_count += 1;
+        let line_start = glyphs.len();
+        let mut x = 0.0;
+        let mut previous: Option<(usize, GlyphId)> = None;
+        for c in line.chars().filter(|c| !c.is_control()) {
+            let (font, glyph) = find_glyph(fonts, c);
+            let scaled = fonts[font].as_scaled(section.size);
+            if let Some((previous_font, previous_glyph)) = previous
+                && previous_font == font
+            {
+                x += scaled.kern(previous_glyph, glyph);
+            }
+            glyphs.push(PositionedGlyph {
+                font,
+                glyph,
+                origin: Vec2::new(x, row as f32 * line_height + primary.ascent()),
+            });
+            x += scaled.h_advance(glyph);
+            previous = Some((font, glyph));
+        }
+        width = width.max(x);
+        let offset = match section.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => -x / 2.0,
+            TextAlign::Right => -x,
+        };
+        for glyph in &mut glyphs[line_start..] {
+            glyph.origin = (section.position + glyph.origin + Vec2::new(offset, 0.0)).round();
+        }
+    }
+    (glyphs, Vec2::new(width, line_count as f32 * line_height))
+}
+
+// 货架装箱：矩形按行摆放，每一行的高度由第一个放进去的矩形决定
+#[derive(Debug, Clone)]
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    // 每一行的 (y, 高度, 已经用到的 x)
+    shelves: Vec<(u32, u32, u32)>,
+}
+
+impl ShelfPacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            shelves: Vec::new(),
+        }
+    }
+
+    // 找一个放得下的位置，返回左上角坐标，图集满了返回 None
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<[u32; 2]> {
+        if width > self.width {
+            return None;
+        }
+        // 优先放进高度最接近的行，浪费的空间最少
+        let best = self
+            .shelves
+            .iter_mut()
+            .filter(|(_, h, x)| *h >= height && self.width - *x >= width)
+            .min_by_key(|(_, h, _)| *h);
+        if let Some((y, _, x)) = best {
+            let position = [*x, *y];
+            *x += width;
+            return Some(position);
+        }
+        let y = self.shelves.last().map_or(0, |&(y, h, _)| y + h);
+        if y + height > self.height {
+            return None;
+        }
+        self.shelves.push((y, height, width));
+        Some([0, y])
+    }
+
+    pub fn clear(&mut self) {
+        self.shelves.clear();
+    }
+}
+
+// 字形在图集里的位置
+#[derive(Debug, Clone, Copy)]
+struct AtlasGlyph {
+    position: [u32; 2],
+    size: [u32; 2],
+    // 字形包围盒的左上角相对基线原点的偏移
+    offset: Vec2,
+}
+
+// 缓存的键：字体、字形和字号，字号用位模式比较
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: usize,
+    glyph: GlyphId,
+    size: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl TextVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TextVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// 和 text.wgsl 里的 TextUniform 对应
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextUniform {
+    screen_size: [f32; 2],
+    decode_srgb: u32,
+    _padding: u32,
+}
+
+pub struct TextRenderer {
+    fonts: Vec<FontArc>,
+    // 这一帧排队等待绘制的文字
+    sections: Vec<TextSection>,
+    // None 表示字形没有轮廓（比如空格），不需要画
+    cache: HashMap<GlyphKey, Option<AtlasGlyph>>,
+    packer: ShelfPacker,
+    atlas_size: u32,
+    // 图集最大的边长，测试里调小来模拟图集放满
+    max_atlas_size: u32,
+    atlas: wgpu::Texture,
+    sampler: wgpu::Sampler,
+    layout: BindingLayout,
+    bind_group: wgpu::BindGroup,
+    uniform: wgpu::Buffer,
+    output_format: wgpu::TextureFormat,
+    pipeline: Pipeline,
+    vertex_buffer: wgpu::Buffer,
+    vertex_count: u32,
+}
+
+impl TextRenderer {
+    // output_format 是展示平面（或离屏目标）的格式，文字直接画在上面
+    pub fn new(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Self {
+        let layout = BindingLayout::new(
+            device,
+            "Text Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::TEXTURE_2D),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::FILTERING_SAMPLER),
+                (wgpu::ShaderStages::VERTEX_FRAGMENT, BindingKind::Uniform),
+            ],
+        );
+        let mut desc = PipelineDescriptor::new(
+            "Text Pipeline",
+            Shader::from_wgsl("text.wgsl", include_str!("shaders/text.wgsl")),
+        );
+        desc.vertex_layouts = vec![TextVertex::desc()];
+        desc.bind_group_layouts = vec![layout.layout.clone()];
+        desc.cull_mode = None;
+        desc.blend = Some(wgpu::BlendState::ALPHA_BLENDING);
+        let targets = TargetState {
+            color_format: output_format,
+            depth_format: None,
+            depth_compare: wgpu::CompareFunction::Always,
+            sample_count: 1,
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Text Atlas Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Uniform Buffer"),
+            size: std::mem::size_of::<TextUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let atlas = Self::create_atlas(device, INITIAL_ATLAS_SIZE);
+        let bind_group = Self::create_bind_group(device, &layout, &atlas, &sampler, &uniform);
+        Self {
+            fonts: default_fonts(),
+            sections: Vec::new(),
+            cache: HashMap::new(),
+            packer: ShelfPacker::new(INITIAL_ATLAS_SIZE, INITIAL_ATLAS_SIZE),
+            atlas_size: INITIAL_ATLAS_SIZE,
+            max_atlas_size: MAX_ATLAS_SIZE,
+            atlas,
+            sampler,
+            layout,
+            bind_group,
+            uniform,
+            output_format,
+            pipeline: Pipeline::new(device, desc, &targets),
+            vertex_buffer: Self::create_vertex_buffer(device, 0),
+            vertex_count: 0,
+        }
+    }
+
+    fn create_atlas(device: &wgpu::Device, size: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &BindingLayout,
+        atlas: &wgpu::Texture,
+        sampler: &wgpu::Sampler,
+        uniform: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        let view = atlas.create_view(&wgpu::TextureViewDescriptor::default());
+        layout.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(&view),
+                wgpu::BindingResource::Sampler(sampler),
+                uniform.as_entire_binding(),
+            ],
+        )
+    }
+
+    // 容量至少一个字形，避免创建大小为 0 的缓冲区
+    fn create_vertex_buffer(device: &wgpu::Device, vertices: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Vertex Buffer"),
+            size: (vertices.max(6) * std::mem::size_of::<TextVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // 添加一个后备字体，返回它在查找顺序里的位置。
+    // 内嵌的中文子集字体始终排在最后兜底，新字体插在它前面，已经缓存的子集字形要清空
+    pub fn add_font(&mut self, data: Vec<u8>) -> Result<usize, ab_glyph::InvalidFont> {
+        let index = self.fonts.len() - 1;
+        self.fonts.insert(index, FontArc::try_from_vec(data)?);
+        self.cache.clear();
+        self.packer.clear();
+        Ok(index)
+    }
+
+    // 把系统的中文字体插到默认字体后面，字形比内嵌的子集字体全；没有找到时返回 false。
+    // 插入会改变后面字体的序号，已经缓存的字形要清空
+    pub fn add_system_cjk_font(&mut self) -> bool {
+        let Some((path, font)) = find_system_cjk_font() else {
+            log::info!("没有找到系统中文字体，只能显示内嵌子集里的汉字");
+            return false;
+        };
+        log::info!("使用系统中文字体 {}", path.display());
+        self.fonts.insert(1, font);
+        self.cache.clear();
+        self.packer.clear();
+        true
+    }
+
+    pub fn load_font(&mut self, path: impl AsRef<Path>) -> Result<usize, TextError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|error| TextError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        self.add_font(data).map_err(|_| TextError::InvalidFont {
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn fonts(&self) -> &[FontArc] {
+        &self.fonts
+    }
+
+    // 排队一段文字，下一次 render() 画出来之后清空
+    pub fn queue(&mut self, section: TextSection) {
+        self.sections.push(section);
+    }
+
+    // 一段文字排版后的宽高，用来在绘制之前计算位置
+    pub fn measure(&self, section: &TextSection) -> Vec2 {
+        layout(&self.fonts, section).1
+    }
+
+    // 排版这一帧的所有文字，光栅化新的字形并上传顶点
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, width: u32, height: u32) {
+        let sections = std::mem::take(&mut self.sections);
+        let mut vertices = Vec::new();
+        // 第一次放不下时清掉之前帧留下的字形，第二次还放不下说明这一帧的字形本身就太多，扩大图集；
+        // 图集已经最大时只跳过放不下的字形，其余的文字照常显示
+        let mut cleared = false;
+        loop {
+            let missing = self.build_vertices(queue, &sections, &mut vertices);
+            if missing == 0 {
+                break;
+            }
+            if !cleared {
+                cleared = true;
+            } else if self.atlas_size < self.max_atlas_size {
+                self.atlas_size *= 2;
+                self.atlas = Self::create_atlas(device, self.atlas_size);
+                self.bind_group = Self::create_bind_group(
+                    device,
+                    &self.layout,
+                    &self.atlas,
+                    &self.sampler,
+                    &self.uniform,
+                );
+                log::info!("字形图集扩大到 {0}×{0}", self.atlas_size);
+            } else {
+                log::warn!("字形图集已满，这一帧有 {missing} 个字形没有画出来");
+                break;
+            }
+            vertices.clear();
+            self.cache.clear();
+            self.packer = ShelfPacker::new(self.atlas_size, self.atlas_size);
+        }
+
+        let uniform = TextUniform {
+            screen_size: [width as f32, height as f32],
+            decode_srgb: self.output_format.is_srgb() as u32,
+            _padding: 0,
+        };
+        queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(&uniform));
+        let capacity = self.vertex_buffer.size() as usize / std::mem::size_of::<TextVertex>();
+        if vertices.len() > capacity {
+            self.vertex_buffer =
+                Self::create_vertex_buffer(device, vertices.len().next_power_of_two());
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    // 返回图集放不下、跳过了的字形个数
+    fn build_vertices(
+        &mut self,
+        queue: &wgpu::Queue,
+        sections: &[TextSection],
+        vertices: &mut Vec<TextVertex>,
+    ) -> usize {
+        let mut missing = 0;
+        let atlas_size = self.atlas_size as f32;
+        for section in sections {
+            let (glyphs, _) = layout(&self.fonts, section);
+            for glyph in glyphs {
+                let key = GlyphKey {
+                    font: glyph.font,
+                    glyph: glyph.glyph,
+                    size: section.size.to_bits(),
+                };
+                let entry = match self.cache.get(&key) {
+                    Some(entry) => *entry,
+                    None => match self.rasterize(queue, key, section.size) {
+                        Some(entry) => {
+                            self.cache.insert(key, entry);
+                            entry
+                        }
+                        None => {
+                            missing += 1;
+                            continue;
+                        }
+                    },
+                };
+                let Some(atlas_glyph) = entry else {
+                    continue;
+                };
+                let min = glyph.origin + atlas_glyph.offset;
+                let max = min + Vec2::new(atlas_glyph.size[0] as f32, atlas_glyph.size[1] as f32);
+                let uv_min = Vec2::new(
+                    atlas_glyph.position[0] as f32,
+                    atlas_glyph.position[1] as f32,
+                ) / atlas_size;
+                let uv_max = uv_min
+                    + Vec2::new(atlas_glyph.size[0] as f32, atlas_glyph.size[1] as f32)
+                        / atlas_size;
+                let corner = |x: f32, y: f32, u: f32, v: f32| TextVertex {
+                    position: [x, y],
+                    uv: [u, v],
+                    color: section.color,
+                };
+                let top_left = corner(min.x, min.y, uv_min.x, uv_min.y);
+                let top_right = corner(max.x, min.y, uv_max.x, uv_min.y);
+                let bottom_left = corner(min.x, max.y, uv_min.x, uv_max.y);
+                let bottom_right = corner(max.x, max.y, uv_max.x, uv_max.y);
+                vertices.extend([
+                    top_left,
+                    bottom_left,
+                    bottom_right,
+                    top_left,
+                    bottom_right,
+                    top_right,
+                ]);
+            }
+        }
+        missing
+    }
+
+    // 光栅化一个字形并上传到图集，图集满了返回 None
+    fn rasterize(
+        &mut self,
+        queue: &wgpu::Queue,
+        key: GlyphKey,
+        size: f32,
+    ) -> Option<Option<AtlasGlyph>> {
+        let glyph = key.glyph.with_scale(size);
+        let Some(outlined) = self.fonts[key.font].outline_glyph(glyph) else {
+            return Some(None);
+        };
+        let bounds = outlined.px_bounds();
+        let width = bounds.width() as u32;
+        let height = bounds.height() as u32;
+        if width == 0 || height == 0 {
+            return Some(None);
+        }
+        let position = self
+            .packer
+            .allocate(width + GLYPH_PADDING, height + GLYPH_PADDING)?;
+        let mut coverage = vec![0u8; (width * height) as usize];
+        outlined.draw(|x, y, c| {
+            if x < width && y < height {
+                coverage[(y * width + x) as usize] = (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        });
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.atlas,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: position[0],
+                    y: position[1],
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &coverage,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        Some(Some(AtlasGlyph {
+            position,
+            size: [width, height],
+            offset: Vec2::new(bounds.min.x, bounds.min.y),
+        }))
+    }
+
+    // 在 output 上画出 prepare() 准备好的所有文字，没有文字时不开启渲染通道
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Text Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        pass.set_pipeline(&self.pipeline.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
+
+    #[test]
+    fn shelf_packer_fills_rows_then_opens_new_ones() {
+        let mut packer = ShelfPacker::new(32, 32);
+        assert_eq!(packer.allocate(20, 10), Some([0, 0]));
+        assert_eq!(packer.allocate(10, 8), Some([20, 0]));
+        // 第一行剩下 2 像素，放不下
+        assert_eq!(packer.allocate(10, 10), Some([0, 10]));
+        assert_eq!(packer.allocate(40, 1), None);
+        assert_eq!(packer.allocate(10, 13), None);
+        assert_eq!(packer.allocate(10, 12), Some([0, 20]));
+        packer.clear();
+        assert_eq!(packer.allocate(32, 32), Some([0, 0]));
+    }
+
+    #[test]
+    fn layout_advances_wraps_and_aligns() {
+        let fonts = default_fonts();
+        let section = TextSection::new("ab\ncd", Vec2::new(100.0, 10.0)).with_size(20.0);
+        let (glyphs, size) = layout(&fonts, &section);
+        assert_eq!(glyphs.len(), 4);
+        // 等宽字体：同一行的字形间距相同，第二行从同一个 x 开始、向下移动一个行高
+        let advance = glyphs[1].origin.x - glyphs[0].origin.x;
+        assert!(advance > 0.0);
+        assert_eq!(glyphs[2].origin.x, glyphs[0].origin.x);
+        assert!(glyphs[2].origin.y > glyphs[0].origin.y);
+        assert_eq!(glyphs[0].origin.x, 100.0);
+        assert!((size.x - 2.0 * advance).abs() <= 1.0);
+
+        let right = layout(&fonts, &section.clone().with_align(TextAlign::Right)).0;
+        assert!((right[1].origin.x + advance - 100.0).abs() <= 1.0);
+        let center = layout(&fonts, &section.with_align(TextAlign::Center)).0;
+        assert!((center[0].origin.x + advance - 100.0).abs() <= 1.0);
+    }
+
+    #[test]
+    fn missing_characters_fall_back_to_later_fonts() {
+        let mut fonts = vec![FontArc::try_from_slice(DEFAULT_FONT).unwrap()];
+        // 等宽字体里没有汉字，显示缺字符号但仍然占位
+        let section = TextSection::new("中文 A", Vec2::ZERO);
+        let (glyphs, _) = layout(&fonts, &section);
+        assert_eq!(glyphs.len(), 4);
+        assert_eq!(glyphs[0].glyph, GlyphId(0));
+        assert_ne!(glyphs[3].glyph, GlyphId(0));
+        // 后面添加的字体里有的字符用后面的字体，两个字体都有的字符用前面的
+        fonts.push(FontArc::try_from_slice(FALLBACK_CJK_FONT).unwrap());
+        assert_eq!(find_glyph(&fonts, 'A').0, 0);
+        let (font, glyph) = find_glyph(&fonts, '中');
+        assert_eq!(font, 1);
+        assert_ne!(glyph, GlyphId(0));
+        let (glyphs, _) = layout(&fonts, &section);
+        assert_eq!(glyphs[1].font, 1);
+        assert_ne!(glyphs[1].glyph, GlyphId(0));
+        // 两个字体里都没有的字符用第一个字体的缺字符号
+        assert_eq!(find_glyph(&fonts, '好'), (0, GlyphId(0)));
+    }
+
+    #[test]
+    fn full_atlas_skips_only_the_glyphs_that_do_not_fit() {
+        let Some(app) = headless::test_device_app() else {
+            return;
+        };
+        let mut text = TextRenderer::new(&app.device, headless::OFFSCREEN_FORMAT);
+        text.max_atlas_size = INITIAL_ATLAS_SIZE;
+        // 先排队的小字放得下，后面的大字母把图集撑满
+        text.queue(TextSection::new("FPS 60", Vec2::ZERO).with_size(16.0));
+        text.queue(TextSection::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", Vec2::ZERO).with_size(200.0));
+        text.prepare(&app.device, &app.queue, 64, 64);
+        // "FPS 60" 有 5 个可见字形，大字母只画出一部分
+        let drawn = text.vertex_count as usize / 6;
+        assert!(drawn >= 5, "{drawn}");
+        assert!(drawn < 5 + 26, "{drawn}");
+        assert_eq!(text.atlas_size, INITIAL_ATLAS_SIZE);
+    }
+
+    #[test]
+    fn default_fonts_cover_the_window_title() {
+        let fonts = default_fonts();
+        for c in "第二章 中文字体".chars().filter(|c| !c.is_whitespace()) {
+            assert_ne!(find_glyph(&fonts, c).1, GlyphId(0), "{c}");
+        }
+    }
+}
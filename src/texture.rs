@@ -0,0 +1,458 @@
+// 纹理子系统
+// 解码 PNG/JPEG/HDR 图片，通过 queue.write_texture 上传到 GPU，创建视图和采样器，
+// 并根据声明的绑定组布局创建绑定组。
+// 每张纹理可以选择 sRGB 或线性颜色空间：颜色贴图一般是 sRGB，法线、粗糙度等数据贴图是线性的。
+
+use std::path::Path;
+
+// 纹理数据所在的颜色空间
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    // 采样时 GPU 自动把 sRGB 编码转换到线性空间
+    Srgb,
+    // 原样读取数据
+    Linear,
+}
+
+impl ColorSpace {
+    // 让颜色贴图和渲染目标匹配：sRGB 渲染目标写入时会再编码回 sRGB，
+    // 非 sRGB 渲染目标不会编码，这时颜色贴图也按原样读取，显示出来的颜色才一致
+    pub fn matching(target_format: wgpu::TextureFormat) -> Self {
+        if target_format.is_srgb() {
+            ColorSpace::Srgb
+        } else {
+            ColorSpace::Linear
+        }
+    }
+
+    fn rgba8_format(self) -> wgpu::TextureFormat {
+        match self {
+            ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
+// 加载纹理时可能出现的错误
+#[derive(Debug)]
+pub enum TextureError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Io(e) => write!(f, "读取纹理文件失败: {e}"),
+            TextureError::Image(e) => write!(f, "解码纹理失败: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<std::io::Error> for TextureError {
+    fn from(e: std::io::Error) -> Self {
+        TextureError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(e: image::ImageError) -> Self {
+        TextureError::Image(e)
+    }
+}
+
+// GPU 上的纹理，带视图和采样器，克隆只是增加引用计数
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+}
+
+impl Texture {
+    // 从文件加载，格式根据文件内容判断
+    pub fn from_path(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+        color_space: ColorSpace,
+    ) -> Result<Self, TextureError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(
+            device,
+            queue,
+            &bytes,
+            &path.display().to_string(),
+            color_space,
+        )
+    }
+
+    // 从内存里的图片文件数据加载，一般配合 include_bytes! 使用
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+        color_space: ColorSpace,
+    ) -> Result<Self, TextureError> {
+        let img = image::load_from_memory(bytes)?;
+        Ok(Self::from_image(device, queue, &img, label, color_space))
+    }
+
+    // 从已经解码的图片创建纹理
+    // 高动态范围的图片（比如 .hdr）上传为 Rgba16Float，这时颜色空间总是线性的
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: &str,
+        color_space: ColorSpace,
+    ) -> Self {
+        let (width, height) = (img.width(), img.height());
+        match img {
+            image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_) => {
+                let pixels: Vec<half::f16> = img
+                    .to_rgba32f()
+                    .into_raw()
+                    .into_iter()
+                    .map(half::f16::from_f32)
+                    .collect();
+                Self::from_pixels(
+                    device,
+                    queue,
+                    bytemuck::cast_slice(&pixels),
+                    width,
+                    height,
+                    wgpu::TextureFormat::Rgba16Float,
+                    label,
+                )
+            }
+            _ => Self::from_pixels(
+                device,
+                queue,
+                &img.to_rgba8(),
+                width,
+                height,
+                color_space.rgba8_format(),
+                label,
+            ),
+        }
+    }
+
+    // 从紧密排列的原始像素创建纹理
+    pub fn from_pixels(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            // TEXTURE_BINDING: 在着色器里采样；COPY_DST: 作为 write_texture 的目标
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let bytes_per_pixel = format.block_copy_size(None).unwrap();
+        queue.write_texture(
+            texture.as_image_copy(),
+            pixels,
+            // write_texture 不要求每行按 256 字节对齐
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_pixel * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            // 超出 [0, 1] 的纹理坐标取边缘的颜色
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            // 放大时线性插值，缩小时取最近的像素
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    // 纯色的 1x1 纹理，可以作为缺失贴图时的默认值
+    pub fn solid_color(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rgba: [u8; 4],
+        color_space: ColorSpace,
+        label: &str,
+    ) -> Self {
+        Self::from_pixels(
+            device,
+            queue,
+            &rgba,
+            1,
+            1,
+            color_space.rgba8_format(),
+            label,
+        )
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.texture.format()
+    }
+
+    // 用 texture_sampler 布局创建绑定组：binding 0 是纹理，binding 1 是采样器
+    pub fn bind_group(&self, device: &wgpu::Device, layout: &BindingLayout) -> wgpu::BindGroup {
+        layout.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(&self.view),
+                wgpu::BindingResource::Sampler(&self.sampler),
+            ],
+        )
+    }
+}
+
+// 绑定组里一个绑定的类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BindingKind {
+    // 可采样的纹理
+    Texture {
+        sample_type: wgpu::TextureSampleType,
+        dimension: wgpu::TextureViewDimension,
+    },
+    // 采样器
+    Sampler(wgpu::SamplerBindingType),
+    // uniform 缓冲区
+    Uniform,
+    // 存储缓冲区
+    Storage {
+        read_only: bool,
+    },
+    // 存储纹理
+    StorageTexture {
+        access: wgpu::StorageTextureAccess,
+        format: wgpu::TextureFormat,
+        dimension: wgpu::TextureViewDimension,
+    },
+}
+
+impl BindingKind {
+    // 可过滤的 2D 浮点纹理，最常见的纹理绑定
+    pub const TEXTURE_2D: Self = BindingKind::Texture {
+        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        dimension: wgpu::TextureViewDimension::D2,
+    };
+
+    pub const FILTERING_SAMPLER: Self = BindingKind::Sampler(wgpu::SamplerBindingType::Filtering);
+
+    fn binding_type(self) -> wgpu::BindingType {
+        match self {
+            BindingKind::Texture {
+                sample_type,
+                dimension,
+            } => wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: dimension,
+                multisampled: false,
+            },
+            BindingKind::Sampler(ty) => wgpu::BindingType::Sampler(ty),
+            BindingKind::Uniform => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            BindingKind::Storage { read_only } => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            BindingKind::StorageTexture {
+                access,
+                format,
+                dimension,
+            } => wgpu::BindingType::StorageTexture {
+                access,
+                format,
+                view_dimension: dimension,
+            },
+        }
+    }
+}
+
+// 声明式的绑定组布局：按顺序列出每个绑定的类型和可见的着色器阶段，
+// binding 编号就是它在列表里的下标
+pub struct BindingLayout {
+    pub label: String,
+    pub entries: Vec<(wgpu::ShaderStages, BindingKind)>,
+    pub layout: wgpu::BindGroupLayout,
+}
+
+impl BindingLayout {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        entries: &[(wgpu::ShaderStages, BindingKind)],
+    ) -> Self {
+        let layout_entries: Vec<wgpu::BindGroupLayoutEntry> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, (visibility, kind))| wgpu::BindGroupLayoutEntry {
+                binding: i as u32,
+                visibility: *visibility,
+                ty: kind.binding_type(),
+                count: None,
+            })
+            .collect();
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &layout_entries,
+        });
+        Self {
+            label: label.to_string(),
+            entries: entries.to_vec(),
+            layout,
+        }
+    }
+
+    // 片元着色器里使用的 纹理 + 采样器
+    pub fn texture_sampler(device: &wgpu::Device) -> Self {
+        Self::new(
+            device,
+            "Texture Bind Group Layout",
+            &[
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::TEXTURE_2D),
+                (wgpu::ShaderStages::FRAGMENT, BindingKind::FILTERING_SAMPLER),
+            ],
+        )
+    }
+
+    // 按声明的顺序传入资源创建绑定组，资源数量必须和声明一致
+    pub fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        resources: &[wgpu::BindingResource<'_>],
+    ) -> wgpu::BindGroup {
+        assert_eq!(
+            resources.len(),
+            self.entries.len(),
+            "绑定组 {} 需要 {} 个资源",
+            self.label,
+            self.entries.len()
+        );
+        let entries: Vec<wgpu::BindGroupEntry> = resources
+            .iter()
+            .enumerate()
+            .map(|(i, resource)| wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: resource.clone(),
+            })
+            .collect();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&self.label),
+            layout: &self.layout,
+            entries: &entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::headless::{self, HeadlessOptions};
+
+    fn encode(img: image::DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        img.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn decodes_png_jpeg_and_hdr() {
+        let Some(app) = headless::test_app(HeadlessOptions::default()) else {
+            return;
+        };
+        let rgb = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            3,
+            2,
+            image::Rgb([200, 100, 50]),
+        ));
+        let cases = [
+            (
+                encode(rgb.clone(), image::ImageFormat::Png),
+                ColorSpace::Srgb,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+            ),
+            (
+                encode(rgb.clone(), image::ImageFormat::Jpeg),
+                ColorSpace::Linear,
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+            (
+                encode(
+                    image::DynamicImage::ImageRgb32F(rgb.to_rgb32f()),
+                    image::ImageFormat::Hdr,
+                ),
+                ColorSpace::Srgb,
+                wgpu::TextureFormat::Rgba16Float,
+            ),
+        ];
+        for (bytes, color_space, format) in cases {
+            let texture =
+                Texture::from_bytes(&app.device, &app.queue, &bytes, "test", color_space).unwrap();
+            assert_eq!(texture.format(), format);
+            assert_eq!((texture.texture.width(), texture.texture.height()), (3, 2));
+        }
+    }
+
+    #[test]
+    fn malformed_image_is_an_error() {
+        let Some(app) = headless::test_app(HeadlessOptions::default()) else {
+            return;
+        };
+        let result = Texture::from_bytes(
+            &app.device,
+            &app.queue,
+            b"not an image",
+            "broken",
+            ColorSpace::Srgb,
+        );
+        assert!(matches!(result, Err(TextureError::Image(_))));
+    }
+
+    #[test]
+    fn color_space_matches_target_format() {
+        assert_eq!(
+            ColorSpace::matching(wgpu::TextureFormat::Bgra8UnormSrgb),
+            ColorSpace::Srgb
+        );
+        assert_eq!(
+            ColorSpace::matching(wgpu::TextureFormat::Bgra8Unorm),
+            ColorSpace::Linear
+        );
+    }
+}